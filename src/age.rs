@@ -0,0 +1,278 @@
+use std::fmt;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use rand::Rng;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+use crate::file::{Cipher, CipherKind};
+use crate::secure::SecureBuffer;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Starts every age file.
+pub const MAGIC: &[u8] = b"age-encryption.org/v1\n";
+/// The work factor `age -p` uses.
+pub const DEFAULT_LOG_N: u8 = 18;
+/// The highest work factor accepted when decrypting, as in the reference implementation, so a
+/// file can't make unlocking take hours.
+const MAX_LOG_N: u8 = 22;
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const CIPHER: CipherKind = CipherKind::ChaCha20Poly1305;
+const FILE_KEY_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+const PAYLOAD_NONCE_LEN: usize = 16;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+/// Stanza bodies are wrapped at this many base64 characters.
+const COLUMNS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AgeError {
+    /// The file isn't a well-formed age file.
+    InvalidFormat(&'static str),
+    /// The file, or the way it is being written, needs something besides a passphrase.
+    Unsupported(&'static str),
+    /// The passphrase is wrong.
+    WrongPassphrase,
+    /// The header or the payload was modified.
+    Modified,
+    Encrypt,
+}
+
+impl fmt::Display for AgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(reason) => write!(f, "not an age file, {}", reason),
+            Self::Unsupported(what) => write!(f, "age files are only supported with a passphrase, not {}", what),
+            Self::WrongPassphrase => f.write_str("the passphrase is wrong"),
+            Self::Modified => f.write_str("the age file was modified"),
+            Self::Encrypt => f.write_str("the age file could not be encrypted")
+        }
+    }
+}
+
+impl std::error::Error for AgeError {}
+
+/// The non-secret parts of an age file, see [`read_header`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AgeHeader {
+    /// The scrypt work factor the file key is wrapped with, as a power of two.
+    pub log_n: u8,
+    salt: Vec<u8>,
+    wrapped_key: Vec<u8>,
+    mac: Vec<u8>,
+    /// The length of the part of the header the MAC covers.
+    mac_input_len: usize,
+    /// Where the header ends and the payload starts.
+    pub len: usize,
+}
+
+/// Returns `true` if `contents` starts like an age file.
+#[must_use]
+pub fn is_age(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+/// Reads the header of an age file encrypted with a passphrase.
+pub fn read_header(contents: &[u8]) -> Result<AgeHeader, AgeError> {
+    if !is_age(contents) {
+        return Err(AgeError::InvalidFormat("the version line is missing"));
+    }
+    let mut lines = Lines { contents, position: MAGIC.len() };
+    let mut scrypt = None;
+    loop {
+        let line = lines.next().ok_or(AgeError::InvalidFormat("the header is truncated"))?;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            let (log_n, salt, wrapped_key) = scrypt.ok_or(AgeError::InvalidFormat("the header has no recipients"))?;
+            // The MAC covers the header up to and including the `---`, without the space after it.
+            let mac_input_len = lines.position - mac.len() - 2;
+            return Ok(AgeHeader { log_n, salt, wrapped_key, mac: decode(mac)?, mac_input_len, len: lines.position });
+        }
+        let arguments: Vec<&str> = line.strip_prefix("-> ").ok_or(AgeError::InvalidFormat("a stanza is malformed"))?.split(' ').collect();
+        let mut body = String::new();
+        loop {
+            let body_line = lines.next().ok_or(AgeError::InvalidFormat("the header is truncated"))?;
+            // Every line but the last is exactly a full line, so each body has one encoding.
+            if body_line.len() > COLUMNS {
+                return Err(AgeError::InvalidFormat("a stanza body line is too long"));
+            }
+            body.push_str(body_line);
+            if body_line.len() < COLUMNS {
+                break;
+            }
+        }
+        match arguments.as_slice() {
+            ["scrypt", salt, work_factor] if scrypt.is_none() => {
+                let log_n = work_factor.parse::<u8>().ok()
+                    .filter(|log_n| (1..=MAX_LOG_N).contains(log_n) && log_n.to_string() == *work_factor)
+                    .ok_or(AgeError::Unsupported("this scrypt work factor"))?;
+                scrypt = Some((log_n, decode(salt)?, decode(&body)?));
+            }
+            ["scrypt", ..] => return Err(AgeError::InvalidFormat("a scrypt stanza must be the only one")),
+            _ => return Err(AgeError::Unsupported("recipients or identities"))
+        }
+        if lines.peek_is_recipient() {
+            return Err(AgeError::InvalidFormat("a scrypt stanza must be the only one"));
+        }
+    }
+}
+
+/// Encrypts `plaintext` with `passphrase`, deriving the key with scrypt at a work factor of
+/// `2^log_n`, into a file in the [age](https://age-encryption.org/v1) format that `age -d` can
+/// decrypt.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::age::{decrypt, encrypt, AgeError};
+///
+/// let encrypted = encrypt("correct horse", 10, b"secret").unwrap();
+/// assert!(encrypted.starts_with(b"age-encryption.org/v1\n-> scrypt "));
+/// assert_eq!(&*decrypt("correct horse", &encrypted).unwrap(), b"secret");
+/// assert_eq!(decrypt("battery staple", &encrypted).unwrap_err(), AgeError::WrongPassphrase);
+/// ```
+///
+pub fn encrypt(passphrase: &str, log_n: u8, plaintext: &[u8]) -> Result<Vec<u8>, AgeError> {
+    let mut rng = rand::thread_rng();
+    let mut file_key = Zeroizing::new([0_u8; FILE_KEY_LEN]);
+    rng.fill(&mut file_key[..]);
+    let salt: [u8; SALT_LEN] = rng.gen();
+    let wrapping_key = scrypt_key(passphrase, &salt, log_n)?;
+    let wrapped_key = CIPHER.encrypt(&wrapping_key[..], &[0; 12], &[], &file_key[..]).map_err(|_| AgeError::Encrypt)?;
+
+    let mut contents = MAGIC.to_vec();
+    contents.extend_from_slice(format!("-> scrypt {} {}\n", encode(&salt), log_n).as_bytes());
+    let body = encode(&wrapped_key);
+    let mut rest = body.as_str();
+    // The last line of a body is always shorter than a full line, even if that leaves it empty.
+    loop {
+        let (line, remaining) = rest.split_at(rest.len().min(COLUMNS));
+        contents.extend_from_slice(line.as_bytes());
+        contents.push(b'\n');
+        rest = remaining;
+        if line.len() < COLUMNS {
+            break;
+        }
+    }
+    contents.extend_from_slice(b"---");
+    let mac = header_mac(&file_key[..], &contents);
+    contents.extend_from_slice(format!(" {}\n", encode(&mac)).as_bytes());
+
+    let nonce: [u8; PAYLOAD_NONCE_LEN] = rng.gen();
+    contents.extend_from_slice(&nonce);
+    let payload_key = hkdf(&nonce, &file_key[..], b"payload");
+    // An empty plaintext is still one, empty, last chunk.
+    let chunks: Vec<&[u8]> = if plaintext.is_empty() { vec![&[]] } else { plaintext.chunks(CHUNK_LEN).collect() };
+    for (index, chunk) in chunks.iter().enumerate() {
+        let nonce = chunk_nonce(index, index + 1 == chunks.len());
+        contents.extend(CIPHER.encrypt(&payload_key[..], &nonce, &[], chunk).map_err(|_| AgeError::Encrypt)?);
+    }
+    Ok(contents)
+}
+
+/// Decrypts an age file encrypted with `passphrase`, such as one written by [`encrypt`] or by
+/// `age -p`.
+pub fn decrypt(passphrase: &str, contents: &[u8]) -> Result<SecureBuffer, AgeError> {
+    let header = read_header(contents)?;
+    let wrapping_key = scrypt_key(passphrase, &header.salt, header.log_n)?;
+    let file_key = Zeroizing::new(CIPHER.decrypt(&wrapping_key[..], &[0; 12], &[], &header.wrapped_key).map_err(|_| AgeError::WrongPassphrase)?);
+    if file_key.len() != FILE_KEY_LEN {
+        return Err(AgeError::InvalidFormat("the file key has the wrong length"));
+    }
+    let mut mac = new_mac(&hkdf(&[], &file_key, b"header")[..]);
+    mac.update(&contents[..header.mac_input_len]);
+    mac.verify(&header.mac).map_err(|_| AgeError::Modified)?;
+
+    let payload = &contents[header.len..];
+    if payload.len() < PAYLOAD_NONCE_LEN + TAG_LEN {
+        return Err(AgeError::Modified);
+    }
+    let (nonce, mut ciphertext) = payload.split_at(PAYLOAD_NONCE_LEN);
+    let payload_key = hkdf(nonce, &file_key, b"payload");
+    let mut plaintext = Zeroizing::new(Vec::with_capacity(ciphertext.len()));
+    let mut index = 0;
+    loop {
+        let (chunk, rest) = ciphertext.split_at(ciphertext.len().min(CHUNK_LEN + TAG_LEN));
+        let last = rest.is_empty();
+        let decrypted = Zeroizing::new(CIPHER.decrypt(&payload_key[..], &chunk_nonce(index, last), &[], chunk).map_err(|_| AgeError::Modified)?);
+        // Only an empty file ends with an empty chunk.
+        if last && decrypted.is_empty() && index > 0 {
+            return Err(AgeError::Modified);
+        }
+        plaintext.extend_from_slice(&decrypted);
+        if last {
+            return Ok(SecureBuffer::from(std::mem::take(&mut *plaintext)));
+        }
+        ciphertext = rest;
+        index += 1;
+    }
+}
+
+/// The header lines of an age file, which are all ASCII.
+struct Lines<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.contents.get(self.position..)?;
+        let len = rest.iter().position(|byte| *byte == b'\n')?;
+        let line = std::str::from_utf8(&rest[..len]).ok().filter(|line| line.is_ascii())?;
+        self.position += len + 1;
+        Some(line)
+    }
+
+    fn peek_is_recipient(&self) -> bool {
+        self.contents.get(self.position..).is_some_and(|rest| rest.starts_with(b"-> "))
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::STANDARD_NO_PAD)
+}
+
+/// Decodes base64 without padding, rejecting any other encoding of the same bytes.
+fn decode(text: &str) -> Result<Vec<u8>, AgeError> {
+    base64::decode_config(text, base64::STANDARD_NO_PAD).ok()
+        .filter(|bytes| encode(bytes) == text)
+        .ok_or(AgeError::InvalidFormat("the header has invalid base64"))
+}
+
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<Zeroizing<[u8; 32]>, AgeError> {
+    if salt.len() != SALT_LEN {
+        return Err(AgeError::InvalidFormat("the scrypt salt has the wrong length"));
+    }
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|_| AgeError::Unsupported("this scrypt work factor"))?;
+    let mut key = Zeroizing::new([0_u8; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), &[SCRYPT_LABEL, salt].concat(), &params, &mut key[..])
+        .map_err(|_| AgeError::Unsupported("this scrypt work factor"))?;
+    Ok(key)
+}
+
+fn new_mac(key: &[u8]) -> HmacSha256 {
+    // HMAC takes keys of any length.
+    HmacSha256::new_from_slice(key).unwrap_or_else(|_| unreachable!())
+}
+
+/// A 32 byte key derived with HKDF-SHA-256.
+fn hkdf(salt: &[u8], input: &[u8], info: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0_u8; 32]);
+    // HKDF-SHA-256 can expand to far more than 32 bytes.
+    Hkdf::<Sha256>::new(Some(salt), input).expand(info, &mut key[..]).unwrap_or_else(|_| unreachable!());
+    key
+}
+
+fn header_mac(file_key: &[u8], header: &[u8]) -> Vec<u8> {
+    let mut mac = new_mac(&hkdf(&[], file_key, b"header")[..]);
+    mac.update(header);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The nonce of a payload chunk: an 11 byte big-endian counter, then 1 for the last chunk.
+fn chunk_nonce(index: usize, last: bool) -> [u8; 12] {
+    let mut nonce = [0_u8; 12];
+    nonce[3..11].copy_from_slice(&(index as u64).to_be_bytes());
+    nonce[11] = u8::from(last);
+    nonce
+}
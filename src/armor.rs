@@ -0,0 +1,90 @@
+use std::fmt;
+
+/// The line before the base64 encoded file.
+pub const ARMOR_BEGIN: &str = "-----BEGIN CRYPT FILE-----";
+/// The line after the base64 encoded file.
+pub const ARMOR_END: &str = "-----END CRYPT FILE-----";
+
+/// The length of each base64 line, short enough to survive email.
+const LINE_LEN: usize = 64;
+
+#[derive(Debug)]
+pub enum ArmorError {
+    MissingBegin,
+    MissingEnd,
+    Base64(base64::DecodeError),
+}
+
+impl From<base64::DecodeError> for ArmorError {
+    fn from(error: base64::DecodeError) -> Self {
+        Self::Base64(error)
+    }
+}
+
+impl fmt::Display for ArmorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingBegin => write!(f, "missing {} line", ARMOR_BEGIN),
+            Self::MissingEnd => write!(f, "missing {} line", ARMOR_END),
+            Self::Base64(error) => write!(f, "invalid base64, {}", error)
+        }
+    }
+}
+
+impl std::error::Error for ArmorError {}
+
+/// Encodes `bytes` as base64 lines between [`ARMOR_BEGIN`] and [`ARMOR_END`], so an encrypted
+/// file can be pasted through email, chat or tickets.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::armor::{armor, dearmor};
+///
+/// let armored = armor(b"encrypted bytes");
+/// assert_eq!(armored, "-----BEGIN CRYPT FILE-----\nZW5jcnlwdGVkIGJ5dGVz\n-----END CRYPT FILE-----\n");
+/// assert_eq!(dearmor(&armored).unwrap(), b"encrypted bytes");
+/// ```
+///
+#[must_use]
+pub fn armor(bytes: &[u8]) -> String {
+    let encoded = base64::encode(bytes);
+    let mut armored = String::with_capacity(encoded.len() + encoded.len() / LINE_LEN + ARMOR_BEGIN.len() + ARMOR_END.len() + 3);
+    armored.push_str(ARMOR_BEGIN);
+    armored.push('\n');
+    for line in encoded.as_bytes().chunks(LINE_LEN) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push_str(ARMOR_END);
+    armored.push('\n');
+    armored
+}
+
+/// Decodes text written by [`armor`]. Anything before the begin line or after the end line, such
+/// as an email signature, is ignored, as is whitespace added around or within lines.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::armor::dearmor;
+///
+/// let pasted = "Here's the file:\n\n  -----BEGIN CRYPT FILE-----\n  ZW5jcnlwdGVk\n  IGJ5dGVz\n  -----END CRYPT FILE-----\nThanks";
+/// assert_eq!(dearmor(pasted).unwrap(), b"encrypted bytes");
+/// assert!(dearmor("ZW5jcnlwdGVkIGJ5dGVz").is_err());
+/// ```
+///
+pub fn dearmor(text: &str) -> Result<Vec<u8>, ArmorError> {
+    let mut lines = text.lines().map(str::trim).skip_while(|line| *line != ARMOR_BEGIN);
+    if lines.next().is_none() {
+        return Err(ArmorError::MissingBegin);
+    }
+    let mut encoded = String::with_capacity(text.len());
+    for line in lines {
+        if line == ARMOR_END {
+            return Ok(base64::decode(encoded)?);
+        }
+        encoded.extend(line.chars().filter(|c| !c.is_whitespace()));
+    }
+    Err(ArmorError::MissingEnd)
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::file::{Backups, CipherKind, Compression, KdfKind, Layout};
+use crate::policy::RulesPolicy;
+use crate::repl::{AutosavePolicy, ExpiryReminders, ExtraArguments, HistoryExclusions, HookCommands, ReplLimits, TrashPolicy};
+use crate::secret::ConfiguredSecretSource;
+
+/// The environment variable that overrides the location of the config file.
+pub const CONFIG_PATH_ENV: &str = "CRYPT_CLIENT_CONFIG";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Toml(error)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Toml(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// User settings, read from a TOML file.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::config::Config;
+/// use crypt_client::file::{CipherKind, Compression, KdfKind, Layout};
+/// use crypt_client::repl::{AutosavePolicy, ExtraArguments};
+///
+/// let config = Config::from_toml("
+/// autosave = 'on-change'
+/// copy_on_get = true
+/// cipher = 'chacha20-poly1305'
+/// kdf = 'argon2id'
+/// compression = 'none'
+/// layout = 'chunked'
+/// extra_arguments = 'warn'
+/// history_exclude = ['^crypt unlock .*prod', 'token']
+///
+/// [limits]
+/// max_open_files = 4
+/// max_entry_size = 65536
+///
+/// [expiry_reminders]
+/// within_days = 14
+///
+/// [trash]
+/// keep_days = 7
+///
+/// [backups]
+/// enabled = true
+/// directory = '/var/backups/crypt'
+/// keep_last = 20
+/// keep_days = 30
+///
+/// [hooks]
+/// post_save = 'rsync -a \"$CRYPT_FILE\" backup:vaults/'
+///
+/// [password_policy]
+/// min_length = 12
+/// require_digit = true
+/// ").unwrap();
+/// assert_eq!(config.autosave, AutosavePolicy::OnChange);
+/// assert!(config.copy_on_get);
+/// assert_eq!(config.cipher, CipherKind::ChaCha20Poly1305);
+/// assert_eq!(config.kdf, KdfKind::Argon2id);
+/// assert_eq!(config.compression, Compression::None);
+/// assert_eq!(config.layout, Layout::Chunked);
+/// assert_eq!(config.extra_arguments, ExtraArguments::Warn);
+/// assert!(config.history_exclude.is_match("crypt data vault get api-token"));
+/// assert!(config.expiry_reminders.enabled);
+/// assert_eq!(config.expiry_reminders.within_days, 14);
+/// assert!(config.trash.enabled);
+/// assert_eq!(config.trash.keep_days, 7);
+/// assert!(config.backups.enabled);
+/// assert_eq!(config.backups.keep_last, Some(20));
+/// assert!(config.hooks.post_save.is_some_and(|command| command.starts_with("rsync")));
+/// assert_eq!(config.limits.max_open_files, Some(4));
+/// assert_eq!(config.limits.max_entry_size, Some(65536));
+/// assert_eq!(config.password_policy.map(|policy| policy.min_length), Some(12));
+/// ```
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub limits: ReplLimits,
+    /// The rules new passwords must follow. Any password is accepted if this is missing.
+    pub password_policy: Option<RulesPolicy>,
+    /// The autosave policy of newly unlocked files, e.g. `"on-change"` or `"60s"`.
+    pub autosave: AutosavePolicy,
+    /// Whether `get` copies values to the clipboard instead of printing them, unless `--print` is
+    /// given.
+    pub copy_on_get: bool,
+    /// Whether unlocking a file warns about expired and expiring entries, on by default.
+    pub expiry_reminders: ExpiryReminders,
+    /// Whether `data delete` moves entries to the trash, on by default, and for how many days
+    /// they are kept there.
+    pub trash: TrashPolicy,
+    /// Whether commands followed by unknown flags or extra arguments are rejected, the default,
+    /// or run with a warning (`"warn"`).
+    pub extra_arguments: ExtraArguments,
+    /// The cipher of new files, `"aes-256-gcm"` (the default) or `"chacha20-poly1305"`.
+    pub cipher: CipherKind,
+    /// The key derivation function of new files, `"argon2id"` (the default) or `"scrypt"` in
+    /// builds with the `scrypt` feature.
+    pub kdf: KdfKind,
+    /// How new files are compressed before they are encrypted, `"none"` (the default) or
+    /// `"zstd"` in builds with the `zstd` feature.
+    pub compression: Compression,
+    /// How the payload of new files is laid out, `"single"` (the default) or `"chunked"` so a
+    /// damaged file can be partly recovered.
+    pub layout: Layout,
+    /// Whether files are backed up before they are overwritten, off by default.
+    pub backups: Backups,
+    /// Shell commands run when files are unlocked, saved or changed.
+    pub hooks: HookCommands,
+    /// Where to fetch the password of each file from, keyed by file path.
+    pub secret_sources: BTreeMap<PathBuf, ConfiguredSecretSource>,
+    /// Regexes of command lines to leave out of the prompt history, see [`HistoryExclusions`].
+    pub history_exclude: HistoryExclusions,
+}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::from_toml(std::fs::read_to_string(path)?.as_str())
+    }
+
+    /// Returns `$CRYPT_CLIENT_CONFIG` if it is set, otherwise `crypt-client/config.toml` in
+    /// `$XDG_CONFIG_HOME` or `~/.config`.
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("crypt-client").join("config.toml"))
+    }
+
+    /// Loads the config file at [`default_path`](Self::default_path), falling back to the
+    /// default config if there is no such file.
+    pub fn load_default() -> Result<Self, ConfigError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default())
+        }
+    }
+}
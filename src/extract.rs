@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use crate::file::CryptData;
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(std::io::Error),
+    /// The key can't be written inside the directory, because it is empty after the prefix, is
+    /// absolute or has an empty, `.` or `..` part.
+    UnsafeKey(String),
+    /// The key is written as a file, but another key needs it to be a directory.
+    Clash(String),
+    /// The file already exists and [`Existing::Refuse`] was asked for.
+    Exists(PathBuf),
+    /// The file, or a directory it would be written in, is a symbolic link, which could point
+    /// outside the directory.
+    Symlink(PathBuf),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::UnsafeKey(key) => write!(f, "{} can't be used as a path inside the directory", key),
+            Self::Clash(key) => write!(f, "{} would be both a file and a directory", key),
+            Self::Exists(path) => write!(f, "{} already exists", path.display()),
+            Self::Symlink(path) => write!(f, "{} is a symbolic link", path.display())
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<std::io::Error> for ExtractError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// What to do with a file that already exists, see [`ExtractOptions`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Existing {
+    /// Write nothing at all if any of the files exist.
+    Refuse,
+    /// Replace the file's contents and permissions.
+    Overwrite,
+    /// Leave the file as it is.
+    Skip,
+}
+
+/// How [`extract_all`] writes the files.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ExtractOptions {
+    /// The permissions of the files, ignored where there are no Unix permissions.
+    pub mode: u32,
+    /// The permissions of the directories it creates, ignored where there are no Unix
+    /// permissions.
+    pub dir_mode: u32,
+    pub existing: Existing,
+}
+
+impl Default for ExtractOptions {
+    /// Files and directories only the current user can open, and nothing written over.
+    fn default() -> Self {
+        Self { mode: 0o600, dir_mode: 0o700, existing: Existing::Refuse }
+    }
+}
+
+/// The files [`extract_all`] wrote and skipped, ordered by key.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ExtractReport {
+    pub written: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Writes the value of every key starting with `prefix` to a file under `dir`, at the key's path
+/// with the prefix removed, so `certs/web/server.pem` extracted with the prefix `certs/` is
+/// written to `dir/web/server.pem`. `dir` and the directories below it are created as needed.
+///
+/// Every path is checked before anything is written, so a key that would escape `dir`, a file
+/// that exists when [`Existing::Refuse`] is asked for, or a symbolic link in place of a file or a
+/// directory below `dir`, leaves the directory untouched. Symbolic links are never followed,
+/// even ones that point nowhere.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::extract::{extract_all, ExtractOptions, ExtractError};
+/// use crypt_client::file::CryptData;
+///
+/// let dir = std::env::temp_dir().join(format!("crypt-client-extract-doctest-{}", std::process::id()));
+/// let mut data = CryptData::new();
+/// data.insert("certs/web/server.pem", "-----BEGIN CERTIFICATE-----");
+/// data.insert("certs/ca.pem", "ca");
+/// data.insert("db/password", "hunter2");
+///
+/// let report = extract_all(&data, "certs/", &dir, &ExtractOptions::default()).unwrap();
+/// assert_eq!(report.written, vec![dir.join("ca.pem"), dir.join("web/server.pem")]);
+/// assert_eq!(std::fs::read_to_string(dir.join("web/server.pem")).unwrap(), "-----BEGIN CERTIFICATE-----");
+/// assert!(!dir.join("db").exists());
+///
+/// let error = extract_all(&data, "certs/", &dir, &ExtractOptions::default()).unwrap_err();
+/// assert!(matches!(error, ExtractError::Exists(_)));
+///
+/// data.insert("certs/../escape", "x");
+/// let error = extract_all(&data, "certs/", &dir, &ExtractOptions::default()).unwrap_err();
+/// assert!(matches!(error, ExtractError::UnsafeKey(_)));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+///
+pub fn extract_all(data: &CryptData, prefix: &str, dir: &Path, options: &ExtractOptions) -> Result<ExtractReport, ExtractError> {
+    let mut files = Vec::new();
+    for (key, entry) in data.entries_with_prefix(prefix) {
+        let relative = relative_path(key, prefix).ok_or_else(|| ExtractError::UnsafeKey(key.to_string()))?;
+        files.push((key, dir.join(relative), entry.value()));
+    }
+    let keys: HashMap<&Path, &str> = files.iter().map(|(key, path, _)| (path.as_path(), *key)).collect();
+    if let Some(key) = files.iter().find_map(|(_, path, _)| path.ancestors().skip(1).find_map(|parent| keys.get(parent))) {
+        return Err(ExtractError::Clash((*key).to_string()));
+    }
+    for (_, path, _) in &files {
+        if let Some(link) = find_symlink(dir, path) {
+            return Err(ExtractError::Symlink(link));
+        }
+    }
+    if options.existing == Existing::Refuse {
+        if let Some((_, path, _)) = files.iter().find(|(_, path, _)| path.symlink_metadata().is_ok()) {
+            return Err(ExtractError::Exists(path.clone()));
+        }
+    }
+
+    let mut report = ExtractReport::default();
+    for (_, path, value) in files {
+        if let Some(parent) = path.parent() {
+            create_dirs(parent, options.dir_mode)?;
+        }
+        // Checked again in case a link was put in the way since.
+        if let Some(link) = find_symlink(dir, &path) {
+            return Err(ExtractError::Symlink(link));
+        }
+        match write_file(&path, value, options) {
+            Ok(()) => report.written.push(path),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => match options.existing {
+                Existing::Skip => report.skipped.push(path),
+                _ => return Err(ExtractError::Exists(path))
+            },
+            Err(error) => return Err(error.into())
+        }
+    }
+    Ok(report)
+}
+
+/// The first of `path` and the directories between it and `dir` that is a symbolic link. `dir`
+/// itself was chosen by the caller, so it may be one.
+fn find_symlink(dir: &Path, path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .take_while(|ancestor| *ancestor != dir)
+        .find(|ancestor| ancestor.symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()))
+        .map(Path::to_path_buf)
+}
+
+/// The path of `key` below the directory, or [`None`] if it could end up anywhere else.
+fn relative_path(key: &str, prefix: &str) -> Option<PathBuf> {
+    let relative = &key[prefix.len()..];
+    // `--prefix certs` should extract `certs/ca.pem` as `ca.pem`, like `--prefix certs/`.
+    let relative = if prefix.is_empty() || prefix.ends_with('/') { relative } else { relative.strip_prefix('/').unwrap_or(relative) };
+    let parts: Vec<&str> = relative.split('/').collect();
+    let safe = parts.iter().all(|part| {
+        let mut components = Path::new(part).components();
+        matches!((components.next(), components.next()), (Some(Component::Normal(name)), None) if name == *part)
+    });
+    safe.then(|| parts.iter().collect())
+}
+
+fn create_dirs(path: &Path, mode: u32) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    builder.create(path)
+}
+
+/// Writes `value` to `path`, failing with [`AlreadyExists`](std::io::ErrorKind::AlreadyExists)
+/// if anything is there unless [`Existing::Overwrite`] is asked for. Symbolic links, which a
+/// file may have been replaced with since it was checked, are never followed.
+fn write_file(path: &Path, value: &str, extract: &ExtractOptions) -> std::io::Result<()> {
+    let mode = extract.mode;
+    let mut options = OpenOptions::new();
+    if extract.existing == Existing::Overwrite {
+        options.write(true).create(true).truncate(true);
+    } else {
+        // Doesn't follow a symbolic link either, even one that points nowhere.
+        options.write(true).create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(mode).custom_flags(libc::O_NOFOLLOW);
+        let mut file = options.open(path)?;
+        // The mode only applies to new files, and is narrowed by the umask.
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+        file.write_all(value.as_bytes())?;
+        file.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = mode;
+        let mut file = options.open(path)?;
+        file.write_all(value.as_bytes())?;
+        file.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_never_followed() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("extract-symlinks");
+        let out = dir.join("out");
+        let outside = dir.join("outside");
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let mut data = CryptData::new();
+        data.insert("ca.pem", "ca");
+        data.insert("web/server.pem", "server");
+
+        // A dangling link would otherwise be followed to create the file it points to.
+        symlink(outside.join("stolen"), out.join("ca.pem")).unwrap();
+        for existing in [Existing::Refuse, Existing::Skip, Existing::Overwrite] {
+            let options = ExtractOptions { existing, ..ExtractOptions::default() };
+            let error = extract_all(&data, "", &out, &options).unwrap_err();
+            assert!(matches!(error, ExtractError::Symlink(path) if path == out.join("ca.pem")));
+        }
+        assert!(!outside.join("stolen").exists());
+        std::fs::remove_file(out.join("ca.pem")).unwrap();
+
+        // As would a linked directory.
+        symlink(&outside, out.join("web")).unwrap();
+        let error = extract_all(&data, "", &out, &ExtractOptions::default()).unwrap_err();
+        assert!(matches!(error, ExtractError::Symlink(path) if path == out.join("web")));
+        assert!(!out.join("ca.pem").exists() && !outside.join("server.pem").exists());
+
+        // The directory asked for may be a link.
+        let linked = dir.join("linked");
+        symlink(&outside, &linked).unwrap();
+        let report = extract_all(&data, "", &linked, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.written.len(), 2);
+        assert_eq!(std::fs::read_to_string(outside.join("web/server.pem")).unwrap(), "server");
+    }
+
+    #[test]
+    fn existing_files_are_skipped_or_overwritten() {
+        let dir = TempDir::new("extract-existing");
+        let mut data = CryptData::new();
+        data.insert("a", "new");
+        data.insert("b", "new");
+        std::fs::write(dir.join("a"), "old").unwrap();
+
+        let skip = ExtractOptions { existing: Existing::Skip, ..ExtractOptions::default() };
+        let report = extract_all(&data, "", &dir, &skip).unwrap();
+        assert_eq!((report.written, report.skipped), (vec![dir.join("b")], vec![dir.join("a")]));
+        assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "old");
+
+        let overwrite = ExtractOptions { existing: Existing::Overwrite, ..ExtractOptions::default() };
+        extract_all(&data, "", &dir, &overwrite).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "new");
+    }
+}
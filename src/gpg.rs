@@ -0,0 +1,276 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use crate::secure::SecureBuffer;
+
+/// The program files are encrypted and decrypted with, looked up in `$PATH`.
+const GPG: &str = "gpg";
+/// Starts every ASCII-armored PGP message.
+const ARMOR_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+/// The PGP packet tag of a session key encrypted to a public key, which every file encrypted
+/// to a recipient starts with.
+const PUBLIC_KEY_SESSION_KEY: u8 = 1;
+/// Stands in for a recipient hidden with `gpg --throw-keyids`, as gpg itself shows them.
+pub const HIDDEN_RECIPIENT: &str = "0000000000000000";
+
+#[derive(Debug)]
+pub enum GpgError {
+    /// `gpg` couldn't be run, usually because it isn't installed.
+    Spawn(std::io::Error),
+    /// `gpg` failed, with what it printed as the reason.
+    Failed(String),
+    /// The file has no recipients to be encrypted to.
+    NoRecipients,
+    /// The file, or the way it is being written, needs something besides a public key.
+    Unsupported(&'static str),
+    /// One of the recipients was hidden when the file was written, so it can't be encrypted to
+    /// again.
+    HiddenRecipient,
+}
+
+impl fmt::Display for GpgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Spawn(error) => write!(f, "gpg could not be run, {}", error),
+            Self::Failed(reason) => write!(f, "gpg failed, {}", reason),
+            Self::NoRecipients => f.write_str("the file has no GPG recipients to be encrypted to"),
+            Self::Unsupported(what) => write!(f, "GPG files are encrypted to public keys and can't use {}", what),
+            Self::HiddenRecipient => f.write_str("a GPG recipient of the file is hidden, name every recipient to encrypt it to again")
+        }
+    }
+}
+
+impl std::error::Error for GpgError {}
+
+/// Returns `true` if `contents` is an PGP message encrypted to a public key, binary or
+/// ASCII-armored.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::gpg::is_gpg;
+///
+/// assert!(is_gpg(b"-----BEGIN PGP MESSAGE-----\n\nhQEMA...\n"));
+/// assert!(is_gpg(&[0x84, 0x04, 0x03, 0x12, 0x34, 0x56]));
+/// assert!(!is_gpg(b"age-encryption.org/v1\n"));
+/// ```
+///
+#[must_use]
+pub fn is_gpg(contents: &[u8]) -> bool {
+    if contents.starts_with(ARMOR_BEGIN.as_bytes()) {
+        return true;
+    }
+    // The packet must also be a version this parser knows, so a legacy file without a header
+    // that happens to start with the same byte isn't mistaken for one.
+    matches!(packet(contents), Some((PUBLIC_KEY_SESSION_KEY, body, _)) if matches!(body.first(), Some(3 | 6)))
+}
+
+/// Returns the IDs of the keys an PGP message is encrypted to, as uppercase hex, without
+/// decrypting it. Version 3 packets name a key ID and version 6 packets a fingerprint. Recipients
+/// hidden with `gpg --throw-keyids` are listed as [`HIDDEN_RECIPIENT`], which
+/// [`encrypt`] refuses, so the file isn't silently written without them.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::gpg::{recipients, HIDDEN_RECIPIENT};
+///
+/// let packet = [0x84, 0x0a, 0x03, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x12];
+/// assert_eq!(recipients(&packet), vec!["0123456789ABCDEF"]);
+/// let hidden = [0x84, 0x0a, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0x12];
+/// assert_eq!(recipients(&hidden), vec![HIDDEN_RECIPIENT]);
+/// ```
+///
+#[must_use]
+pub fn recipients(contents: &[u8]) -> Vec<String> {
+    let dearmored;
+    let mut rest = if contents.starts_with(ARMOR_BEGIN.as_bytes()) {
+        dearmored = dearmor(contents).unwrap_or_default();
+        dearmored.as_slice()
+    } else {
+        contents
+    };
+    let mut recipients = Vec::new();
+    while let Some((PUBLIC_KEY_SESSION_KEY, body, next)) = packet(rest) {
+        let key = match body {
+            // Version 3 packets hold the 8 byte key ID right after the version, all zeros if it
+            // was hidden.
+            [3, key_id @ ..] => key_id.get(..8).map(|key_id| if key_id.iter().all(|byte| *byte == 0) { &[][..] } else { key_id }),
+            // Version 6 packets hold the length of the key version and fingerprint, then them,
+            // with a length of 0 if they were hidden.
+            [6, len, rest @ ..] => match usize::from(*len) {
+                0 => Some(&[][..]),
+                len => rest.get(1..len)
+            },
+            _ => None
+        };
+        match key {
+            Some([]) => recipients.push(HIDDEN_RECIPIENT.to_string()),
+            Some(key) => recipients.push(to_hex(key)),
+            None => {}
+        }
+        rest = next;
+    }
+    recipients
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{:02X}", byte);
+        hex
+    })
+}
+
+/// Encrypts `plaintext` to each of `recipients`, anything `gpg --recipient` accepts such as a
+/// key ID, fingerprint or email address. Their public keys must already be in the keyring. Keys
+/// named by their full fingerprint are used whether or not they are trusted, as no other key
+/// can have it, the others must be trusted in the keyring.
+pub fn encrypt(recipients: &[String], plaintext: &[u8]) -> Result<Vec<u8>, GpgError> {
+    Ok(run(&encrypt_args(recipients)?, plaintext)?.to_vec())
+}
+
+fn encrypt_args(recipients: &[String]) -> Result<Vec<&str>, GpgError> {
+    if recipients.is_empty() {
+        return Err(GpgError::NoRecipients);
+    }
+    if recipients.iter().any(|recipient| recipient == HIDDEN_RECIPIENT) {
+        return Err(GpgError::HiddenRecipient);
+    }
+    let mut args = Vec::new();
+    if recipients.iter().all(|recipient| is_fingerprint(recipient)) {
+        args.extend(["--trust-model", "always"].iter());
+    }
+    args.push("--encrypt");
+    for recipient in recipients {
+        args.extend(["--recipient", recipient.as_str()].iter());
+    }
+    Ok(args)
+}
+
+/// Returns `true` if `recipient` is the full fingerprint of a version 4 or 6 key, which no other
+/// key can share, unlike a key ID or an email address.
+fn is_fingerprint(recipient: &str) -> bool {
+    let hex = recipient.strip_prefix("0x").unwrap_or(recipient);
+    matches!(hex.len(), 40 | 64) && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Decrypts an PGP message with a secret key from the keyring. gpg-agent asks for the
+/// passphrase of the key if it needs one.
+pub fn decrypt(contents: &[u8]) -> Result<SecureBuffer, GpgError> {
+    run(&["--decrypt"], contents)
+}
+
+/// Runs `gpg` with `args`, writing `input` to it and returning what it wrote.
+fn run(args: &[&str], input: &[u8]) -> Result<SecureBuffer, GpgError> {
+    let mut child = Command::new(GPG)
+        .args(["--batch", "--yes", "--quiet"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(GpgError::Spawn)?;
+    let mut stdin = child.stdin.take().ok_or_else(|| GpgError::Failed("its input could not be opened".to_string()))?;
+    // The input is written while the output is read, so neither fills a pipe and blocks gpg.
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            // gpg fails with a reason of its own if it stops reading early.
+            let _ = stdin.write_all(input);
+        });
+        child.wait_with_output()
+    }).map_err(GpgError::Spawn)?;
+    let stdout = SecureBuffer::from(output.stdout);
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).trim().replace('\n', "; ");
+        return Err(GpgError::Failed(if reason.is_empty() { output.status.to_string() } else { reason }));
+    }
+    Ok(stdout)
+}
+
+/// Splits the first PGP packet off `contents`, returning its tag, its body and what follows
+/// it. Packets with partial lengths, which session key packets never have, are not read.
+fn packet(contents: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&first, rest) = contents.split_first()?;
+    if first & 0x80 == 0 {
+        return None;
+    }
+    let (tag, len, rest) = if first & 0x40 == 0 {
+        let (len, rest) = match first & 0x03 {
+            0 => (usize::from(*rest.first()?), rest.get(1..)?),
+            1 => (usize::from(u16::from_be_bytes([*rest.first()?, *rest.get(1)?])), rest.get(2..)?),
+            2 => (be_u32(rest.get(..4)?)?, rest.get(4..)?),
+            _ => return None,
+        };
+        ((first >> 2) & 0x0f, len, rest)
+    } else {
+        let (len, rest) = match *rest.first()? {
+            len @ 0..=191 => (usize::from(len), rest.get(1..)?),
+            len @ 192..=223 => (((usize::from(len) - 192) << 8) + usize::from(*rest.get(1)?) + 192, rest.get(2..)?),
+            255 => (be_u32(rest.get(1..5)?)?, rest.get(5..)?),
+            _ => return None,
+        };
+        (first & 0x3f, len, rest)
+    };
+    Some((tag, rest.get(..len)?, rest.get(len..)?))
+}
+
+fn be_u32(bytes: &[u8]) -> Option<usize> {
+    usize::try_from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok()
+}
+
+/// Decodes the base64 body of an ASCII-armored message, between the blank line ending the armor
+/// headers and the checksum.
+fn dearmor(contents: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(contents).ok()?;
+    let body: String = text.lines()
+        .skip(1)
+        .skip_while(|line| !line.trim().is_empty())
+        .take_while(|line| !line.starts_with('=') && !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    base64::decode(body).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A public key encrypted session key packet with a new format header around `body`.
+    fn session_key_packet(body: &[u8]) -> Vec<u8> {
+        [&[0xc1, u8::try_from(body.len()).unwrap()][..], body].concat()
+    }
+
+    #[test]
+    fn recipients_of_each_packet_version() {
+        let fingerprint = [0xab_u8; 32];
+        let contents = [
+            session_key_packet(&[3, 1, 2, 3, 4, 5, 6, 7, 8, 18]),
+            session_key_packet(&[3, 0, 0, 0, 0, 0, 0, 0, 0, 18]),
+            session_key_packet(&[&[6, 33, 6][..], &fingerprint, &[18]].concat()),
+            session_key_packet(&[6, 0, 18]),
+        ].concat();
+        assert!(is_gpg(&contents));
+        assert_eq!(recipients(&contents), vec![
+            "0102030405060708".to_string(),
+            HIDDEN_RECIPIENT.to_string(),
+            "AB".repeat(32),
+            HIDDEN_RECIPIENT.to_string(),
+        ]);
+
+        let armored = format!("{}\nVersion: test\n\n{}\n=abcd\n-----END PGP MESSAGE-----\n", ARMOR_BEGIN, base64::encode(&contents));
+        assert_eq!(recipients(armored.as_bytes()).len(), 4);
+    }
+
+    #[test]
+    fn only_fingerprints_skip_the_trust_model() {
+        let fingerprint = "0123456789ABCDEF0123456789ABCDEF01234567".to_string();
+        let pinned = [fingerprint.clone()];
+        assert_eq!(encrypt_args(&pinned).unwrap(), ["--trust-model", "always", "--encrypt", "--recipient", fingerprint.as_str()]);
+        let mixed = [fingerprint.clone(), "alice@example.com".to_string()];
+        assert_eq!(encrypt_args(&mixed).unwrap()[0], "--encrypt");
+        assert!(matches!(encrypt_args(&[]), Err(GpgError::NoRecipients)));
+        assert!(matches!(encrypt_args(&[fingerprint, HIDDEN_RECIPIENT.to_string()]), Err(GpgError::HiddenRecipient)));
+    }
+}
@@ -0,0 +1,726 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+#[cfg(feature = "gpg")]
+use std::path::Path;
+use zeroize::Zeroizing;
+use serde::Deserialize;
+use crate::file::CryptData;
+#[cfg(feature = "gpg")]
+use crate::gpg::GpgError;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    /// The header row has no column with this name.
+    UnknownColumn(String),
+    /// A row, by line number, has too few columns for the ones being imported.
+    MissingColumn(u64),
+    /// The file isn't laid out like the format being imported, at this line if it is known. The
+    /// details aren't kept, as they can quote the values.
+    Invalid(Option<u64>),
+    /// The export is encrypted, and can only be read by the program that wrote it.
+    Encrypted,
+    /// The export holds more than this many bytes of data.
+    TooLarge(u64),
+    /// The entry of a password store, by key, couldn't be decrypted.
+    #[cfg(feature = "gpg")]
+    Gpg(String, GpgError),
+    /// The entry, by key, was decrypted but isn't text.
+    #[cfg(feature = "gpg")]
+    NotText(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::UnknownColumn(name) => write!(f, "the header row has no column named {}", name),
+            Self::MissingColumn(line) => write!(f, "line {} has too few columns", line),
+            Self::Invalid(Some(line)) => write!(f, "the file is malformed at line {}", line),
+            Self::Invalid(None) => f.write_str("the file is malformed"),
+            Self::Encrypted => f.write_str("the export is encrypted, export it again unencrypted"),
+            Self::TooLarge(max) => write!(f, "the export holds more than {} MiB of data", max / (1024 * 1024)),
+            #[cfg(feature = "gpg")]
+            Self::Gpg(key, error) => write!(f, "{} could not be decrypted, {}", key, error),
+            #[cfg(feature = "gpg")]
+            Self::NotText(key) => write!(f, "{} is not text", key)
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<csv::Error> for ImportError {
+    fn from(error: csv::Error) -> Self {
+        let line = error.position().map(csv::Position::line);
+        match error.into_kind() {
+            csv::ErrorKind::Io(error) => Self::Io(error),
+            _ => Self::Invalid(line)
+        }
+    }
+}
+
+/// A column of a CSV file, see [`CsvOptions`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CsvColumn {
+    /// The column at this position, counting from 0.
+    Index(usize),
+    /// The column with this name in the header row.
+    Name(String),
+}
+
+/// Which columns of a CSV file hold the keys and values.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CsvOptions {
+    pub key: CsvColumn,
+    pub value: CsvColumn,
+    /// Whether the first row names the columns rather than holding an entry.
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    /// Keys in the first column and values in the second, below a header row.
+    fn default() -> Self {
+        Self { key: CsvColumn::Index(0), value: CsvColumn::Index(1), header: true }
+    }
+}
+
+/// Reads the entries of a CSV file, such as a spreadsheet or another password manager exports,
+/// taking each key and value from the columns `options` selects. Rows with an empty key are
+/// skipped, and a key that appears more than once keeps its last value.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::import::{read_csv, CsvColumn, CsvOptions};
+///
+/// let csv = "name,url,password\ngithub,https://github.com,hunter2\n,,\n";
+/// let options = CsvOptions { key: CsvColumn::Name("name".to_string()), value: CsvColumn::Index(2), header: true };
+/// let data = read_csv(csv.as_bytes(), &options).unwrap();
+/// assert_eq!(data.iter().collect::<Vec<_>>(), vec![("github", "hunter2")]);
+/// ```
+///
+pub fn read_csv(reader: impl Read, options: &CsvOptions) -> Result<CryptData, ImportError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(options.header)
+        .flexible(true)
+        .from_reader(reader);
+    let key = column_index(&mut reader, &options.key)?;
+    let value = column_index(&mut reader, &options.value)?;
+    let mut data = CryptData::new();
+    let mut record = csv::StringRecord::new();
+    while reader.read_record(&mut record)? {
+        let line = record.position().map_or(0, csv::Position::line);
+        match (record.get(key), record.get(value)) {
+            (Some(""), _) => {}
+            (Some(key), Some(value)) => {
+                data.insert(key, value);
+            }
+            _ => return Err(ImportError::MissingColumn(line))
+        }
+    }
+    Ok(data)
+}
+
+/// Finds the position of `column`, looking names up in the header row.
+fn column_index<R: Read>(reader: &mut csv::Reader<R>, column: &CsvColumn) -> Result<usize, ImportError> {
+    match column {
+        CsvColumn::Index(index) => Ok(*index),
+        CsvColumn::Name(name) => {
+            let headers = if reader.has_headers() { Some(reader.headers()?) } else { None };
+            // Spreadsheets often start the file with a byte order mark.
+            headers.and_then(|headers| headers.iter().position(|header| header.trim_start_matches('\u{feff}').trim() == name))
+                .ok_or_else(|| ImportError::UnknownColumn(name.clone()))
+        }
+    }
+}
+
+/// Reads the saved passwords Chrome, Edge and Firefox export as CSV, recognized by their `url`,
+/// `username` and `password` columns. Each login is stored under its `name` column, or the host
+/// of its URL where there is none as in Firefox exports, as `<name>/<field>` for the fields
+/// `url`, `username`, `password` and `notes` it has. Logins with the same name as an earlier one
+/// get ` (2)`, ` (3)` and so on after it, and the other columns are ignored.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::import::read_browser_csv;
+///
+/// let chrome = "name,url,username,password\ngithub.com,https://github.com/login,me,hunter2\ngithub.com,https://github.com/,work,correct horse\n";
+/// let data = read_browser_csv(chrome.as_bytes()).unwrap();
+/// assert_eq!(data.iter().collect::<Vec<_>>(), vec![
+///     ("github.com (2)/password", "correct horse"),
+///     ("github.com (2)/url", "https://github.com/"),
+///     ("github.com (2)/username", "work"),
+///     ("github.com/password", "hunter2"),
+///     ("github.com/url", "https://github.com/login"),
+///     ("github.com/username", "me"),
+/// ]);
+///
+/// let firefox = "\"url\",\"username\",\"password\",\"httpRealm\"\n\"https://example.com:8443/a\",\"\",\"pw\",\"\"\n";
+/// let data = read_browser_csv(firefox.as_bytes()).unwrap();
+/// assert_eq!(data.iter().collect::<Vec<_>>(), vec![("example.com/password", "pw"), ("example.com/url", "https://example.com:8443/a")]);
+/// ```
+///
+pub fn read_browser_csv(reader: impl Read) -> Result<CryptData, ImportError> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let column = |reader: &mut csv::Reader<_>, name: &str| column_index(reader, &CsvColumn::Name(name.to_string()));
+    let url = column(&mut reader, "url")?;
+    let username = column(&mut reader, "username")?;
+    let password = column(&mut reader, "password")?;
+    let name = column(&mut reader, "name").ok();
+    // Chrome calls it `note`, other browsers `notes`.
+    let notes = column(&mut reader, "note").or_else(|_| column(&mut reader, "notes")).ok();
+
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut data = CryptData::new();
+    let mut record = csv::StringRecord::new();
+    while reader.read_record(&mut record)? {
+        let line = record.position().map_or(0, csv::Position::line);
+        let field = |index: usize| record.get(index).ok_or(ImportError::MissingColumn(line));
+        let (url, username, password) = (field(url)?, field(username)?, field(password)?);
+        let notes = notes.and_then(|index| record.get(index)).unwrap_or_default();
+        let name = name.and_then(|index| record.get(index)).filter(|name| !name.is_empty()).unwrap_or_else(|| url_host(url));
+        let prefix = item_prefix(&mut names, None, name);
+        for (field, value) in [("url", url), ("username", username), ("password", password), ("notes", notes)] {
+            if !value.is_empty() {
+                data.insert(format!("{}/{}", prefix, field), value);
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// The host of `url`, without the scheme, credentials, port or path.
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(&['/', '?', '#'][..]).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    // Leave IPv6 addresses such as `[::1]:8080` in their brackets.
+    match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => &host[..colon],
+        _ => host
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            return Self::Io(error.into());
+        }
+        Self::Invalid(u64::try_from(error.line()).ok())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    password_protected: bool,
+    #[serde(default)]
+    folders: Vec<BitwardenFolder>,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    name: String,
+    folder_id: Option<String>,
+    notes: Option<String>,
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+/// The `type` of Bitwarden logins.
+const BITWARDEN_LOGIN: u8 = 1;
+/// The `type` of Bitwarden secure notes.
+const BITWARDEN_NOTE: u8 = 2;
+
+/// Reads the logins and secure notes of an unencrypted Bitwarden JSON export. Each item is
+/// stored under its folder and name, as `<folder>/<name>/<field>` for the fields `username`,
+/// `password`, `totp`, `uri` and `notes` it has. Further URIs are stored as `uri2`, `uri3` and
+/// so on, and items with the same name as an earlier one in the folder get ` (2)`, ` (3)` and
+/// so on after it. Cards and identities are skipped.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::import::read_bitwarden;
+///
+/// let export = r#"{
+///     "encrypted": false,
+///     "folders": [{"id": "f1", "name": "Work"}],
+///     "items": [
+///         {"type": 1, "name": "GitHub", "folderId": "f1", "notes": null,
+///          "login": {"username": "me", "password": "hunter2", "uris": [{"uri": "https://github.com"}]}},
+///         {"type": 2, "name": "Wi-Fi", "folderId": null, "notes": "battery staple"}
+///     ]
+/// }"#;
+/// let data = read_bitwarden(export.as_bytes()).unwrap();
+/// assert_eq!(data.iter().collect::<Vec<_>>(), vec![
+///     ("Wi-Fi/notes", "battery staple"),
+///     ("Work/GitHub/password", "hunter2"),
+///     ("Work/GitHub/uri", "https://github.com"),
+///     ("Work/GitHub/username", "me"),
+/// ]);
+/// ```
+///
+pub fn read_bitwarden(reader: impl Read) -> Result<CryptData, ImportError> {
+    let export: BitwardenExport = serde_json::from_reader(reader)?;
+    if export.encrypted || export.password_protected {
+        return Err(ImportError::Encrypted);
+    }
+    let folders: HashMap<&str, &str> = export.folders.iter().map(|folder| (folder.id.as_str(), folder.name.as_str())).collect();
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut data = CryptData::new();
+    for item in export.items.into_iter().filter(|item| item.kind == BITWARDEN_LOGIN || item.kind == BITWARDEN_NOTE) {
+        let folder = item.folder_id.as_deref().and_then(|id| folders.get(id).copied());
+        let prefix = item_prefix(&mut names, folder, &item.name);
+
+        let mut fields = Vec::new();
+        if let Some(login) = item.login {
+            fields.extend([("username".to_string(), login.username), ("password".to_string(), login.password), ("totp".to_string(), login.totp)]);
+            let uris = login.uris.unwrap_or_default().into_iter().filter_map(|uri| uri.uri);
+            fields.extend(uris.enumerate().map(|(index, uri)| (if index == 0 { "uri".to_string() } else { format!("uri{}", index + 1) }, Some(uri))));
+        }
+        fields.push(("notes".to_string(), item.notes));
+        for (field, value) in fields {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                data.insert(format!("{}/{}", prefix, field), value);
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(feature = "onepassword")]
+impl From<zip::result::ZipError> for ImportError {
+    fn from(error: zip::result::ZipError) -> Self {
+        match error {
+            zip::result::ZipError::Io(error) => Self::Io(error),
+            _ => Self::Invalid(None)
+        }
+    }
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxExport {
+    #[serde(default)]
+    accounts: Vec<OnePuxAccount>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxAccount {
+    #[serde(default)]
+    vaults: Vec<OnePuxVault>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxVault {
+    attrs: OnePuxVaultAttrs,
+    #[serde(default)]
+    items: Vec<OnePuxItem>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxVaultAttrs {
+    #[serde(default)]
+    name: String,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxItem {
+    /// Set by older exports for items in the trash, newer ones use [`state`](Self::state).
+    #[serde(default)]
+    trashed: bool,
+    /// `active`, `archived` or `deleted`.
+    state: Option<String>,
+    #[serde(default)]
+    details: OnePuxDetails,
+    #[serde(default)]
+    overview: OnePuxOverview,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct OnePuxDetails {
+    #[serde(default)]
+    login_fields: Vec<OnePuxLoginField>,
+    notes_plain: Option<String>,
+    password: Option<String>,
+    #[serde(default)]
+    sections: Vec<OnePuxSection>,
+    document_attributes: Option<serde_json::Value>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxLoginField {
+    value: Option<String>,
+    designation: Option<String>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxSection {
+    #[serde(default)]
+    fields: Vec<OnePuxField>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize)]
+struct OnePuxField {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    id: String,
+    /// An object with a single member named after the kind of value, such as
+    /// `{"concealed": "hunter2"}`.
+    #[serde(default)]
+    value: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "onepassword")]
+#[derive(Deserialize, Default)]
+struct OnePuxOverview {
+    #[serde(default)]
+    title: String,
+    url: Option<String>,
+}
+
+/// The entries read by [`read_1pux`], and what couldn't be translated.
+#[cfg(feature = "onepassword")]
+#[derive(Clone, Default)]
+pub struct OnePuxImport {
+    pub data: CryptData,
+    /// A line for each item or field that wasn't imported, naming it and saying why.
+    pub skipped: Vec<String>,
+}
+
+/// Reads the items of a 1Password 1PUX export, the zip archive 1Password writes with "Export"
+/// and the 1PUX format. Each item is stored under its vault and title, as
+/// `<vault>/<title>/<field>`. The fields are `username`, `password`, `uri` and `notes`, and the
+/// fields of the item's sections by their titles. Items with the same title as an earlier one
+/// in the vault get ` (2)`, ` (3)` and so on after it.
+///
+/// Anything that can't be stored as text, such as an attached file or a field holding an
+/// address, is left out and listed in [`OnePuxImport::skipped`], rather than failing the whole
+/// import. So are archived items and items in the trash.
+#[cfg(feature = "onepassword")]
+pub fn read_1pux(reader: impl Read + std::io::Seek) -> Result<OnePuxImport, ImportError> {
+    // Attachments are kept in files of their own, so this is only the text of the items.
+    const MAX_EXPORT_DATA: u64 = 256 * 1024 * 1024;
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let entry = archive.by_name("export.data")?;
+    if entry.size() > MAX_EXPORT_DATA {
+        return Err(ImportError::TooLarge(MAX_EXPORT_DATA));
+    }
+    // The size in the archive can't be trusted, so the decompressed data is limited as well.
+    let mut json = Zeroizing::new(Vec::new());
+    entry.take(MAX_EXPORT_DATA + 1).read_to_end(&mut json)?;
+    if json.len() as u64 > MAX_EXPORT_DATA {
+        return Err(ImportError::TooLarge(MAX_EXPORT_DATA));
+    }
+    let export: OnePuxExport = serde_json::from_slice(&json)?;
+    let mut import = OnePuxImport::default();
+    let mut names = HashMap::new();
+    for vault in export.accounts.into_iter().flat_map(|account| account.vaults) {
+        for item in vault.items {
+            let state = if item.trashed { Some("deleted") } else { item.state.as_deref() };
+            let left_out = match state {
+                Some("archived") => Some("it is archived"),
+                Some("deleted") => Some("it is in the trash"),
+                _ => None
+            };
+            if let Some(reason) = left_out {
+                let title = if item.overview.title.is_empty() { "untitled" } else { item.overview.title.as_str() };
+                import.skipped.push(if vault.attrs.name.is_empty() { format!("{}, {}", title, reason) } else { format!("{}/{}, {}", vault.attrs.name, title, reason) });
+                continue;
+            }
+            let prefix = item_prefix(&mut names, Some(vault.attrs.name.as_str()).filter(|name| !name.is_empty()), &item.overview.title);
+            let before = (import.data.len(), import.skipped.len());
+            read_1pux_item(item, &prefix, &mut import);
+            if (import.data.len(), import.skipped.len()) == before {
+                import.skipped.push(format!("{}, nothing in it could be imported", prefix));
+            }
+        }
+    }
+    Ok(import)
+}
+
+/// Adds the fields of `item` to `import` under `prefix`.
+#[cfg(feature = "onepassword")]
+fn read_1pux_item(item: OnePuxItem, prefix: &str, import: &mut OnePuxImport) {
+    let OnePuxDetails { login_fields, notes_plain, password, sections, document_attributes } = item.details;
+    let mut fields = Vec::new();
+    for field in login_fields {
+        if let (Some(designation @ ("username" | "password")), Some(value)) = (field.designation.as_deref(), field.value) {
+            fields.push((designation.to_string(), value));
+        }
+    }
+    fields.extend(password.map(|password| ("password".to_string(), password)));
+    fields.extend(item.overview.url.map(|url| ("uri".to_string(), url)));
+    fields.extend(notes_plain.map(|notes| ("notes".to_string(), notes)));
+    if document_attributes.is_some() {
+        import.skipped.push(format!("{}, its attached file isn't imported", prefix));
+    }
+    for field in sections.into_iter().flat_map(|section| section.fields) {
+        let name = if field.title.is_empty() { field.id } else { field.title };
+        match field.value.into_iter().next().map(|(_, value)| value) {
+            Some(serde_json::Value::String(value)) => fields.push((name, value)),
+            Some(serde_json::Value::Number(value)) => fields.push((name, value.to_string())),
+            Some(serde_json::Value::Null) | None => {}
+            Some(_) => import.skipped.push(format!("{}/{}, the kind of value isn't supported", prefix, name))
+        }
+    }
+    for (name, value) in fields.into_iter().filter(|(_, value)| !value.is_empty()) {
+        let key = format!("{}/{}", prefix, name);
+        if import.data.contains_key(&key) {
+            import.skipped.push(format!("{}, another field of the item has the same name", key));
+        } else {
+            import.data.insert(key, value);
+        }
+    }
+}
+
+/// The key prefix of an item named `name` in `folder`, numbered if an earlier item in the folder
+/// has the same name.
+fn item_prefix(names: &mut HashMap<String, usize>, folder: Option<&str>, name: &str) -> String {
+    let name = if name.is_empty() { "untitled" } else { name };
+    let path = folder.map_or_else(|| name.to_string(), |folder| format!("{}/{}", folder, name));
+    let count = names.entry(path.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 { path } else { format!("{} ({})", path, count) }
+}
+
+/// Reads the variables of a dotenv file, one `KEY=VALUE` per line. Blank lines, lines starting
+/// with `#` and comments after unquoted values are ignored, and a leading `export` is allowed.
+/// Values in single quotes are taken as they are, values in double quotes may use `\n`, `\t`,
+/// `\"` and `\\` escapes, and both may span several lines. `${VAR}` references aren't expanded.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::import::read_dotenv;
+///
+/// let dotenv = "# database\nexport DB_USER=admin # not secret\nDB_PASSWORD='p#ss word'\nCERT=\"line 1\nline 2\"\n";
+/// let data = read_dotenv(dotenv.as_bytes()).unwrap();
+/// assert_eq!(data.iter().collect::<Vec<_>>(), vec![
+///     ("CERT", "line 1\nline 2"),
+///     ("DB_PASSWORD", "p#ss word"),
+///     ("DB_USER", "admin"),
+/// ]);
+/// ```
+///
+pub fn read_dotenv(mut reader: impl Read) -> Result<CryptData, ImportError> {
+    let mut text = Zeroizing::new(String::new());
+    reader.read_to_string(&mut text)?;
+    let mut data = CryptData::new();
+    let mut lines = (1_u64..).zip(text.lines());
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+        let (key, rest) = line.split_once('=').ok_or(ImportError::Invalid(Some(number)))?;
+        let key = key.trim_end();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(ImportError::Invalid(Some(number)));
+        }
+        let trimmed = rest.trim_start();
+        let value = if let Some(quote @ ('"' | '\'')) = trimmed.chars().next() {
+            let mut quoted = Zeroizing::new(trimmed[1..].to_string());
+            let end = loop {
+                if let Some(end) = closing_quote(&quoted, quote) {
+                    break end;
+                }
+                let (_, next) = lines.next().ok_or(ImportError::Invalid(Some(number)))?;
+                quoted.push('\n');
+                quoted.push_str(next);
+            };
+            let trailing = quoted[end + 1..].trim_start();
+            if !trailing.is_empty() && !trailing.starts_with('#') {
+                return Err(ImportError::Invalid(Some(number)));
+            }
+            if quote == '"' { unescape(&quoted[..end]) } else { quoted[..end].to_string() }
+        } else {
+            // Looked for before trimming, so `KEY= # comment` is empty rather than `# comment`.
+            let comment = rest.char_indices().find(|(index, c)| *c == '#' && rest[..*index].ends_with(char::is_whitespace));
+            rest[..comment.map_or(rest.len(), |(index, _)| index)].trim().to_string()
+        };
+        data.insert(key, value);
+    }
+    Ok(data)
+}
+
+/// Finds the quote ending a value that started with `quote`. Double quotes can be escaped.
+fn closing_quote(quoted: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replaces the escapes of a double-quoted value, leaving unknown ones as they are.
+fn unescape(quoted: &str) -> String {
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(c @ ('"' | '\\' | '$')) => value.push(c),
+            Some(c) => {
+                value.push('\\');
+                value.push(c);
+            }
+            None => value.push('\\')
+        }
+    }
+    value
+}
+
+/// Reads the entries of a [pass](https://www.passwordstore.org/) password store, decrypting each
+/// `.gpg` file below `dir` with gpg. Each entry is stored under its path in the store without
+/// the extension, such as `email/work`, with the whole of what `pass show` prints as its value
+/// but for the final newline. Hidden files and directories, such as `.git` and `.gpg-id`, are
+/// skipped.
+///
+/// gpg-agent asks for the passphrase of the secret key if it needs one, usually only once.
+#[cfg(feature = "gpg")]
+pub fn read_pass_store(dir: &Path) -> Result<CryptData, ImportError> {
+    let mut data = CryptData::new();
+    read_pass_dir(dir, "", &mut data)?;
+    Ok(data)
+}
+
+/// Adds the entries below `dir`, whose keys start with `prefix`, to `data`.
+#[cfg(feature = "gpg")]
+fn read_pass_dir(dir: &Path, prefix: &str, data: &mut CryptData) -> Result<(), ImportError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str().filter(|name| !name.starts_with('.')) else {
+            continue;
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            read_pass_dir(&entry.path(), &format!("{}{}/", prefix, name), data)?;
+        } else if let Some(name) = name.strip_suffix(".gpg").filter(|_| file_type.is_file()) {
+            let key = format!("{}{}", prefix, name);
+            let contents = std::fs::read(entry.path())?;
+            let decrypted = crate::gpg::decrypt(&contents).map_err(|error| ImportError::Gpg(key.clone(), error))?;
+            let value = std::str::from_utf8(&decrypted).map_err(|_| ImportError::NotText(key.clone()))?;
+            data.insert(key, value.strip_suffix('\n').unwrap_or(value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "onepassword")]
+    #[test]
+    fn onepux_reports_what_it_skips() {
+        use std::io::{Cursor, Write};
+
+        let export = serde_json::json!({"accounts": [{"vaults": [{
+            "attrs": {"name": "Personal"},
+            "items": [
+                {"state": "active", "overview": {"title": "Mail", "url": "https://mail.example"}, "details": {
+                    "loginFields": [{"designation": "username", "value": "me"}, {"designation": "password", "value": "hunter2"}],
+                    "sections": [{"fields": [{"title": "Home", "value": {"address": {"city": "Leeds"}}}]}]
+                }},
+                {"state": "archived", "overview": {"title": "Old"}, "details": {"password": "stale"}},
+                {"trashed": true, "overview": {"title": "Gone"}, "details": {"password": "deleted"}},
+                {"state": "deleted", "overview": {"title": "Binned"}, "details": {"password": "deleted"}},
+                {"overview": {"title": "Scan"}, "details": {"documentAttributes": {"fileName": "scan.pdf"}}},
+                {"overview": {"title": "Empty"}}
+            ]
+        }]}]});
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("export.data", zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored)).unwrap();
+        zip.write_all(export.to_string().as_bytes()).unwrap();
+        let archive = zip.finish().unwrap();
+
+        let import = read_1pux(Cursor::new(archive.into_inner())).unwrap();
+        assert_eq!(import.data.iter().collect::<Vec<_>>(), vec![
+            ("Personal/Mail/password", "hunter2"),
+            ("Personal/Mail/uri", "https://mail.example"),
+            ("Personal/Mail/username", "me"),
+        ]);
+        assert_eq!(import.skipped, vec![
+            "Personal/Mail/Home, the kind of value isn't supported",
+            "Personal/Old, it is archived",
+            "Personal/Gone, it is in the trash",
+            "Personal/Binned, it is in the trash",
+            "Personal/Scan, its attached file isn't imported",
+            "Personal/Empty, nothing in it could be imported",
+        ]);
+    }
+
+    #[test]
+    fn dotenv_comments_after_empty_values() {
+        let dotenv = "EMPTY= # filled in by CI\nBARE=\nHASH=#not-a-comment\nSPACED=  value  # note\n";
+        let data = read_dotenv(dotenv.as_bytes()).unwrap();
+        assert_eq!(data.iter().collect::<Vec<_>>(), vec![
+            ("BARE", ""),
+            ("EMPTY", ""),
+            ("HASH", "#not-a-comment"),
+            ("SPACED", "value"),
+        ]);
+    }
+}
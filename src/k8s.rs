@@ -0,0 +1,85 @@
+use std::fmt;
+use std::fmt::Write;
+
+/// The longest name Kubernetes accepts for a secret or a key in one.
+const MAX_NAME_LEN: usize = 253;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum K8sError {
+    /// The secret name isn't a lowercase DNS subdomain name.
+    InvalidName(String),
+    /// The key can't be used as a key of a secret, which only allows letters, digits, `-`, `_`
+    /// and `.`.
+    InvalidKey(String),
+}
+
+impl fmt::Display for K8sError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "'{}' is not a valid secret name, use lowercase letters, digits, '-' and '.'", name),
+            Self::InvalidKey(key) => write!(f, "'{}' is not a valid secret key, use letters, digits, '-', '_' and '.'", key)
+        }
+    }
+}
+
+impl std::error::Error for K8sError {}
+
+/// Renders `entries` as a YAML manifest of an `Opaque` `v1/Secret` called `name`, with the
+/// values base64 encoded under `data`, ready for `kubectl apply -f -`.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::k8s::{secret_manifest, K8sError};
+///
+/// let manifest = secret_manifest("app-secrets", vec![("DB_PASSWORD", "hunter2")]).unwrap();
+/// assert_eq!(manifest, "\
+/// apiVersion: v1
+/// kind: Secret
+/// metadata:
+///   name: app-secrets
+/// type: Opaque
+/// data:
+///   DB_PASSWORD: aHVudGVyMg==
+/// ");
+/// assert_eq!(secret_manifest("App", Vec::new()), Err(K8sError::InvalidName("App".to_string())));
+/// assert_eq!(secret_manifest("app", vec![("db/password", "")]), Err(K8sError::InvalidKey("db/password".to_string())));
+/// ```
+///
+pub fn secret_manifest<'a>(name: &str, entries: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<String, K8sError> {
+    if !is_valid_name(name) {
+        return Err(K8sError::InvalidName(name.to_string()));
+    }
+    let mut data = String::new();
+    for (key, value) in entries {
+        if !is_valid_key(key) {
+            return Err(K8sError::InvalidKey(key.to_string()));
+        }
+        // Neither the key nor base64 needs quoting in YAML.
+        let _ = writeln!(data, "  {}: {}", key, base64::encode(value));
+    }
+    if data.is_empty() {
+        data.push_str(" {}\n");
+    } else {
+        data.insert(0, '\n');
+    }
+    Ok(format!("apiVersion: v1\nkind: Secret\nmetadata:\n  name: {}\ntype: Opaque\ndata:{}", name, data))
+}
+
+/// Kubernetes names secrets with DNS subdomain names: lowercase alphanumeric labels joined by
+/// `-` or `.`, starting and ending with an alphanumeric character.
+fn is_valid_name(name: &str) -> bool {
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    name.len() <= MAX_NAME_LEN
+        && name.starts_with(alphanumeric)
+        && name.ends_with(alphanumeric)
+        && name.chars().all(|c| alphanumeric(c) || c == '-' || c == '.')
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_NAME_LEN
+        && key != "."
+        && key != ".."
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
@@ -1,10 +1,39 @@
-#![feature(never_type)]
+#![cfg_attr(feature = "repl", feature(never_type))]
 #![forbid(unsafe_code)]
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::non_ascii_literal)]
+#![allow(clippy::uninlined_format_args)]
 
+#[cfg(feature = "age")]
+pub mod age;
+pub mod armor;
+#[cfg(feature = "repl")]
+pub mod config;
+pub mod extract;
 pub mod file;
+pub mod generate;
+#[cfg(feature = "gpg")]
+pub mod gpg;
+pub mod import;
+pub mod k8s;
+pub mod manifest;
+#[cfg(feature = "openssl")]
+pub mod openssl;
+pub mod path;
+pub mod policy;
+pub mod report;
+#[cfg(feature = "repl")]
 pub mod repl;
+pub mod secret;
+pub mod secure;
+pub mod self_test;
+pub mod share;
+pub mod shred;
+pub mod timestamp;
+pub mod verify;
+
+#[cfg(test)]
+mod testing;
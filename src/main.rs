@@ -1,7 +1,177 @@
-use crypt_client::repl::{Repl, RustyLineReplDriver};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+use crypt_client::config::Config;
+use crypt_client::repl::{BatchReplDriver, OutputStyle, Repl, ReplDriver, RustyLineReplDriver};
+use crypt_client::secret::{ConfiguredSecretSource, SecretSource};
+use crypt_client::self_test::{self, SelfTest};
+use crypt_client::verify::{find_files, verify_files};
+
+const USAGE: &str = "Usage: crypt-client [--batch <script|->] [--json-errors]
+       crypt-client verify-all <dir> [--password-file <path>]
+       crypt-client --self-test";
+
+struct Args {
+    /// Commands are read from this file, or stdin if it is `-`, instead of interactively.
+    batch: Option<String>,
+    json_errors: bool,
+    verify_all: Option<VerifyAllArgs>,
+    /// Run the full self-test and exit, instead of only the quick one before starting.
+    self_test: bool,
+}
+
+struct VerifyAllArgs {
+    dir: PathBuf,
+    /// The password is read from the first line of this file instead of being asked for.
+    password_file: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args { batch: None, json_errors: false, verify_all: None, self_test: false };
+    let mut iter = std::env::args().skip(1).peekable();
+    if iter.peek().map(String::as_str) == Some("verify-all") {
+        iter.next();
+        let dir = iter.next().ok_or("verify-all requires a directory")?;
+        let mut verify_all = VerifyAllArgs { dir: PathBuf::from(dir), password_file: None };
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--password-file" => verify_all.password_file = Some(iter.next().ok_or("--password-file requires a path")?.into()),
+                _ => return Err(format!("Unknown argument: {}", arg))
+            }
+        }
+        args.verify_all = Some(verify_all);
+        return Ok(args);
+    }
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--batch" => args.batch = Some(iter.next().ok_or("--batch requires a script path")?),
+            "--json-errors" => args.json_errors = true,
+            "--self-test" => args.self_test = true,
+            _ => return Err(format!("Unknown argument: {}", arg))
+        }
+    }
+    Ok(args)
+}
+
+fn configure<D: ReplDriver>(repl: &mut Repl<D>, config: Config) {
+    repl.set_limits(config.limits);
+    repl.set_autosave(config.autosave);
+    repl.set_copy_on_get(config.copy_on_get);
+    repl.set_expiry_reminders(config.expiry_reminders);
+    repl.set_trash(config.trash);
+    repl.set_cipher(config.cipher);
+    repl.set_compression(config.compression);
+    repl.set_layout(config.layout);
+    repl.set_kdf(config.kdf);
+    repl.set_backups(config.backups);
+    repl.set_hooks(config.hooks);
+    repl.set_extra_arguments(config.extra_arguments);
+    for (filepath, source) in config.secret_sources {
+        repl.set_secret_source(filepath, source);
+    }
+    if let Some(policy) = config.password_policy {
+        repl.set_password_policy(policy);
+    }
+}
+
+fn run_batch(script: impl BufRead, json_errors: bool, config: Config) -> ! {
+    let mut repl = Repl::new(BatchReplDriver::new(script, json_errors));
+    configure(&mut repl, config);
+    match repl.run() {
+        // A failed command fails the script, even if it went on to exit with 0.
+        Ok(exit_command) if exit_command.code == 0 && repl.driver().has_failed() => std::process::exit(1),
+        Ok(exit_command) => std::process::exit(exit_command.code),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Verifies every file under `args.dir` with the same password, printing a line per file and a
+/// summary. Exits with 1 if any file fails.
+fn run_verify_all(args: &VerifyAllArgs) -> ! {
+    let password = match &args.password_file {
+        Some(path) => ConfiguredSecretSource::File(path.clone()).fetch()
+            .map(Option::unwrap_or_default)
+            .map_err(|error| error.to_string()),
+        None => rpassword::read_password_from_tty(Some("Enter password for files: "))
+            .map(Zeroizing::new)
+            .map_err(|error| error.to_string())
+    };
+    let password = match password {
+        Ok(password) => password,
+        Err(error) => {
+            eprintln!("Failed to read password: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let filepaths = match find_files(&args.dir) {
+        Ok(filepaths) => filepaths,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", args.dir.display(), error);
+            std::process::exit(1);
+        }
+    };
+    let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+    let report = verify_files(&filepaths, password.as_str(), threads);
+    print!("{}", OutputStyle::from_env().table(&report.rows()));
+    println!("{}", report.summary());
+    std::process::exit(i32::from(!report.is_success()));
+}
+
+/// Runs the self-test, printing every check if `depth` is [`SelfTest::Full`] or any check
+/// failed. Exits with 1 if one did, and after the full test whatever happened.
+fn run_self_test(depth: SelfTest) {
+    let report = self_test::run(depth);
+    if depth == SelfTest::Full || !report.is_success() {
+        print!("{}", OutputStyle::from_env().table(&report.rows()));
+        println!("{}", report.summary());
+    }
+    if !report.is_success() {
+        eprintln!("Refusing to run, the crypto in this build gives wrong answers");
+        std::process::exit(1);
+    }
+    if depth == SelfTest::Full {
+        std::process::exit(0);
+    }
+}
 
 fn main() {
-    let mut repl = Repl::new(RustyLineReplDriver::default());
-    repl.print_usage();
-    repl.run_loop().unwrap();
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    run_self_test(if args.self_test { SelfTest::Full } else { SelfTest::Quick });
+    if let Some(verify_all) = &args.verify_all {
+        run_verify_all(verify_all);
+    }
+    let config = match Config::load_default() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Failed to load config: {}", error);
+            std::process::exit(1);
+        }
+    };
+    match args.batch.as_deref() {
+        Some("-") => run_batch(std::io::stdin().lock(), args.json_errors, config),
+        Some(path) => match File::open(path) {
+            Ok(script) => run_batch(BufReader::new(script), args.json_errors, config),
+            Err(error) => {
+                eprintln!("Failed to open {}: {}", path, error);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let driver = RustyLineReplDriver::default().with_history_exclusions(config.history_exclude.clone());
+            let mut repl = Repl::new(driver);
+            configure(&mut repl, config);
+            repl.print_usage();
+            repl.run_loop().unwrap();
+        }
+    }
 }
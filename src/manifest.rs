@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use hmac::{Hmac, Mac, NewMac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::file::{derive_key, CryptData, EncryptError};
+
+const VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+/// Keeps manifest keys apart from anything else derived from the same password.
+const KEY_CONTEXT: &[u8] = b"crypt-client manifest";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug)]
+pub enum ManifestError {
+    DeriveKey(EncryptError),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    InvalidEncoding(base64::DecodeError),
+    /// The manifest was changed after it was created, or was created with another password.
+    BadSignature,
+    /// The file has no password to sign the manifest with, such as a GPG file.
+    NoPassword,
+}
+
+impl From<EncryptError> for ManifestError {
+    fn from(error: EncryptError) -> Self {
+        Self::DeriveKey(error)
+    }
+}
+
+impl From<serde_json::Error> for ManifestError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<base64::DecodeError> for ManifestError {
+    fn from(error: base64::DecodeError) -> Self {
+        Self::InvalidEncoding(error)
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DeriveKey(error) => write!(f, "{}", error),
+            Self::Json(error) => write!(f, "invalid manifest, {}", error),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported manifest version {}", version),
+            Self::InvalidEncoding(error) => write!(f, "invalid manifest, {}", error),
+            Self::BadSignature => f.write_str("the manifest was modified or made with a different password"),
+            Self::NoPassword => f.write_str("manifests are signed with the password, and the file has none")
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// How a crypt differs from a [`Manifest`], see [`Manifest::check`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ManifestDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ManifestDiff {
+    /// Returns `true` if the crypt matches the manifest exactly.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The key names of a crypt with a keyed hash of each value, signed as a whole. It holds no
+/// plaintext values, so it can be committed next to a shared crypt file to detect unauthorized
+/// changes.
+///
+/// Hashes are keyed with a key derived from the crypt's password, so values can't be guessed from
+/// the manifest, and only someone who knows the password can create or check one.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::file::CryptData;
+/// use crypt_client::manifest::Manifest;
+///
+/// let mut data = CryptData::new();
+/// data.insert("db/password", "hunter2");
+/// data.insert("api/token", "abc123");
+///
+/// let manifest = Manifest::create(&data, "vault password").unwrap();
+/// let json = manifest.to_json();
+/// assert!(json.contains("db/password"));
+/// assert!(!json.contains("hunter2"));
+///
+/// let manifest = Manifest::from_json(&json).unwrap();
+/// assert!(manifest.check(&data, "vault password").unwrap().is_clean());
+///
+/// data.insert("db/password", "changed");
+/// data.remove("api/token");
+/// let diff = manifest.check(&data, "vault password").unwrap();
+/// assert_eq!(diff.changed, vec!["db/password".to_string()]);
+/// assert_eq!(diff.removed, vec!["api/token".to_string()]);
+///
+/// assert!(manifest.check(&data, "another password").is_err());
+/// ```
+///
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    version: u32,
+    /// Base64 encoded salt of the key derivation.
+    salt: String,
+    /// Hex encoded hash of each value, by key.
+    entries: BTreeMap<String, String>,
+    /// Base64 encoded MAC of everything above.
+    mac: String,
+}
+
+impl Manifest {
+    pub fn create(data: &CryptData, password: &str) -> Result<Self, ManifestError> {
+        let mut salt = [0_u8; SALT_LEN];
+        rand::thread_rng().fill(&mut salt[..]);
+        let key = derive_key(password, &salt, KEY_CONTEXT)?;
+        let salt = base64::encode(salt);
+        let entries = hash_entries(&key, data);
+        let mac = base64::encode(sign(&key, VERSION, &salt, &entries).finalize().into_bytes());
+        Ok(Self { version: VERSION, salt, entries, mac })
+    }
+
+    /// Pretty printed, one entry per line, so changes show up clearly in diffs.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ManifestError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Checks the manifest's signature and compares it against `data`. Fails if the manifest was
+    /// tampered with or `password` isn't the one it was created with.
+    pub fn check(&self, data: &CryptData, password: &str) -> Result<ManifestDiff, ManifestError> {
+        if self.version != VERSION {
+            return Err(ManifestError::UnsupportedVersion(self.version));
+        }
+        let key = derive_key(password, &base64::decode(&self.salt)?, KEY_CONTEXT)?;
+        sign(&key, self.version, &self.salt, &self.entries)
+            .verify(&base64::decode(&self.mac)?)
+            .map_err(|_| ManifestError::BadSignature)?;
+        let current = hash_entries(&key, data);
+        let mut diff = ManifestDiff::default();
+        for (name, hash) in &current {
+            match self.entries.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(expected) if expected != hash => diff.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = self.entries.keys().filter(|name| !current.contains_key(*name)).cloned().collect();
+        Ok(diff)
+    }
+}
+
+fn new_mac(key: &[u8]) -> HmacSha256 {
+    // HMAC accepts keys of any length.
+    HmacSha256::new_from_slice(key).unwrap_or_else(|_| unreachable!())
+}
+
+/// Hashes each value along with its key, so values can't be swapped between keys unnoticed.
+fn hash_entries(key: &[u8], data: &CryptData) -> BTreeMap<String, String> {
+    data.iter()
+        .map(|(name, value)| {
+            let mut mac = new_mac(key);
+            update_str(&mut mac, name);
+            update_str(&mut mac, value);
+            (name.to_string(), format!("{:x}", mac.finalize().into_bytes()))
+        })
+        .collect()
+}
+
+fn sign(key: &[u8], version: u32, salt: &str, entries: &BTreeMap<String, String>) -> HmacSha256 {
+    let mut mac = new_mac(key);
+    mac.update(&version.to_le_bytes());
+    update_str(&mut mac, salt);
+    for (name, hash) in entries {
+        update_str(&mut mac, name);
+        update_str(&mut mac, hash);
+    }
+    mac
+}
+
+/// Adds `s` prefixed with its length, so adjacent strings can't be shifted into each other.
+fn update_str(mac: &mut HmacSha256, s: &str) {
+    mac.update(&(s.len() as u64).to_le_bytes());
+    mac.update(s.as_bytes());
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+use aes::Aes256;
+use block_modes::{BlockMode, Cbc};
+use block_modes::block_padding::Pkcs7;
+use hmac::Hmac;
+use rand::Rng;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+use crate::secure::SecureBuffer;
+
+type Aes256Cbc = Cbc<Aes256, Pkcs7>;
+
+/// Starts every file `openssl enc` writes with a salt.
+pub const MAGIC: &[u8] = b"Salted__";
+/// The PBKDF2 iterations `openssl enc -pbkdf2` uses when not given `-iter`. The count isn't
+/// stored in the file, so both sides have to agree on it.
+pub const DEFAULT_ITERATIONS: u32 = 10_000;
+const SALT_LEN: usize = 8;
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OpensslError {
+    /// The file isn't something `openssl enc` wrote with a salt.
+    InvalidFormat(&'static str),
+    /// The file can't be written the way it is being written, as `openssl enc` couldn't read it.
+    Unsupported(&'static str),
+    /// The password is wrong or the file was modified. AES-256-CBC has no authentication tag, so
+    /// the two can't be told apart, and some modifications go unnoticed.
+    WrongPassword,
+    Encrypt,
+}
+
+impl fmt::Display for OpensslError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(reason) => write!(f, "not an openssl enc file, {}", reason),
+            Self::Unsupported(what) => write!(f, "openssl enc files are only supported with a password, not {}", what),
+            Self::WrongPassword => f.write_str("the password is wrong or the file was modified"),
+            Self::Encrypt => f.write_str("the openssl enc file could not be encrypted")
+        }
+    }
+}
+
+impl std::error::Error for OpensslError {}
+
+/// Returns `true` if `contents` starts like a salted `openssl enc` file.
+#[must_use]
+pub fn is_openssl(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+/// Encrypts `plaintext` with `password` the way `openssl enc -aes-256-cbc -pbkdf2 -iter
+/// <iterations>` does: the key and IV are derived with PBKDF2-HMAC-SHA-256 from a random salt
+/// stored after [`MAGIC`].
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::openssl::{decrypt, encrypt, DEFAULT_ITERATIONS};
+///
+/// let encrypted = encrypt("correct horse", DEFAULT_ITERATIONS, b"secret").unwrap();
+/// assert!(encrypted.starts_with(b"Salted__"));
+/// assert_eq!(&*decrypt("correct horse", DEFAULT_ITERATIONS, &encrypted).unwrap(), b"secret");
+/// ```
+///
+pub fn encrypt(password: &str, iterations: u32, plaintext: &[u8]) -> Result<Vec<u8>, OpensslError> {
+    let salt: [u8; SALT_LEN] = rand::thread_rng().gen();
+    let key = derive_key(password, &salt, iterations);
+    let cipher = Aes256Cbc::new_from_slices(&key[..KEY_LEN], &key[KEY_LEN..]).map_err(|_| OpensslError::Encrypt)?;
+    let mut contents = MAGIC.to_vec();
+    contents.extend_from_slice(&salt);
+    contents.extend(cipher.encrypt_vec(plaintext));
+    Ok(contents)
+}
+
+/// Decrypts a file encrypted with `password` by [`encrypt`] or by `openssl enc -aes-256-cbc
+/// -pbkdf2 -iter <iterations>`.
+pub fn decrypt(password: &str, iterations: u32, contents: &[u8]) -> Result<SecureBuffer, OpensslError> {
+    if !is_openssl(contents) {
+        return Err(OpensslError::InvalidFormat("the salt is missing"));
+    }
+    let (salt, ciphertext) = contents[MAGIC.len()..].split_at_checked(SALT_LEN)
+        .ok_or(OpensslError::InvalidFormat("the file is too short"))?;
+    let key = derive_key(password, salt, iterations);
+    let cipher = Aes256Cbc::new_from_slices(&key[..KEY_LEN], &key[KEY_LEN..]).map_err(|_| OpensslError::WrongPassword)?;
+    cipher.decrypt_vec(ciphertext).map(SecureBuffer::from).map_err(|_| OpensslError::WrongPassword)
+}
+
+/// Derives the key followed by the IV with PBKDF2-HMAC-SHA-256.
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> Zeroizing<[u8; KEY_LEN + IV_LEN]> {
+    let mut output = Zeroizing::new([0_u8; KEY_LEN + IV_LEN]);
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, &mut output[..]);
+    output
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum CryptPathError {
+    Empty,
+    /// `~` was used but the home directory isn't known.
+    NoHomeDirectory,
+    IsDirectory(PathBuf),
+    ParentMissing(PathBuf),
+    ParentNotDirectory(PathBuf),
+    ParentReadOnly(PathBuf),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for CryptPathError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for CryptPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("the path is empty"),
+            Self::NoHomeDirectory => f.write_str("the home directory is unknown, set $HOME"),
+            Self::IsDirectory(path) => write!(f, "{} is a directory", path.display()),
+            Self::ParentMissing(path) => write!(f, "parent directory {} does not exist", path.display()),
+            Self::ParentNotDirectory(path) => write!(f, "parent {} is not a directory", path.display()),
+            Self::ParentReadOnly(path) => write!(f, "parent directory {} is read-only", path.display()),
+            Self::Io(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for CryptPathError {}
+
+/// Replaces a leading `~` with the home directory.
+pub(crate) fn expand_home(path: &Path) -> Result<PathBuf, CryptPathError> {
+    let Ok(rest) = path.strip_prefix("~") else {
+        return Ok(path.to_path_buf());
+    };
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .ok_or(CryptPathError::NoHomeDirectory)?;
+    Ok(PathBuf::from(home).join(rest))
+}
+
+/// The absolute path of a crypt file that either exists or can be created.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::path::CryptPath;
+///
+/// let path = CryptPath::new("./new.crypt").unwrap();
+/// assert!(path.as_path().is_absolute());
+/// assert!(path.as_path().ends_with("new.crypt"));
+///
+/// let error = CryptPath::new("./missing-dir/new.crypt").unwrap_err();
+/// assert!(error.to_string().starts_with("parent directory"));
+///
+/// assert!(CryptPath::new(".").is_err());
+/// ```
+///
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CryptPath(PathBuf);
+
+impl CryptPath {
+    /// Expands a leading `~`, makes `path` absolute and resolves symlinks. Fails with the reason
+    /// if `path` is a directory, or doesn't exist and couldn't be created.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, CryptPathError> {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() {
+            return Err(CryptPathError::Empty);
+        }
+        let path = expand_home(path)?;
+        if path.exists() {
+            if path.is_dir() {
+                return Err(CryptPathError::IsDirectory(path));
+            }
+            return Ok(Self(path.canonicalize()?));
+        }
+        let Some(file_name) = path.file_name() else {
+            return Err(CryptPathError::IsDirectory(path));
+        };
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new(".")
+        };
+        if !parent.exists() {
+            return Err(CryptPathError::ParentMissing(parent.to_path_buf()));
+        }
+        if !parent.is_dir() {
+            return Err(CryptPathError::ParentNotDirectory(parent.to_path_buf()));
+        }
+        if parent.metadata()?.permissions().readonly() {
+            return Err(CryptPathError::ParentReadOnly(parent.to_path_buf()));
+        }
+        Ok(Self(parent.canonicalize()?.join(file_name)))
+    }
+
+    #[must_use]
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_path_buf(self) -> PathBuf {
+        self.0
+    }
+}
+
+impl AsRef<Path> for CryptPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for CryptPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use serde::Deserialize;
+
+/// A rule broken by a password, as reported by a [`PasswordPolicy`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    Denylisted,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooShort { min_length } => write!(f, "must be at least {} characters long", min_length),
+            Self::MissingLowercase => f.write_str("must contain a lowercase letter"),
+            Self::MissingUppercase => f.write_str("must contain an uppercase letter"),
+            Self::MissingDigit => f.write_str("must contain a digit"),
+            Self::MissingSymbol => f.write_str("must contain a symbol"),
+            Self::Denylisted => f.write_str("is too common to be used")
+        }
+    }
+}
+
+/// Decides whether a password is acceptable for a crypt file. Checked whenever a new password
+/// is chosen, i.e. when creating a file.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::policy::{PasswordPolicy, PolicyViolation};
+///
+/// struct NoSpaces;
+///
+/// impl PasswordPolicy for NoSpaces {
+///     fn check(&self, password: &str) -> Vec<PolicyViolation> {
+///         if password.contains(' ') {
+///             vec![PolicyViolation::MissingSymbol]
+///         } else {
+///             Vec::new()
+///         }
+///     }
+/// }
+///
+/// assert!(NoSpaces.check("correct-horse").is_empty());
+/// ```
+///
+pub trait PasswordPolicy {
+    /// Returns every rule `password` breaks, or an empty list if it is acceptable.
+    fn check(&self, password: &str) -> Vec<PolicyViolation>;
+}
+
+/// Accepts every password. This is the default policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PermissivePolicy;
+
+impl PasswordPolicy for PermissivePolicy {
+    fn check(&self, _password: &str) -> Vec<PolicyViolation> {
+        Vec::new()
+    }
+}
+
+/// A [`PasswordPolicy`] built from simple rules, usually read from the `[password_policy]`
+/// section of the config file.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::policy::{PasswordPolicy, PolicyViolation, RulesPolicy};
+///
+/// let policy = RulesPolicy {
+///     min_length: 12,
+///     require_digit: true,
+///     denylist: vec!["password1234".to_string()],
+///     ..RulesPolicy::default()
+/// };
+/// assert_eq!(policy.check("short"), vec![PolicyViolation::TooShort { min_length: 12 }, PolicyViolation::MissingDigit]);
+/// assert_eq!(policy.check("PASSWORD1234"), vec![PolicyViolation::Denylisted]);
+/// assert!(policy.check("a much longer passphrase 1").is_empty());
+/// ```
+///
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RulesPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Passwords that are rejected outright, compared ignoring case.
+    pub denylist: Vec<String>,
+}
+
+impl PasswordPolicy for RulesPolicy {
+    fn check(&self, password: &str) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        if password.chars().count() < self.min_length {
+            violations.push(PolicyViolation::TooShort { min_length: self.min_length });
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PolicyViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PolicyViolation::MissingUppercase);
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PolicyViolation::MissingDigit);
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            violations.push(PolicyViolation::MissingSymbol);
+        }
+        if self.denylist.iter().any(|denied| denied.to_lowercase() == password.to_lowercase()) {
+            violations.push(PolicyViolation::Denylisted);
+        }
+        violations
+    }
+}
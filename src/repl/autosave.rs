@@ -0,0 +1,61 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+use crate::repl::parse_autosave_policy;
+
+/// When a [`Repl`](crate::repl::Repl) saves an open file without being asked to. Files are only
+/// saved if they have unsaved changes, and the policy is checked after every command.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use crypt_client::repl::AutosavePolicy;
+///
+/// assert_eq!("on-change".parse(), Ok(AutosavePolicy::OnChange));
+/// assert_eq!("30s".parse(), Ok(AutosavePolicy::Every(Duration::from_secs(30))));
+/// assert_eq!(AutosavePolicy::Every(Duration::from_secs(30)).to_string(), "30s");
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AutosavePolicy {
+    /// Only save when locking the file or exiting.
+    #[default]
+    Off,
+    /// Save after every command that changes the file.
+    OnChange,
+    /// Save changes once this long has passed since the file was last saved.
+    Every(Duration),
+}
+
+impl fmt::Display for AutosavePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Off => f.write_str("off"),
+            Self::OnChange => f.write_str("on-change"),
+            Self::Every(interval) => write!(f, "{}s", interval.as_secs())
+        }
+    }
+}
+
+impl FromStr for AutosavePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_autosave_policy::<nom::error::VerboseError<&str>>(s) {
+            Ok(("", policy)) => Ok(policy),
+            _ => Err(format!("invalid autosave policy '{}', expected off, on-change or a number of seconds like 30s", s))
+        }
+    }
+}
+
+impl TryFrom<String> for AutosavePolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
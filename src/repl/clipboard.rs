@@ -0,0 +1,42 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The commands tried, in order, to copy text to the system clipboard. Each reads the text from
+/// stdin.
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["clip.exe"],
+];
+
+/// Copies `text` to the system clipboard using the first clipboard command that is installed.
+///
+/// # Errors
+///
+/// Fails if no clipboard command is installed, or the one found couldn't copy `text`.
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    for command in CLIPBOARD_COMMANDS {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error)
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("{} failed with {}", command[0], status)));
+        }
+        return Ok(());
+    }
+    Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no clipboard command found, install wl-copy, xclip or xsel"))
+}
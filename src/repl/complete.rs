@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use crate::repl::COMMANDS;
+
+/// The aliases and keys that can be completed, taken from a [`Repl`](crate::repl::Repl) with
+/// [`Repl::completions`](crate::repl::Repl::completions), so every frontend completes commands
+/// the same way.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::Completions;
+///
+/// let mut completions = Completions::default();
+/// completions.add_alias("work", vec!["db/password", "db/user", "api key"]);
+/// completions.add_alias("home", Vec::<String>::new());
+///
+/// assert_eq!(completions.candidates("crypt lo"), vec!["lock"]);
+/// assert_eq!(completions.candidates("crypt lock "), vec!["home", "work"]);
+/// assert_eq!(completions.candidates("crypt data work get db/"), vec!["db/password", "db/user"]);
+/// assert_eq!(completions.candidates("crypt data work get a"), vec!["'api key'"]);
+/// assert_eq!(completions.candidates("crypt unlock work ./work.crypt --layout "), vec!["chunked", "single"]);
+/// assert!(completions.candidates("crypt data nope get ").is_empty());
+/// ```
+///
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Completions {
+    /// The keys of each open alias.
+    aliases: BTreeMap<String, Vec<String>>,
+}
+
+impl Completions {
+    /// Adds an open alias and its keys.
+    pub fn add_alias<K: Into<String>>(&mut self, alias: impl Into<String>, keys: impl IntoIterator<Item = K>) {
+        self.aliases.insert(alias.into(), keys.into_iter().map(Into::into).collect());
+    }
+
+    /// Returns what the last, partly typed word of `input` could be completed to, sorted and
+    /// quoted where needed. The words before it decide whether commands, aliases, keys or the
+    /// values of an option are offered. Each candidate replaces `input` from
+    /// [`completion_start`] on.
+    #[must_use]
+    pub fn candidates(&self, input: &str) -> Vec<String> {
+        let (words, partial) = split_words(input);
+        let mut candidates = Vec::new();
+        for command in COMMANDS {
+            let usage = usage_tokens(command.usage);
+            if usage.len() <= words.len() || !words.iter().zip(&usage).all(|(word, token)| is_placeholder(token) || word == token) {
+                continue;
+            }
+            let alias = usage.iter().position(|token| *token == "<alias>").and_then(|index| words.get(index));
+            match placeholder_name(usage[words.len()]) {
+                None => candidates.push(usage[words.len()].to_string()),
+                Some("alias" | "source-alias") => candidates.extend(self.aliases.keys().cloned()),
+                Some("key") => candidates.extend(alias.and_then(|alias| self.aliases.get(alias)).into_iter().flatten().cloned()),
+                Some(name) if name.contains('/') && !name.contains(' ') => candidates.extend(name.split('/').map(str::to_string)),
+                Some(_) => {}
+            }
+        }
+        candidates.retain(|candidate| candidate.starts_with(partial.as_str()));
+        candidates.sort();
+        candidates.dedup();
+        candidates.iter().map(|candidate| quote(candidate)).collect()
+    }
+}
+
+/// Returns the byte offset in `input` where the word [`Completions::candidates`] completes
+/// starts.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::completion_start;
+///
+/// assert_eq!(completion_start("crypt lo"), 6);
+/// assert_eq!(completion_start("crypt lock "), 11);
+/// assert_eq!(completion_start("crypt data work get 'api k"), 20);
+/// ```
+///
+#[must_use]
+pub fn completion_start(input: &str) -> usize {
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => start = index + c.len_utf8(),
+            _ => {}
+        }
+    }
+    start
+}
+
+/// Splits `input` into the words typed so far, unquoted, and the partly typed last word.
+fn split_words(input: &str) -> (Vec<String>, String) {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in input.chars() {
+        match c {
+            _ if escaped => {
+                word.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    (words, word)
+}
+
+/// Splits a usage from [`COMMANDS`] into its words, keeping placeholders like
+/// `<YYYY-MM-DD or never>` whole and leaving out everything from the first optional `[...]` on.
+fn usage_tokens(usage: &'static str) -> Vec<&'static str> {
+    let mut tokens = Vec::new();
+    let mut rest = usage.trim_start();
+    while !rest.is_empty() && !rest.starts_with('[') {
+        let end = if rest.starts_with('<') {
+            rest.find('>').map_or(rest.len(), |end| end + 1)
+        } else {
+            rest.find(' ').unwrap_or(rest.len())
+        };
+        tokens.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches("...").trim_start();
+    }
+    tokens
+}
+
+fn is_placeholder(token: &str) -> bool {
+    token.starts_with('<')
+}
+
+/// The name of a placeholder token without its angle brackets, `None` for a literal word.
+fn placeholder_name(token: &str) -> Option<&str> {
+    token.strip_prefix('<').and_then(|token| token.strip_suffix('>'))
+}
+
+/// Quotes `word` the way the REPL parses quoted strings, if it needs quoting.
+fn quote(word: &str) -> String {
+    if !word.contains(char::is_whitespace) {
+        return word.to_string();
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('\'');
+    for c in word.chars() {
+        if matches!(c, '\'' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
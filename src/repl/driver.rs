@@ -1,4 +1,7 @@
 use std::fmt;
+use std::io::{BufRead, IsTerminal};
+use std::time::Duration;
+use crate::repl::{completion_start, contains_secret, Completions, HistoryExclusions, ReplError};
 
 /// An interface for prompting the user for input.
 ///
@@ -21,7 +24,9 @@ use std::fmt;
 ///         eprint!("{}", s);
 ///     }
 ///
-///     fn clear_screen(&mut self) -> Result<(), Self::Error> {}
+///     fn clear_screen(&mut self) -> Result<(), Self::Error> {
+///         Ok(())
+///     }
 ///
 ///     fn prompt_line(&mut self, prompt: &str) -> Result<String, Self::Error> {
 ///         Ok("Not gonna ask the user".to_string())
@@ -46,9 +51,61 @@ pub trait ReplDriver {
 
     fn clear_screen(&mut self) -> Result<(), Self::Error>;
 
+    /// Whether [`clear_screen`](Self::clear_screen) can clear the screen. When it can't, or
+    /// fails, the REPL scrolls the screen with blank lines instead.
+    fn can_clear_screen(&self) -> bool {
+        true
+    }
+
     fn prompt_line(&mut self, prompt: &str) -> Result<String, Self::Error>;
 
     fn prompt_password(&mut self, prompt: &str) -> Result<String, Self::Error>;
+
+    /// Whether asking for a password again after a wrong one could give a different answer.
+    fn can_retry_password(&self) -> bool {
+        true
+    }
+
+    /// Whether someone can answer questions the user didn't ask for, such as what to do with
+    /// the files an interrupted save left behind.
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    /// Waits up to `timeout` for any key to be pressed, returning whether one was. The default
+    /// implementation can't tell and waits out the timeout.
+    fn wait_for_key(&mut self, timeout: Duration) -> bool {
+        std::thread::sleep(timeout);
+        false
+    }
+
+    /// Called before each command is prompted for with what it can be completed with. The
+    /// default implementation ignores them.
+    fn set_completions(&mut self, completions: Completions) {
+        let _ = completions;
+    }
+
+    /// Reports a failed command. The default implementation prints the message to stderr.
+    fn report_error(&mut self, error: &ReplError) {
+        self.eprint(format!("{}\n", error));
+    }
+
+    /// Prompts the user to pick one of `options`, returning the index of the chosen option.
+    ///
+    /// The default implementation prints a numbered list and re-prompts until a valid number is
+    /// entered.
+    fn select(&mut self, prompt: &str, options: &[&str]) -> Result<usize, Self::Error> {
+        for (index, option) in options.iter().enumerate() {
+            self.print(format!("  {}) {}\n", index + 1, option));
+        }
+        loop {
+            let answer = self.prompt_line(prompt)?;
+            match answer.trim().parse::<usize>() {
+                Ok(choice) if (1..=options.len()).contains(&choice) => return Ok(choice - 1),
+                _ => self.eprint(format!("Enter a number between 1 and {}\n", options.len()))
+            }
+        }
+    }
 }
 
 /// An implementation of [`ReplDriver`] using `rustyline`, `rpassword` and `clearscreen`.
@@ -63,7 +120,19 @@ pub trait ReplDriver {
 /// assert_eq!(driver.prompt_password("...").unwrap(), "A password".to_string());
 /// ```
 pub struct RustyLineReplDriver {
-    rl: rustyline::Editor<()>,
+    rl: rustyline::Editor<CompletionHelper>,
+    /// Set once clearing the screen has failed, so it isn't attempted again.
+    clear_failed: bool,
+    history_exclusions: HistoryExclusions,
+}
+
+impl RustyLineReplDriver {
+    /// Leaves lines matching `exclusions` out of the history, as well as the lines that carry
+    /// secret values.
+    #[must_use]
+    pub fn with_history_exclusions(self, exclusions: HistoryExclusions) -> Self {
+        Self { history_exclusions: exclusions, ..self }
+    }
 }
 
 impl Default for RustyLineReplDriver {
@@ -83,10 +152,37 @@ impl Default for RustyLineReplDriver {
             .indent_size(2)
             .bracketed_paste(true)
             .build();
-        Self { rl: rustyline::Editor::with_config(config) }
+        let mut rl = rustyline::Editor::with_config(config);
+        rl.set_helper(Some(CompletionHelper::default()));
+        Self { rl, clear_failed: false, history_exclusions: HistoryExclusions::default() }
     }
 }
 
+/// Completes commands in [`RustyLineReplDriver`] with [`Completions::candidates`].
+#[derive(Default)]
+struct CompletionHelper {
+    completions: Completions,
+}
+
+impl rustyline::completion::Completer for CompletionHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let input = &line[..pos];
+        Ok((completion_start(input), self.completions.candidates(input)))
+    }
+}
+
+impl rustyline::hint::Hinter for CompletionHelper {
+    type Hint = String;
+}
+
+impl rustyline::highlight::Highlighter for CompletionHelper {}
+
+impl rustyline::validate::Validator for CompletionHelper {}
+
+impl rustyline::Helper for CompletionHelper {}
+
 #[derive(Debug)]
 pub enum RustyLineDriverError {
     RustyLine(rustyline::error::ReadlineError),
@@ -136,13 +232,30 @@ impl ReplDriver for RustyLineReplDriver {
     }
 
     fn clear_screen(&mut self) -> Result<(), Self::Error> {
-        clearscreen::clear()?;
+        if let Err(error) = clearscreen::clear() {
+            self.clear_failed = true;
+            return Err(error.into());
+        }
         Ok(())
     }
 
+    fn can_clear_screen(&self) -> bool {
+        !self.clear_failed
+            && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+            && std::io::stdout().is_terminal()
+    }
+
+    fn set_completions(&mut self, completions: Completions) {
+        if let Some(helper) = self.rl.helper_mut() {
+            helper.completions = completions;
+        }
+    }
+
     fn prompt_line(&mut self, prompt: &str) -> Result<String, Self::Error> {
         let line = self.rl.readline(prompt)?;
-        self.rl.add_history_entry(line.as_str());
+        if !contains_secret(line.as_str()) && !self.history_exclusions.is_match(line.as_str()) {
+            self.rl.add_history_entry(line.as_str());
+        }
         Ok(line)
     }
 
@@ -150,4 +263,157 @@ impl ReplDriver for RustyLineReplDriver {
         let password = rpassword::read_password_from_tty(Some(prompt))?;
         Ok(password)
     }
+
+    fn wait_for_key(&mut self, timeout: Duration) -> bool {
+        #[cfg(unix)]
+        if let Some(pressed) = wait_for_terminal_key(timeout) {
+            return pressed;
+        }
+        std::thread::sleep(timeout);
+        false
+    }
+}
+
+/// Waits up to `timeout` for a key on the terminal, with line editing switched off so a single
+/// key is enough, then switches it back. [`None`] if stdin isn't a terminal.
+#[cfg(unix)]
+fn wait_for_terminal_key(timeout: Duration) -> Option<bool> {
+    use std::convert::TryFrom;
+    use std::os::unix::io::AsRawFd;
+    use nix::poll::{poll, PollFd, PollFlags};
+    use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg};
+
+    let fd = std::io::stdin().as_raw_fd();
+    let original = tcgetattr(fd).ok()?;
+    let mut single_keys = original.clone();
+    single_keys.local_flags.remove(LocalFlags::ICANON | LocalFlags::ECHO);
+    tcsetattr(fd, SetArg::TCSANOW, &single_keys).ok()?;
+    let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+    let pressed = poll(&mut [PollFd::new(fd, PollFlags::POLLIN)], timeout).is_ok_and(|ready| ready > 0);
+    if pressed {
+        // Read, so the key doesn't turn up at the start of the next command.
+        let _ = nix::unistd::read(fd, &mut [0_u8; 16]);
+    }
+    let _ = tcsetattr(fd, SetArg::TCSANOW, &original);
+    Some(pressed)
+}
+
+/// The environment variable [`BatchReplDriver`] reads passwords from, instead of asking on the
+/// terminal.
+pub const PASSWORD_ENV: &str = "CRYPT_CLIENT_PASSWORD";
+
+/// A non-interactive implementation of [`ReplDriver`] that reads commands line by line, e.g. from
+/// a script or a pipe.
+///
+/// Passwords are read from `$CRYPT_CLIENT_PASSWORD` if it is set, otherwise from the terminal.
+/// With `json_errors`, failed commands are reported on stderr as one JSON object per line, see
+/// [`ReplError`]. [`has_failed`](Self::has_failed) tells whether any were.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::{BatchReplDriver, ReplDriver};
+///
+/// let script = "crypt list\nexit 0\n".as_bytes();
+/// let mut driver = BatchReplDriver::new(script, true);
+/// assert_eq!(driver.prompt_line("> ").unwrap(), "crypt list");
+/// assert_eq!(driver.prompt_line("> ").unwrap(), "exit 0");
+/// assert!(driver.prompt_line("> ").is_err());
+/// assert!(!driver.has_failed());
+/// ```
+pub struct BatchReplDriver<R> {
+    lines: R,
+    json_errors: bool,
+    failed: bool,
+}
+
+impl<R: BufRead> BatchReplDriver<R> {
+    pub fn new(lines: R, json_errors: bool) -> Self {
+        Self { lines, json_errors, failed: false }
+    }
+
+    /// Whether an error was reported, so a script that carries on past a failed command and
+    /// ends with `exit 0` still fails.
+    #[must_use]
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchDriverError {
+    /// The input ended before an `exit` command.
+    EndOfInput,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BatchDriverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::EndOfInput => f.write_str("input ended without an exit command"),
+            Self::Io(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for BatchDriverError {}
+
+impl From<std::io::Error> for BatchDriverError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl<R: BufRead> ReplDriver for BatchReplDriver<R> {
+    type Error = BatchDriverError;
+
+    fn print<T: fmt::Display>(&mut self, s: T) {
+        print!("{}", s);
+    }
+
+    fn eprint<T: fmt::Display>(&mut self, s: T) {
+        eprint!("{}", s);
+    }
+
+    fn clear_screen(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn prompt_line(&mut self, _prompt: &str) -> Result<String, Self::Error> {
+        let mut line = String::new();
+        if self.lines.read_line(&mut line)? == 0 {
+            return Err(BatchDriverError::EndOfInput);
+        }
+        Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+    }
+
+    /// A password from `$CRYPT_CLIENT_PASSWORD` would be wrong again.
+    fn can_retry_password(&self) -> bool {
+        std::env::var_os(PASSWORD_ENV).is_none()
+    }
+
+    /// A script can't know to answer questions that only come up sometimes.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    fn prompt_password(&mut self, prompt: &str) -> Result<String, Self::Error> {
+        if let Ok(password) = std::env::var(PASSWORD_ENV) {
+            return Ok(password);
+        }
+        let password = rpassword::read_password_from_tty(Some(prompt))?;
+        Ok(password)
+    }
+
+    fn report_error(&mut self, error: &ReplError) {
+        self.failed = true;
+        if self.json_errors {
+            match serde_json::to_string(error) {
+                Ok(json) => eprintln!("{}", json),
+                Err(_) => eprintln!("{}", error)
+            }
+        } else {
+            eprintln!("{}", error);
+        }
+    }
 }
@@ -70,4 +70,9 @@ impl ReplDriver for MockDriver {
             Self::MockAll { password, .. } => Ok(password.clone())
         }
     }
+
+    fn select(&mut self, prompt: &str, options: &[&str]) -> Result<usize, Self::Error> {
+        let answer = self.prompt_line(prompt)?;
+        Ok(answer.trim().parse::<usize>().ok().filter(|choice| (1..=options.len()).contains(choice)).map_or(0, |choice| choice - 1))
+    }
 }
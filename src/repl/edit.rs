@@ -0,0 +1,174 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use rand::Rng;
+use zeroize::Zeroizing;
+
+/// Returns a directory for temporary files holding decrypted values, preferring `/dev/shm` so
+/// they never reach a disk.
+fn secret_temp_dir() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    if shm.is_dir() {
+        shm.to_path_buf()
+    } else {
+        std::env::temp_dir()
+    }
+}
+
+/// Creates a new file only the current user can read and write.
+pub(super) fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Overwrites the contents of `file` with zeros before it is removed.
+fn shred(file: &mut File) -> std::io::Result<()> {
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(0))?;
+    std::io::copy(&mut std::io::repeat(0).take(len), file)?;
+    file.sync_all()
+}
+
+/// Writes `value` to a private temporary file, runs `tool` with the file's path as its last
+/// argument, and returns the file's contents once the tool exits. The file is shredded and
+/// removed afterwards, whether or not the tool succeeded.
+///
+/// Editors usually end files with a newline, so one is removed from the result if `value` didn't
+/// end with one.
+pub fn edit_with(value: &str, tool: &[impl AsRef<str>]) -> std::io::Result<Zeroizing<String>> {
+    let (program, args) = tool.split_first()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no tool given"))?;
+    let path = secret_temp_dir().join(format!("crypt-client-{:016x}", rand::thread_rng().gen::<u64>()));
+    let mut file = create_private(&path)?;
+    let result = file.write_all(value.as_bytes())
+        .and_then(|()| file.sync_all())
+        .and_then(|()| Command::new(program.as_ref()).args(args.iter().map(AsRef::as_ref)).arg(&path).status())
+        .and_then(|status| {
+            if !status.success() {
+                return Err(std::io::Error::other(format!("{} failed with {}", program.as_ref(), status)));
+            }
+            // The tool may have replaced the file rather than writing to it.
+            let mut edited = Zeroizing::new(String::new());
+            File::open(&path)?.read_to_string(&mut edited)?;
+            if !value.ends_with('\n') && edited.ends_with('\n') {
+                edited.pop();
+            }
+            Ok(edited)
+        });
+    // A tool that removed the file has left nothing to clean up.
+    let gone = |result: std::io::Result<()>| match result {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result
+    };
+    let shredded = gone(OpenOptions::new().write(true).open(&path).and_then(|mut edited| shred(&mut edited)));
+    let shredded_original = shred(&mut file);
+    let removed = gone(std::fs::remove_file(&path));
+    let edited = result?;
+    shredded.and(shredded_original).and(removed)?;
+    Ok(edited)
+}
+
+/// The environment variable that points at the file holding `key`: the key upper-cased, with
+/// anything but letters and digits replaced by `_`, followed by `_FILE`.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::file_variable;
+///
+/// assert_eq!(file_variable("db/password"), "DB_PASSWORD_FILE");
+/// assert_eq!(file_variable("2fa-seed"), "_2FA_SEED_FILE");
+/// ```
+///
+#[must_use]
+pub fn file_variable(key: &str) -> String {
+    let mut name: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_uppercase() || c == '_') {
+        name.insert(0, '_');
+    }
+    name.push_str("_FILE");
+    name
+}
+
+/// Writes each `(key, value)` pair to a private file in a new private directory, preferring
+/// `/dev/shm`, and runs `command` with [`file_variable`] of each key set to the path of its
+/// file, the convention containers use for secrets. Everything is shredded and removed once the
+/// command exits, whether or not it succeeded.
+pub fn exec_with_files<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>, command: &[impl AsRef<str>]) -> std::io::Result<ExitStatus> {
+    let (program, args) = command.split_first()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no command given"))?;
+    let mut variables: Vec<(String, &str, &str)> = Vec::new();
+    for (key, value) in entries {
+        let variable = file_variable(key);
+        if let Some((_, other, _)) = variables.iter().find(|(existing, _, _)| *existing == variable) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} and {} would both be {}", other, key, variable)));
+        }
+        variables.push((variable, key, value));
+    }
+    let dir = secret_temp_dir().join(format!("crypt-client-{:016x}", rand::thread_rng().gen::<u64>()));
+    create_private_dir(&dir)?;
+    // Each file is named after its variable without the suffix, so `A_FILE` reads `A`.
+    let file_path = |variable: &str| dir.join(variable.strip_suffix("_FILE").unwrap_or(variable));
+    let mut files = Vec::new();
+    let result = variables.iter()
+        .try_for_each(|(variable, _, value)| {
+            let path = file_path(variable);
+            let mut file = create_private(&path)?;
+            files.push(file.try_clone()?);
+            file.write_all(value.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| {
+            let mut command = Command::new(program.as_ref());
+            command.args(args.iter().map(AsRef::as_ref));
+            for (variable, _, _) in &variables {
+                command.env(variable, file_path(variable));
+            }
+            command.status()
+        });
+    let shredded = files.iter_mut().try_for_each(shred);
+    let removed = std::fs::remove_dir_all(&dir);
+    let status = result?;
+    shredded.and(removed)?;
+    Ok(status)
+}
+
+/// Creates a new directory only the current user can open.
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn keys_ending_in_file_get_files_of_their_own() {
+        let script = r#"test "$(cat "$A_FILE")" = 1 && test "$(cat "$A_FILE_FILE")" = 2 && test "$A_FILE" != "$A_FILE_FILE""#;
+        let status = exec_with_files([("A", "1"), ("A_FILE", "2")], &["sh", "-c", script]).unwrap();
+        assert!(status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tools_may_remove_the_file() {
+        let error = edit_with("value", &["sh", "-c", r#"rm "$0" && exit 3"#]).unwrap_err();
+        assert!(error.to_string().contains("failed with"), "{}", error);
+    }
+}
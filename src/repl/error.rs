@@ -0,0 +1,125 @@
+use std::fmt;
+use serde::Serialize;
+
+/// A stable identifier for each kind of [`ReplError`], so scripts can react to specific failures
+/// without matching on messages.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The command could not be parsed.
+    InvalidCommand,
+    /// An argument was parsed but can't be used, e.g. merging a crypt into itself.
+    InvalidArgument,
+    /// A regex given to a command is invalid.
+    InvalidPattern,
+    /// No file is open with the given alias.
+    UnknownAlias,
+    /// The key doesn't exist in the file.
+    UnknownKey,
+    /// A new password was rejected by the password policy or wasn't confirmed.
+    PasswordRejected,
+    /// The password couldn't be fetched from the file's secret source.
+    SecretUnavailable,
+    /// A session variable that isn't set was used.
+    UnknownVariable,
+    /// A value couldn't be edited with an external tool.
+    EditFailed,
+    /// A value couldn't be copied to the clipboard.
+    ClipboardFailed,
+    /// A command run with `exec` couldn't be started or failed.
+    ExecFailed,
+    /// A hook command couldn't be started or failed.
+    HookFailed,
+    /// A configured limit would be exceeded.
+    LimitExceeded,
+    /// A rename would overwrite an existing key.
+    RenameCollision,
+    /// A new key doesn't follow the naming policy of the file, see
+    /// [`KeyPolicy`](crate::repl::KeyPolicy).
+    KeyRejected,
+    /// A new value would replace an existing one that the command only replaces when asked to.
+    KeyExists,
+    /// The file to write already exists.
+    FileExists,
+    /// A crypt doesn't match its manifest, or the manifest's signature is invalid.
+    ManifestMismatch,
+    /// The password of a file is wrong, or the file was modified.
+    WrongPassword,
+    UnlockFailed,
+    LockFailed,
+    WriteFailed,
+}
+
+impl ErrorCode {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidCommand => "invalid_command",
+            Self::InvalidArgument => "invalid_argument",
+            Self::InvalidPattern => "invalid_pattern",
+            Self::UnknownAlias => "unknown_alias",
+            Self::UnknownKey => "unknown_key",
+            Self::PasswordRejected => "password_rejected",
+            Self::SecretUnavailable => "secret_unavailable",
+            Self::UnknownVariable => "unknown_variable",
+            Self::EditFailed => "edit_failed",
+            Self::ClipboardFailed => "clipboard_failed",
+            Self::ExecFailed => "exec_failed",
+            Self::HookFailed => "hook_failed",
+            Self::LimitExceeded => "limit_exceeded",
+            Self::RenameCollision => "rename_collision",
+            Self::KeyRejected => "key_rejected",
+            Self::KeyExists => "key_exists",
+            Self::FileExists => "file_exists",
+            Self::ManifestMismatch => "manifest_mismatch",
+            Self::WrongPassword => "wrong_password",
+            Self::UnlockFailed => "unlock_failed",
+            Self::LockFailed => "lock_failed",
+            Self::WriteFailed => "write_failed"
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A failed command, reported through [`ReplDriver::report_error`](crate::repl::ReplDriver::report_error).
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::{ErrorCode, ReplError};
+///
+/// let error = ReplError::new(ErrorCode::UnknownAlias, 3, "No files are open with the alias: work");
+/// assert_eq!(error.to_string(), "No files are open with the alias: work");
+/// assert_eq!(
+///     serde_json::to_string(&error).unwrap(),
+///     r#"{"code":"unknown_alias","message":"No files are open with the alias: work","command_index":3}"#
+/// );
+/// ```
+///
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ReplError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// The 1-based index of the command that failed, counting every line read at the prompt.
+    pub command_index: usize,
+}
+
+impl ReplError {
+    #[must_use]
+    pub fn new(code: ErrorCode, command_index: usize, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), command_index }
+    }
+}
+
+impl fmt::Display for ReplError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message.as_str())
+    }
+}
+
+impl std::error::Error for ReplError {}
@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::io::IsTerminal;
+
+/// The width assumed when it can't be detected.
+const DEFAULT_WIDTH: usize = 80;
+
+/// How command output is laid out.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::OutputStyle;
+///
+/// let style = OutputStyle { color: false, width: 24 };
+/// let table = style.table(&[
+///     vec!["user".to_string(), "alice".to_string()],
+///     vec!["aws/key".to_string(), "a value too long to fit".to_string()],
+/// ]);
+/// assert_eq!(table, "  user     alice\n  aws/key  a value too …\n");
+/// ```
+///
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OutputStyle {
+    /// Whether ANSI colors and styles may be used.
+    pub color: bool,
+    /// The number of columns lines are truncated to.
+    pub width: usize,
+}
+
+impl OutputStyle {
+    /// Detects the style from the environment.
+    ///
+    /// Color is used when stdout is a terminal, unless `NO_COLOR` is set or `CLICOLOR` is `0`.
+    /// `CLICOLOR_FORCE` enables color even when stdout isn't a terminal. The width is taken from
+    /// `COLUMNS`, then the terminal size.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let env_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0");
+        let color = if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            false
+        } else if env_set("CLICOLOR_FORCE") {
+            true
+        } else {
+            std::env::var_os("CLICOLOR").is_none_or(|value| value != "0") && std::io::stdout().is_terminal()
+        };
+        let width = std::env::var("COLUMNS").ok()
+            .and_then(|columns| columns.parse().ok())
+            .or_else(|| terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| usize::from(width)))
+            .unwrap_or(DEFAULT_WIDTH);
+        Self { color, width }
+    }
+
+    /// Makes `s` bold if color is enabled.
+    #[must_use]
+    pub fn bold<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if self.color {
+            Cow::Owned(format!("\x1b[1m{}\x1b[0m", s))
+        } else {
+            Cow::Borrowed(s)
+        }
+    }
+
+    /// Lays out `rows` as indented, aligned columns. The last column is truncated to fit the
+    /// width, and the first column is bold if color is enabled.
+    #[must_use]
+    pub fn table(&self, rows: &[Vec<String>]) -> String {
+        const INDENT: usize = 2;
+        const GAP: usize = 2;
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        // Leading columns may take up to half the width between them, the rest is left for the
+        // last column.
+        let leading_max = (self.width / 2 / columns.saturating_sub(1).max(1)).max(1);
+        let widths: Vec<usize> = (0..columns.saturating_sub(1))
+            .map(|column| rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count().min(leading_max))
+                .max()
+                .unwrap_or(0))
+            .collect();
+        let used = INDENT + widths.iter().map(|width| width + GAP).sum::<usize>();
+        let last_width = self.width.saturating_sub(used).max(8);
+
+        let mut out = String::new();
+        for row in rows {
+            let mut line = " ".repeat(INDENT);
+            for (column, cell) in row.iter().enumerate() {
+                let width = widths.get(column).copied().unwrap_or(last_width);
+                let cell = truncate(cell, width);
+                let padding = if column < widths.len() { width - cell.chars().count() + GAP } else { 0 };
+                if column == 0 {
+                    line.push_str(self.bold(&cell).as_ref());
+                } else {
+                    line.push_str(cell.as_ref());
+                }
+                line.push_str(" ".repeat(padding).as_str());
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        Self { color: false, width: DEFAULT_WIDTH }
+    }
+}
+
+/// Formats a number of bytes for people, in binary units.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::format_size;
+///
+/// assert_eq!(format_size(512), "512 B");
+/// assert_eq!(format_size(1536), "1.5 KiB");
+/// assert_eq!(format_size(200 * 1024 * 1024), "200.0 MiB");
+/// ```
+///
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Shortens `s` to at most `width` characters, replacing the end with an ellipsis if anything was
+/// cut off.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::truncate;
+///
+/// assert_eq!(truncate("short", 10), "short");
+/// assert_eq!(truncate("much too long", 8), "much to…");
+/// ```
+///
+#[must_use]
+pub fn truncate(s: &str, width: usize) -> Cow<'_, str> {
+    if s.chars().count() <= width {
+        return Cow::Borrowed(s);
+    }
+    let mut truncated: String = s.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    Cow::Owned(truncated)
+}
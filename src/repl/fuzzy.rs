@@ -0,0 +1,62 @@
+/// Scores how well `query` matches `candidate`, or returns [`None`] if the characters of `query`
+/// don't all appear in `candidate` in order. Matching ignores case, and higher scores are better:
+/// consecutive characters and characters at the start of a word, after `/`, `-`, `_`, `.` or a
+/// space, score extra.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::fuzzy_score;
+///
+/// assert!(fuzzy_score("dbpw", "db/password").is_some());
+/// assert!(fuzzy_score("pwdb", "db/password").is_none());
+/// assert!(fuzzy_score("pass", "db/password") > fuzzy_score("pass", "aws/prod/access"));
+/// ```
+///
+#[must_use]
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let mut score = 0;
+    let mut candidate_chars = candidate.chars().flat_map(char::to_lowercase).enumerate().peekable();
+    let mut previous_match = None;
+    let mut previous_char = None;
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (index, c) = candidate_chars.next()?;
+            let at_word_start = previous_char.is_none_or(|previous| matches!(previous, '/' | '-' | '_' | '.' | ' '));
+            previous_char = Some(c);
+            if c != query_char {
+                continue;
+            }
+            score += 1;
+            if at_word_start {
+                score += 3;
+            }
+            if previous_match.is_some_and(|previous| previous + 1 == index) {
+                score += 2;
+            }
+            previous_match = Some(index);
+            break;
+        }
+    }
+    Some(score)
+}
+
+/// Returns the candidates matching `query`, best match first. Ties keep their original order.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::fuzzy_filter;
+///
+/// let keys = ["aws/access-key", "db/password", "db/user"];
+/// assert_eq!(fuzzy_filter("dbp", keys.iter().copied()), vec!["db/password"]);
+/// assert_eq!(fuzzy_filter("", keys.iter().copied()), keys.to_vec());
+/// ```
+///
+pub fn fuzzy_filter<'a>(query: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut matches: Vec<(u32, &str)> = candidates
+        .filter_map(|candidate| fuzzy_score(query, candidate).map(|score| (score, candidate)))
+        .collect();
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+    matches.into_iter().map(|(_, candidate)| candidate).collect()
+}
@@ -0,0 +1,281 @@
+use std::fmt::Write;
+
+/// The usage and description of one REPL command, as listed by `help`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CommandHelp {
+    /// How the command is written, with placeholders like `<alias>`.
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+impl CommandHelp {
+    const fn new(usage: &'static str, description: &'static str) -> Self {
+        Self { usage, description }
+    }
+
+    /// The section of the reference the command is listed under, `crypt` and its subcommand for
+    /// crypt commands, `repl` for everything else.
+    #[must_use]
+    pub fn group(&self) -> &'static str {
+        let mut words = self.usage.splitn(3, ' ');
+        match (words.next(), words.next()) {
+            (Some("crypt"), Some(subcommand)) => &self.usage[..("crypt ".len() + subcommand.len())],
+            _ => "repl",
+        }
+    }
+}
+
+/// Every REPL command, in the order `help` lists them. New commands are added here, next to the
+/// other rows of the same command.
+pub const COMMANDS: &[CommandHelp] = &[
+    CommandHelp::new("clear", "Clear the screen"),
+    CommandHelp::new("help", "Print this help dialog"),
+    CommandHelp::new("help --all --markdown", "Print the reference of every command as markdown, for other frontends to display"),
+    CommandHelp::new("timings", "Show how long each command and key derivation took this session"),
+    CommandHelp::new("let <name> <value>", "Set a session variable, used as ${name} in later commands"),
+    CommandHelp::new("unset <name>", "Remove a session variable"),
+    CommandHelp::new("vars", "List all session variables"),
+    CommandHelp::new("if-set <name> then <command>", "Run the command only if the session variable is set"),
+    CommandHelp::new("source <filepath>", "Run the commands in a file, one per line, skipping blank lines and # comments"),
+    CommandHelp::new("exit <code>", "Exit the REPL, asking whether to save each file with unsaved changes"),
+    CommandHelp::new("exit <code> --save", "Save every open file and exit the REPL"),
+    CommandHelp::new("exit <code> --no-save", "Discard all changes and exit the REPL"),
+    CommandHelp::new("crypt list", "List all unsaved crypts with their descriptions"),
+    CommandHelp::new("crypt list --fingerprint", "Print each alias, a short hash of its data and of its file on disk, saved or unsaved and its path, tab-separated"),
+    CommandHelp::new("crypt save-all", "Save every open file with unsaved changes, then show what happened to each"),
+    CommandHelp::new("crypt unlock <alias> <filepath>", "Read and decrypt the specified file using the specified alias"),
+    CommandHelp::new("crypt unlock <alias> <dir>", "Create a new file in the directory, choosing from memorable names like brave-otter.crypt"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --dual", "Unlock a file that needs the passwords of two different people"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --cipher <aes-256-gcm/chacha20-poly1305>", "Unlock or create a file and encrypt it with the given cipher when it is next saved"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --keyfile <path>", "Unlock or create a file whose key is derived from the password and the keyfile's contents"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --compression <none/zstd>", "Unlock or create a file and compress it before it is encrypted when it is next saved"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --layout <single/chunked>", "Unlock or create a file and write it in chunks that can be recovered one by one when it is next saved"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --container <crypt/age>", "Create a file as an age file that age -d can decrypt with the password, age needs the age feature"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --container gpg", "Create a file encrypted to GPG public keys instead of a password, for builds with the gpg feature"),
+    CommandHelp::new("crypt unlock <alias> <filepath> --container openssl", "Create a file that openssl enc -d -aes-256-cbc -pbkdf2 can decrypt with the password, for builds with the openssl feature"),
+    CommandHelp::new("crypt lock <alias>", "Encrypt and write the file mapped to the specified alias"),
+    CommandHelp::new("crypt passwd <alias>", "Ask for a new password twice and re-encrypt the file with it when it is next saved"),
+    CommandHelp::new("crypt compact <alias> [--shred]", "Empty the trash, forget old deletions, rewrite the file and remove its backups and leftover temporary files, overwriting them first with --shred"),
+    CommandHelp::new("crypt migrate <alias>", "Rewrite the file with the configured cipher, kdf, compression and layout"),
+    CommandHelp::new("crypt sync <alias> [<filepath>]", "Merge the file on disk or another copy of it, by the clocks the sync crdt setting keeps"),
+    CommandHelp::new("crypt inspect <filepath>", "Print the format, cipher and size of a file without unlocking it"),
+    CommandHelp::new("crypt recipients <alias> [<key-id>...]", "List or replace the GPG keys a gpg file is encrypted to when it is next saved"),
+    CommandHelp::new("crypt recover <alias> <filepath>", "Open the intact entries of a damaged chunked file and list the keys that were lost"),
+    CommandHelp::new("crypt verify <filepath>", "Check a file's header, authentication and entries without opening it, reporting the stage that failed"),
+    CommandHelp::new("crypt backups <alias>", "List the backups of a file, oldest first"),
+    CommandHelp::new("crypt backups <alias> restore <backup>", "Replace the data with a backup, written to disk when the file is next saved"),
+    CommandHelp::new("crypt share <alias> <key> <filepath> [--expires <n><s/m/h/d>]", "Write one entry sealed with a one-time key, printed to pass on separately, for a day by default"),
+    CommandHelp::new("crypt receive <alias> <filepath>", "Ask for the one-time key, store the shared entry and delete the share"),
+    CommandHelp::new("crypt export-k8s <alias> --name <name> [keys...]", "Print the keys, or every key, as a Kubernetes Secret manifest with base64 values"),
+    CommandHelp::new("crypt extract-all <alias> <dir> [--prefix <prefix>] [--mode <octal>] [--overwrite] [--skip-existing]", "Write each value, or those under prefix, to a file at its key's path below dir, private by default (mode 600)"),
+    CommandHelp::new("crypt export-armor <filepath> <armor-filepath>", "Write an encrypted file as pasteable text, without unlocking it"),
+    CommandHelp::new("crypt import-armor <armor-filepath> <filepath>", "Write the encrypted file held in pasted text to a new file"),
+    CommandHelp::new("crypt manifest <alias> <filepath>", "Write the keys and signed value hashes, but no values, to a file that can be committed"),
+    CommandHelp::new("crypt check-manifest <alias> <filepath>", "Show the keys added, removed or changed since the manifest was written"),
+    CommandHelp::new("crypt meta <alias> show", "Print the description and metadata of the crypt"),
+    CommandHelp::new("crypt meta <alias> describe <description>", "Set the description of the crypt, '' removes it"),
+    CommandHelp::new("crypt meta <alias> set <key> <value>", "Set a metadata field of the crypt"),
+    CommandHelp::new("crypt meta <alias> unset <key>", "Remove a metadata field of the crypt"),
+    CommandHelp::new("crypt meta <alias> set-setting <name> <value>", "Store autosave, copy_on_get, expiry_reminders, rotate_after (e.g. 90d), key_pattern (a regex new keys must match), key_max_depth, sync (manual or crdt) or tombstone_days (how long deletions sync, 90 by default) in the crypt"),
+    CommandHelp::new("crypt meta <alias> unset-setting <name>", "Remove a setting stored in the crypt, so the local config applies again"),
+    CommandHelp::new("crypt data <alias> list [--sort <last-accessed or reads>]", "List all keys, or the least recently or least often read first"),
+    CommandHelp::new("crypt data <alias> get <key> [--print]", "Print the value of the specified key, or copy it if copy_on_get is set"),
+    CommandHelp::new("crypt data <alias> get <key> --copy", "Copy the value of the specified key to the clipboard"),
+    CommandHelp::new("crypt data <alias> set <key> <value> [--note <note>] [--force]", "Set the specified key/value pair and optional note, --force ignores size limits"),
+    CommandHelp::new("crypt data <alias> set <prefix> <value> --name", "Add an entry under the prefix, choosing from memorable names like brave-otter, as generate --name and an empty key do too"),
+    CommandHelp::new("crypt data <alias> field list <key>", "List the fields of a record, such as username, password, url, otp and notes"),
+    CommandHelp::new("crypt data <alias> field get <key> <field> [--print/--copy]", "Print or copy a field of a record, the password being the value of the key"),
+    CommandHelp::new("crypt data <alias> field set <key> <field> <value>", "Set a standard or custom field of a record, an empty value removes it"),
+    CommandHelp::new("crypt data <alias> show-big <key> [--for <duration>]", "Show the value of the specified key in large type, then clear the screen after 30s or the given duration, at most 5m, or when a key is pressed"),
+    CommandHelp::new("crypt data <alias> info <key>", "Print the note and length of the specified key"),
+    CommandHelp::new("crypt data <alias> search <term>", "List keys whose name or note contains the term"),
+    CommandHelp::new("crypt data <alias> keys [--prefix <prefix>] [--null]", "Print only the keys, one per line or NUL terminated, for scripts"),
+    CommandHelp::new("crypt data <alias> import-env --prefix <prefix>", "Store the environment variables starting with the prefix, without it, replacing existing keys"),
+    CommandHelp::new("crypt data <alias> pick [<query>]", "Choose a key from those fuzzy matching the query, then show, copy or describe it"),
+    CommandHelp::new("crypt data <alias> exec <key>... -- <command> [<args>...]", "Run a command with each value in a private file, pointed at by <KEY>_FILE, shredded afterwards"),
+    CommandHelp::new("crypt data <alias> move <pattern> <to-alias>", "Move the entries whose key matches the regex to another open crypt without unsaved changes, deleting them only once it is saved"),
+    CommandHelp::new("crypt data <alias> generate <key> <uuid/hex <bytes>/base64 <bytes>/ssh-ed25519> [--replace] [--name]", "Store a new random value of up to 1024 bytes, or an SSH keypair with its public key under <key>.pub for builds with the ssh feature"),
+    CommandHelp::new("crypt data <alias> edit-with <key> -- <tool> [<args>...]", "Edit a value with an external tool through a private, shredded temporary file"),
+    CommandHelp::new("crypt data <alias> rename <key> <new-key>", "Rename the specified key"),
+    CommandHelp::new("crypt data <alias> rename-prefix <old-prefix> <new-prefix> [--dry-run]", "Replace the prefix of every key starting with old-prefix"),
+    CommandHelp::new("crypt data <alias> rename --pattern <regex> <replacement> [--dry-run]", "Rewrite every key matching the regex, $1 etc. refer to groups"),
+    CommandHelp::new("crypt data <alias> clear [<prefix>]", "Delete every key, or every key starting with prefix, after confirmation"),
+    CommandHelp::new("crypt data <alias> tag <key> <tag>", "Add a tag to the specified key"),
+    CommandHelp::new("crypt data <alias> untag <key> <tag>", "Remove a tag from the specified key"),
+    CommandHelp::new("crypt data <alias> expire <key> <YYYY-MM-DD or never>", "Set or remove the date the specified key should be rotated by"),
+    CommandHelp::new("crypt data <alias> delete <key>", "Move the specified key to the trash, or delete it if the trash is off"),
+    CommandHelp::new("crypt data <alias> trash list", "List deleted keys and when they will be purged"),
+    CommandHelp::new("crypt data <alias> trash restore <key>", "Move a deleted key back out of the trash"),
+    CommandHelp::new("crypt data <alias> trash purge [<key>]", "Delete a key, or everything, in the trash for good"),
+    CommandHelp::new("crypt autosave <alias> <policy>", "Save changes automatically (policy: off, on-change or seconds like 60s)"),
+    CommandHelp::new("crypt merge <alias> <source-alias> [--on-conflict <policy>]", "Copy all keys from another open crypt (policy: keep, take or rename)"),
+    CommandHelp::new("crypt export <alias> [json] <filepath> [--prefix <prefix>] [--tag <tag>]", "Write matching keys and values to a new unencrypted JSON file"),
+    CommandHelp::new("crypt export <alias> bundle <filepath> [--prefix <prefix>] [--tag <tag>]", "Write matching entries to a new file encrypted with a new password, to hand to someone who imports it as a bundle"),
+    CommandHelp::new("crypt import <alias> bundle <filepath> [--overwrite] [--skip-existing]", "Merge the entries of a bundle, with their notes, tags and fields, asking for its password"),
+    CommandHelp::new("crypt import <alias> json <filepath> [--overwrite] [--skip-existing]", "Merge the keys and values of a JSON object, asking about existing keys unless a flag is given"),
+    CommandHelp::new("crypt import <alias> dotenv <filepath> [--overwrite] [--skip-existing]", "Merge the KEY=VALUE lines of a .env file, honoring quotes and comments"),
+    CommandHelp::new("crypt import <alias> bitwarden <filepath> [--overwrite] [--skip-existing]", "Merge the logins and secure notes of an unencrypted Bitwarden JSON export as <folder>/<name>/<field> keys"),
+    CommandHelp::new("crypt import <alias> browser <filepath> [--overwrite] [--skip-existing]", "Merge the saved passwords Chrome, Edge or Firefox exported as CSV as <name>/<field> keys, naming logins by site"),
+    CommandHelp::new("crypt import <alias> 1pux <filepath> [--overwrite] [--skip-existing]", "Merge the items of a 1Password 1PUX export as <vault>/<title>/<field> keys, listing what couldn't be imported, for builds with the onepassword feature"),
+    CommandHelp::new("crypt import <alias> pass <dir> [--overwrite] [--skip-existing]", "Merge the entries of a pass password store, such as ~/.password-store, decrypted with gpg, for builds with the gpg feature"),
+    CommandHelp::new("crypt import <alias> csv <filepath> [--key-column <column>] [--value-column <column>] [--no-header] [--overwrite] [--skip-existing]", "Merge keys and values from CSV columns, by number or header name (default: columns 1 and 2)"),
+    CommandHelp::new("crypt clone <alias> <filepath> [--prefix <prefix>]", "Copy an open crypt, or the keys under prefix, to a new password-protected file"),
+];
+
+/// Renders [`COMMANDS`] as the aligned table printed by `help`.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::usage_text;
+///
+/// let usage = usage_text();
+/// assert!(usage.starts_with("Crypt REPL usage:\n| Command "));
+/// assert!(usage.lines().any(|line| line.starts_with("| crypt lock <alias> ") && line.ends_with(" |")));
+/// ```
+///
+#[must_use]
+pub fn usage_text() -> String {
+    let usage_width = COMMANDS.iter().map(|command| command.usage.len()).max().unwrap_or(0);
+    let description_width = COMMANDS.iter().map(|command| command.description.len()).max().unwrap_or(0);
+    let mut text = String::from("Crypt REPL usage:\n");
+    let _ = writeln!(text, "| {:usage_width$} | {:description_width$} |", "Command", "Description");
+    let _ = writeln!(text, "|{}|{}|", "-".repeat(usage_width + 2), "-".repeat(description_width + 2));
+    for command in COMMANDS {
+        let _ = writeln!(text, "| {:usage_width$} | {:description_width$} |", command.usage, command.description);
+    }
+    text
+}
+
+/// The table `help` printed before it was generated from [`COMMANDS`], without the commands added
+/// since.
+#[deprecated(since = "0.1.0", note = "use usage_text(), which lists every command")]
+pub const USAGE_TEXT: &str = "Crypt REPL usage:
+| Command                                                                  | Description                                                                                     |
+|--------------------------------------------------------------------------|-------------------------------------------------------------------------------------------------|
+| clear                                                                    | Clear the screen                                                                                |
+| help                                                                     | Print this help dialog                                                                          |
+| timings                                                                  | Show how long each command and key derivation took this session                                 |
+| let <name> <value>                                                       | Set a session variable, used as ${name} in later commands                                       |
+| unset <name>                                                             | Remove a session variable                                                                       |
+| vars                                                                     | List all session variables                                                                      |
+| if-set <name> then <command>                                             | Run the command only if the session variable is set                                             |
+| source <filepath>                                                        | Run the commands in a file, one per line, skipping blank lines and # comments                   |
+| exit <code>                                                              | Exit the REPL, asking whether to save each file with unsaved changes                            |
+| exit <code> --save                                                       | Save every open file and exit the REPL                                                          |
+| exit <code> --no-save                                                    | Discard all changes and exit the REPL                                                           |
+| crypt list                                                               | List all unsaved crypts with their descriptions                                                 |
+| crypt save-all                                                           | Save every open file with unsaved changes, then show what happened to each                      |
+| crypt unlock <alias> <filepath> --dual                                   | Unlock a file that needs the passwords of two different people                                  |
+| crypt unlock <alias> <filepath> --cipher <aes-256-gcm/chacha20-poly1305> | Unlock or create a file and encrypt it with the given cipher when it is next saved              |
+| crypt unlock <alias> <filepath> --keyfile <path>                         | Unlock or create a file whose key is derived from the password and the keyfile's contents       |
+| crypt unlock <alias> <filepath>                                          | Read and decrypt the specified file using the specified alias                                   |
+| crypt lock <alias>                                                       | Encrypt and write the file mapped to the specified alias                                        |
+| crypt passwd <alias>                                                     | Ask for a new password twice and re-encrypt the file with it when it is next saved              |
+| crypt unlock <alias> <filepath> --compression <none/zstd>                | Unlock or create a file and compress it before it is encrypted when it is next saved            |
+| crypt inspect <filepath>                                                 | Print the format, cipher and size of a file without unlocking it                                |
+| crypt backups <alias>                                                    | List the backups of a file, oldest first                                                        |
+| crypt backups <alias> restore <backup>                                   | Replace the data with a backup, written to disk when the file is next saved                     |
+| crypt share <alias> <key> <filepath> [--expires <n><s/m/h/d>]            | Write one entry sealed with a one-time key, printed to pass on separately, for a day by default |
+| crypt receive <alias> <filepath>                                         | Ask for the one-time key, store the shared entry and delete the share                           |
+| crypt export-k8s <alias> --name <name> [keys...]                         | Print the keys, or every key, as a Kubernetes Secret manifest with base64 values                |
+| crypt export-armor <filepath> <armor-filepath>                           | Write an encrypted file as pasteable text, without unlocking it                                 |
+| crypt import-armor <armor-filepath> <filepath>                           | Write the encrypted file held in pasted text to a new file                                      |
+| crypt manifest <alias> <filepath>                                        | Write the keys and signed value hashes, but no values, to a file that can be committed          |
+| crypt check-manifest <alias> <filepath>                                  | Show the keys added, removed or changed since the manifest was written                          |
+| crypt meta <alias> show                                                  | Print the description and metadata of the crypt                                                 |
+| crypt meta <alias> describe <description>                                | Set the description of the crypt, '' removes it                                                 |
+| crypt meta <alias> set <key> <value>                                     | Set a metadata field of the crypt                                                               |
+| crypt meta <alias> unset <key>                                           | Remove a metadata field of the crypt                                                            |
+| crypt meta <alias> set-setting <name> <value>                            | Store autosave, copy_on_get or expiry_reminders in the crypt, overriding the local config       |
+| crypt meta <alias> unset-setting <name>                                  | Remove a setting stored in the crypt, so the local config applies again                         |
+| crypt data <alias> list [--sort <last-accessed or reads>]                | List all keys, or the least recently or least often read first                                  |
+| crypt data <alias> get <key> [--print]                                   | Print the value of the specified key, or copy it if copy_on_get is set                          |
+| crypt data <alias> get <key> --copy                                      | Copy the value of the specified key to the clipboard                                            |
+| crypt data <alias> set <key> <value> [--note <note>] [--force]           | Set the specified key/value pair and optional note, --force ignores size limits                 |
+| crypt data <alias> info <key>                                            | Print the note and length of the specified key                                                  |
+| crypt data <alias> search <term>                                         | List keys whose name or note contains the term                                                  |
+| crypt data <alias> keys [--prefix <prefix>] [--null]                     | Print only the keys, one per line or NUL terminated, for scripts                                |
+| crypt data <alias> import-env --prefix <prefix>                          | Store the environment variables starting with the prefix, without it, replacing existing keys   |
+| crypt data <alias> pick [<query>]                                        | Choose a key from those fuzzy matching the query, then show, copy or describe it                |
+| crypt data <alias> exec <key>... -- <command> [<args>...]                | Run a command with each value in a private file, pointed at by <KEY>_FILE, shredded afterwards  |
+| crypt data <alias> edit-with <key> -- <tool> [<args>...]                 | Edit a value with an external tool through a private, shredded temporary file                   |
+| crypt data <alias> rename <key> <new-key>                                | Rename the specified key                                                                        |
+| crypt data <alias> rename-prefix <old-prefix> <new-prefix> [--dry-run]   | Replace the prefix of every key starting with old-prefix                                        |
+| crypt data <alias> rename --pattern <regex> <replacement> [--dry-run]    | Rewrite every key matching the regex, $1 etc. refer to groups                                   |
+| crypt data <alias> clear [<prefix>]                                      | Delete every key, or every key starting with prefix, after confirmation                         |
+| crypt data <alias> tag <key> <tag>                                       | Add a tag to the specified key                                                                  |
+| crypt data <alias> untag <key> <tag>                                     | Remove a tag from the specified key                                                             |
+| crypt data <alias> expire <key> <YYYY-MM-DD or never>                    | Set or remove the date the specified key should be rotated by                                   |
+| crypt data <alias> delete <key>                                          | Delete the specified key                                                                        |
+| crypt autosave <alias> <policy>                                          | Save changes automatically (policy: off, on-change or seconds like 60s)                         |
+| crypt merge <alias> <source-alias> [--on-conflict <policy>]              | Copy all keys from another open crypt (policy: keep, take or rename)                            |
+| crypt export <alias> <filepath> [--prefix <prefix>] [--tag <tag>]        | Write matching keys and values to a new unencrypted JSON file                                   |
+| crypt clone <alias> <filepath> [--prefix <prefix>]                       | Copy an open crypt, or the keys under prefix, to a new password-protected file                  |
+";
+
+/// Renders [`COMMANDS`] as a markdown reference with a table for each [`CommandHelp::group`], for
+/// frontends that show the built-in help themselves.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::markdown_reference;
+///
+/// let reference = markdown_reference();
+/// assert!(reference.starts_with("# Crypt REPL commands\n\n## repl\n\n| Command | Description |\n|---|---|\n| `clear` | Clear the screen |\n"));
+/// assert!(reference.contains("\n## crypt data\n"));
+/// assert!(reference.contains(r"copy\_on\_get"));
+/// ```
+///
+#[must_use]
+pub fn markdown_reference() -> String {
+    let mut groups: Vec<(&str, Vec<&CommandHelp>)> = Vec::new();
+    for command in COMMANDS {
+        match groups.iter_mut().find(|(group, _)| *group == command.group()) {
+            Some((_, commands)) => commands.push(command),
+            None => groups.push((command.group(), vec![command])),
+        }
+    }
+    let mut text = String::from("# Crypt REPL commands\n");
+    for (group, commands) in groups {
+        let _ = write!(text, "\n## {}\n\n| Command | Description |\n|---|---|\n", group);
+        for command in commands {
+            let _ = writeln!(text, "| `{}` | {} |", command.usage, escape_markdown(command.description));
+        }
+    }
+    text
+}
+
+/// Escapes the characters in plain text that markdown would read as emphasis or HTML.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '<' | '>' | '[' | ']' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_of_a_command_are_listed_together() {
+        let command = |help: &CommandHelp| {
+            let words: Vec<&str> = help.usage.split(' ').collect();
+            if words[0] == "crypt" { words[..2].join(" ") } else { words[0].to_string() }
+        };
+        let mut seen: Vec<String> = Vec::new();
+        for help in COMMANDS {
+            let name = command(help);
+            if seen.last() != Some(&name) {
+                assert!(!seen.contains(&name), "the rows of {} are apart", name);
+                seen.push(name);
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use serde::Deserialize;
+use super::PASSWORD_ENV;
+
+/// A point in the life of an open file that hooks run at.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HookEvent {
+    /// The file was unlocked and opened under an alias.
+    PostUnlock,
+    /// The file is about to be written.
+    PreSave,
+    /// The file was written.
+    PostSave,
+    /// An entry was added, changed, renamed or deleted, including its tags and expiry. A rename
+    /// changes both the old and the new key.
+    EntryChanged,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::PostUnlock => "post-unlock",
+            Self::PreSave => "pre-save",
+            Self::PostSave => "post-save",
+            Self::EntryChanged => "entry-changed"
+        })
+    }
+}
+
+/// What a hook is told about an event. Values and passwords are never passed to hooks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HookContext<'a> {
+    pub event: HookEvent,
+    pub alias: &'a str,
+    pub filepath: &'a Path,
+    /// The changed key, for [`HookEvent::EntryChanged`].
+    pub key: Option<&'a str>,
+}
+
+/// Shell commands to run on each event, from the `[hooks]` table of the config.
+///
+/// Each command is run with `sh -c` once the event happens, with the event name in
+/// `$CRYPT_EVENT`, the alias in `$CRYPT_ALIAS`, the file path in `$CRYPT_FILE` and, for
+/// `entry_changed`, the key in `$CRYPT_KEY`. A failing hook is reported, but never stops the
+/// file from being saved or changed.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookCommands {
+    pub post_unlock: Option<String>,
+    pub pre_save: Option<String>,
+    pub post_save: Option<String>,
+    pub entry_changed: Option<String>,
+}
+
+impl HookCommands {
+    fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::PostUnlock => self.post_unlock.as_deref(),
+            HookEvent::PreSave => self.pre_save.as_deref(),
+            HookEvent::PostSave => self.post_save.as_deref(),
+            HookEvent::EntryChanged => self.entry_changed.as_deref()
+        }
+    }
+}
+
+type HookCallback = Box<dyn FnMut(&HookContext<'_>)>;
+
+/// The configured [`HookCommands`] and the callbacks added by the program embedding the REPL.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    commands: HookCommands,
+    callbacks: Vec<HookCallback>,
+    /// Why hooks failed since they were last reported.
+    failures: Vec<String>,
+}
+
+impl Hooks {
+    pub fn set_commands(&mut self, commands: HookCommands) {
+        self.commands = commands;
+    }
+
+    pub fn add_callback(&mut self, callback: impl FnMut(&HookContext<'_>) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Calls every callback, then runs the command configured for the event, if any.
+    pub fn fire(&mut self, context: &HookContext<'_>) {
+        for callback in &mut self.callbacks {
+            callback(context);
+        }
+        let Some(command) = self.commands.command(context.event) else {
+            return;
+        };
+        let mut command_line = Command::new("sh");
+        command_line.arg("-c").arg(command)
+            .env("CRYPT_EVENT", context.event.to_string())
+            .env("CRYPT_ALIAS", context.alias)
+            .env("CRYPT_FILE", context.filepath)
+            .env_remove("CRYPT_KEY")
+            .env_remove(PASSWORD_ENV)
+            .stdin(Stdio::null());
+        if let Some(key) = context.key {
+            command_line.env("CRYPT_KEY", key);
+        }
+        match command_line.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.failures.push(format!("The {} hook for {} failed with {}", context.event, context.alias, status)),
+            Err(error) => self.failures.push(format!("The {} hook for {} could not be run: {}", context.event, context.alias, error))
+        }
+    }
+
+    /// Returns why hooks failed since this was last called.
+    pub fn take_failures(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.failures)
+    }
+}
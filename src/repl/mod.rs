@@ -1,195 +1,2628 @@
-use crate::file::{UnlockedFile, CryptFile, CryptFileError};
+use crate::file::{Backups, CipherKind, Compression, Container, FileFormat, FileInfo, KdfKind, Layout, Recovery, UnlockedCrypt, UnlockedFile, CryptData, CryptFile, Entry, CryptFileError, ConflictPolicy, MergeReport, RenameCollision, RestoreError, SyncMode, SyncReport};
+use crate::extract::{extract_all, Existing, ExtractError, ExtractOptions};
+use crate::generate::{memorable_name, Generated, Generator};
+use crate::import::{read_bitwarden, read_browser_csv, read_csv, read_dotenv};
+use crate::k8s::secret_manifest;
+use crate::manifest::Manifest;
+use crate::path::CryptPath;
+use crate::report::OutcomeReport;
+use crate::timestamp::format_utc;
+use crate::verify::{VerifyError, VerifyStage};
+use crate::secret::{dual_control_password, SecretSource};
+use crate::share;
+use crate::policy::{PasswordPolicy, PermissivePolicy};
+use regex::Regex;
+use zeroize::Zeroizing;
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::convert::TryFrom;
-use std::collections::HashMap;
+use std::fmt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
 
+mod autosave;
+mod clipboard;
+mod complete;
 mod driver;
+mod edit;
+mod error;
+mod format;
+mod fuzzy;
+mod help;
+mod hooks;
+mod large_type;
 mod parser;
+mod redact;
+mod session;
+mod settings;
+mod variables;
 
 #[cfg(feature = "dummy-drivers")]
 mod dummy_drivers;
 
+pub use autosave::*;
+pub use clipboard::*;
+pub use complete::*;
 pub use driver::*;
+pub use edit::*;
+pub use error::*;
+pub use format::*;
+pub use fuzzy::*;
+pub use help::*;
+pub use hooks::{HookCommands, HookContext, HookEvent};
+use hooks::Hooks;
+pub use large_type::*;
 pub use parser::*;
+pub use redact::*;
+pub use session::*;
+pub use settings::*;
+pub use variables::*;
 
 #[cfg(feature = "dummy-drivers")]
 pub use dummy_drivers::*;
-use std::path::PathBuf;
-
-pub const USAGE_TEXT: &str = "Crypt REPL usage:
-| Command                              | Description                                                   |
-|--------------------------------------|---------------------------------------------------------------|
-| clear                                | Clear the screen                                              |
-| help                                 | Print this help dialog                                        |
-| exit <code> [--no-save]              | Exit the REPL                                                 |
-| crypt list                           | List all unsaved crypts                                       |
-| crypt unlock <alias> <filepath>      | Read and decrypt the specified file using the specified alias |
-| crypt lock <alias>                   | Encrypt and write the file mapped to the specified alias      |
-| crypt data <alias> list              | List all keys                                                 |
-| crypt data <alias> get <key>         | Print the value of the specified key                          |
-| crypt data <alias> set <key> <value> | Set the specified key/value pair                              |
-| crypt data <alias> delete <key>      | Delete the specified key                                      |
-";
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+enum LockError {
+    /// No file is open with the alias.
+    NotOpen,
+    Crypt(CryptFileError),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotOpen => f.write_str("the file is not open"),
+            Self::Crypt(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+/// How deeply `source` commands may nest, so a file sourcing itself fails rather than overflowing
+/// the stack.
+const MAX_SOURCE_DEPTH: usize = 16;
+
+/// How many blank lines scroll the screen when it can't be cleared and its height is unknown.
+const DEFAULT_SCROLL_LINES: usize = 50;
+/// How long `show-big` shows a value for when no duration is given.
+pub const SHOW_BIG_DURATION: Duration = Duration::from_secs(30);
+/// The longest `show-big` shows a value for, so a value isn't left on an unattended screen.
+pub const MAX_SHOW_BIG_DURATION: Duration = Duration::from_mins(5);
+/// How many times a wrong password can be entered when unlocking a file.
+const MAX_PASSWORD_ATTEMPTS: usize = 3;
+/// How long a shared entry can be received for unless `--expires` says otherwise.
+const DEFAULT_SHARE_EXPIRY: Duration = Duration::from_hours(24);
+
+/// The number of single character insertions, deletions or substitutions needed to turn `a`
+/// into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns `true` for [`Container::Gpg`] in builds with the `gpg` feature.
+fn is_gpg(container: Container) -> bool {
+    #[cfg(feature = "gpg")]
+    return container == Container::Gpg;
+    #[cfg(not(feature = "gpg"))]
+    {
+        let _ = container;
+        false
+    }
+}
+
+/// Normalizes `path` so the same file is found however it was written, see [`CryptPath`].
+fn source_key(path: &Path) -> PathBuf {
+    CryptPath::new(path).map_or_else(|_| path.to_path_buf(), CryptPath::into_path_buf)
+}
+
+/// How `crypt unlock` was told to write a file from now on, instead of how it is already
+/// written, or how new files are written by default.
+#[derive(Debug, Clone, Copy, Default)]
+struct FormatChoice {
+    cipher: Option<CipherKind>,
+    compression: Option<Compression>,
+    layout: Option<Layout>,
+    container: Option<Container>,
+}
+
+/// A file unlocked in a [`Repl`], along with the secret needed to lock it again.
+struct OpenFile {
+    secret: SessionSecret,
+    file: UnlockedCrypt,
+    autosave: AutosavePolicy,
+    /// When the file was unlocked or last saved.
+    saved_at: Instant,
+}
+
+/// How long a single command took to execute, see [`Repl::timings`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommandTiming {
+    pub command_index: usize,
+    /// The command as entered, with any secret values redacted.
+    pub command: String,
+    pub duration: Duration,
+    /// How long deriving the key took, if the command unlocked a file.
+    pub kdf_duration: Option<Duration>,
+}
+
+/// How the user chose to resolve a single merge conflict.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ConflictResolution {
+    Policy(ConflictPolicy),
+    Edit(String),
+    Skip,
+}
+
+/// Hides a value while still hinting at how different two values are.
+fn mask(value: &str) -> String {
+    format!("<hidden, {} characters>", value.chars().count())
+}
+
+/// The file name a backup is listed and restored by.
+fn backup_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Guard rails on how much decrypted data a [`Repl`] may hold at once. [`None`] means unlimited.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplLimits {
+    /// The maximum number of files that can be unlocked at the same time.
+    pub max_open_files: Option<usize>,
+    /// The maximum combined [`payload_size`](crate::file::CryptData::payload_size) of all
+    /// unlocked files.
+    pub max_payload_size: Option<usize>,
+    /// The maximum size of a single value in bytes. `set --force` ignores it.
+    pub max_entry_size: Option<usize>,
+    /// The maximum [`payload_size`](crate::file::CryptData::payload_size) of a single file.
+    /// `set --force` ignores it.
+    pub max_crypt_size: Option<usize>,
+}
+
+/// Whether and how far ahead to warn about expiring entries when a file is unlocked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExpiryReminders {
+    pub enabled: bool,
+    /// Entries expiring within this many days are counted as expiring soon.
+    pub within_days: u64,
+}
+
+impl Default for ExpiryReminders {
+    fn default() -> Self {
+        Self { enabled: true, within_days: 7 }
+    }
+}
+
+/// Whether `data delete` moves entries to the trash, and how long they are kept there.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashPolicy {
+    pub enabled: bool,
+    /// Entries deleted more than this many days ago are purged when the file is unlocked.
+    pub keep_days: u64,
+}
+
+impl Default for TrashPolicy {
+    fn default() -> Self {
+        Self { enabled: true, keep_days: 30 }
+    }
+}
+
+impl TrashPolicy {
+    /// How long entries are kept in the trash, [`None`] if `keep_days` is too many to count.
+    fn keep(self) -> Option<Duration> {
+        self.keep_days.checked_mul(86_400).map(Duration::from_secs)
+    }
+}
+
+/// What to do with unknown flags and extra arguments after an otherwise valid command.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtraArguments {
+    /// Fail the command, naming the unexpected argument.
+    #[default]
+    Reject,
+    /// Warn about the unexpected argument and run the command anyway, as older versions did
+    /// without the warning.
+    Warn,
+}
 
 /// Uses a [`ReplDriver`] to prompt for input, parse that input into a [`ReplCommand`], act on
 /// that command and output the result.
 pub struct Repl<D> {
     driver: D,
-    open_files: HashMap<String, (String, CryptFile<UnlockedFile>)>,
+    open_files: HashMap<String, OpenFile>,
+    limits: ReplLimits,
+    password_policy: Box<dyn PasswordPolicy>,
+    output: OutputStyle,
+    /// Where to fetch passwords from, keyed by [`source_key`].
+    secret_sources: HashMap<PathBuf, Box<dyn SecretSource>>,
+    /// The autosave policy given to files when they are unlocked.
+    autosave: AutosavePolicy,
+    /// Whether `get` copies values to the clipboard instead of printing them, unless told otherwise.
+    copy_on_get: bool,
+    expiry_reminders: ExpiryReminders,
+    trash: TrashPolicy,
+    extra_arguments: ExtraArguments,
+    /// The cipher new files are encrypted with.
+    cipher: CipherKind,
+    /// The key derivation function new files are written with.
+    kdf: KdfKind,
+    /// How new files are compressed before they are encrypted.
+    compression: Compression,
+    /// How the payload of new files is laid out.
+    layout: Layout,
+    /// Whether and where files are backed up before they are overwritten.
+    backups: Backups,
+    hooks: Hooks,
+    /// The number of commands read so far, used to point errors at the command that caused them.
+    command_index: usize,
+    timings: Vec<CommandTiming>,
+    /// The key derivation time of a file unlocked by the current command.
+    unlock_kdf_duration: Option<Duration>,
+    /// Session variables set with `let`.
+    variables: BTreeMap<String, String>,
+    /// The number of `source` commands currently running.
+    source_depth: usize,
+}
+
+impl<D> Repl<D> {
+    /// The execution time of every successfully parsed command this session.
+    #[must_use]
+    pub fn timings(&self) -> &[CommandTiming] {
+        &self.timings
+    }
+
+    /// The open aliases and their keys, for completing commands.
+    #[must_use]
+    pub fn completions(&self) -> Completions {
+        let mut completions = Completions::default();
+        for (alias, open) in &self.open_files {
+            completions.add_alias(alias.as_str(), open.file.data().keys());
+        }
+        completions
+    }
+
+    /// Returns what the last word of `input` could be completed to: a command, an open alias, a
+    /// key of the alias named in `input` or a value of an option. Drivers get the same candidates
+    /// through [`ReplDriver::set_completions`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::repl::{MockDriver, Repl};
+    ///
+    /// let repl = Repl::new(MockDriver::Echo);
+    /// assert_eq!(repl.completion_candidates("crypt data <alias> ren"), vec!["rename", "rename-prefix"]);
+    /// assert_eq!(repl.completion_candidates("crypt unlock a b --cipher "), vec!["aes-256-gcm", "chacha20-poly1305"]);
+    /// assert!(repl.completion_candidates("crypt lock ").is_empty());
+    /// ```
+    ///
+    #[must_use]
+    pub fn completion_candidates(&self, input: &str) -> Vec<String> {
+        self.completions().candidates(input)
+    }
+
+    /// The combined payload size of all unlocked files.
+    fn payload_size(&self) -> usize {
+        self.open_files.values().map(|open| open.file.data().payload_size()).sum()
+    }
+
+    /// Returns how far over [`ReplLimits::max_payload_size`] the open files would be if
+    /// `additional` bytes were added, if at all.
+    fn payload_overflow(&self, additional: usize) -> Option<usize> {
+        let max = self.limits.max_payload_size?;
+        (self.payload_size() + additional).checked_sub(max).filter(|overflow| *overflow > 0)
+    }
+
+    /// Fires `event` for the file open under `alias`, if there is one.
+    fn fire_hook(&mut self, event: HookEvent, alias: &str, key: Option<&str>) {
+        if let Some(open) = self.open_files.get(alias) {
+            self.hooks.fire(&HookContext { event, alias, filepath: open.file.filepath(), key });
+        }
+    }
+
+    fn lock_file(&mut self, alias: impl AsRef<str>) -> Result<(), LockError> {
+        let alias = alias.as_ref();
+        self.fire_hook(HookEvent::PreSave, alias, None);
+        let Some(OpenFile { secret, file, autosave, saved_at }) = self.open_files.remove(alias) else {
+            return Err(LockError::NotOpen);
+        };
+        match secret.lock(file) {
+            Ok(locked) => {
+                self.hooks.fire(&HookContext { event: HookEvent::PostSave, alias, filepath: locked.filepath(), key: None });
+                Ok(())
+            }
+            Err((file, error)) => {
+                self.open_files.insert(alias.to_string(), OpenFile { secret, file, autosave, saved_at });
+                Err(LockError::Crypt(error))
+            }
+        }
+    }
+
+    /// Returns up to 3 open aliases that look like `alias`, closest first.
+    fn similar_aliases(&self, alias: &str) -> Vec<&str> {
+        let max_distance = (alias.chars().count() / 3).max(1);
+        let mut similar: Vec<(usize, &str)> = self.open_files.keys()
+            .map(|open| (edit_distance(alias, open), open.as_str()))
+            .filter(|(distance, open)| *distance <= max_distance || open.contains(alias) || alias.contains(open))
+            .collect();
+        similar.sort_unstable();
+        similar.into_iter().take(3).map(|(_, open)| open).collect()
+    }
+
+    /// Locks every open file, ordered by alias. Files that fail to lock stay open.
+    pub fn lock_all_files(&mut self) -> OutcomeReport {
+        let mut open_files: Vec<(String, OpenFile)> = std::mem::take(&mut self.open_files).into_iter().collect();
+        open_files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut report = OutcomeReport::new();
+        for (alias, OpenFile { secret, file, autosave, saved_at }) in open_files {
+            self.hooks.fire(&HookContext { event: HookEvent::PreSave, alias: &alias, filepath: file.filepath(), key: None });
+            match secret.lock(file) {
+                Ok(locked) => {
+                    self.hooks.fire(&HookContext { event: HookEvent::PostSave, alias: &alias, filepath: locked.filepath(), key: None });
+                    report.succeeded(alias, format!("saved and closed {}", locked.filepath().display()));
+                }
+                Err((file, error)) => {
+                    report.failed(alias.clone(), error.to_string());
+                    self.open_files.insert(alias, OpenFile { secret, file, autosave, saved_at });
+                }
+            }
+        }
+        report
+    }
+
+    /// Saves every open file with unsaved changes without closing it, ordered by alias.
+    pub fn save_all_files(&mut self) -> OutcomeReport {
+        let mut aliases: Vec<&String> = self.open_files.keys().collect();
+        aliases.sort();
+        let aliases: Vec<String> = aliases.into_iter().cloned().collect();
+        let mut report = OutcomeReport::new();
+        for alias in aliases {
+            let Some(open) = self.open_files.get_mut(&alias) else {
+                continue;
+            };
+            if !open.file.is_dirty() {
+                report.succeeded(alias, "no unsaved changes");
+                continue;
+            }
+            self.hooks.fire(&HookContext { event: HookEvent::PreSave, alias: &alias, filepath: open.file.filepath(), key: None });
+            match open.secret.save(&mut open.file) {
+                Ok(()) => {
+                    open.saved_at = Instant::now();
+                    self.hooks.fire(&HookContext { event: HookEvent::PostSave, alias: &alias, filepath: open.file.filepath(), key: None });
+                    report.succeeded(alias, format!("saved {}", open.file.filepath().display()));
+                }
+                Err(error) => report.failed(alias, error.to_string())
+            }
+        }
+        report
+    }
 }
 
-impl<D> Repl<D> {
-    fn unlock_file(&mut self, alias: String, filepath: impl Into<PathBuf>, password: String) -> Result<(), CryptFileError> {
-        let file = CryptFile::new(filepath.into());
-        let file = file.unlock(password.as_str())?;
-        self.open_files.insert(alias, (password, file));
+impl<D: ReplDriver> Repl<D> {
+    /// Creates a new [`Repl`] with `driver`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::repl::{ReplDriver, MockDriver, Repl};
+    ///
+    /// let repl = Repl::new(MockDriver::Echo);
+    /// ```
+    ///
+    pub fn new(driver: D) -> Self {
+        Self {
+            driver,
+            open_files: HashMap::new(),
+            limits: ReplLimits::default(),
+            password_policy: Box::new(PermissivePolicy),
+            output: OutputStyle::from_env(),
+            secret_sources: HashMap::new(),
+            copy_on_get: false,
+            expiry_reminders: ExpiryReminders::default(),
+            trash: TrashPolicy::default(),
+            extra_arguments: ExtraArguments::default(),
+            cipher: CipherKind::default(),
+            compression: Compression::default(),
+            layout: Layout::default(),
+            kdf: KdfKind::default(),
+            backups: Backups::default(),
+            hooks: Hooks::default(),
+            autosave: AutosavePolicy::default(),
+            command_index: 0,
+            timings: Vec::new(),
+            unlock_kdf_duration: None,
+            variables: BTreeMap::new(),
+            source_depth: 0,
+        }
+    }
+
+    /// The driver the REPL runs on, e.g. to ask a [`BatchReplDriver`] whether a command failed.
+    #[must_use]
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Sets the limits enforced when unlocking files and setting values. Files that are already
+    /// open are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::repl::{MockDriver, Repl, ReplLimits};
+    ///
+    /// let mut repl = Repl::new(MockDriver::Echo);
+    /// repl.set_limits(ReplLimits { max_open_files: Some(4), max_entry_size: Some(64 * 1024), ..ReplLimits::default() });
+    /// ```
+    ///
+    pub fn set_limits(&mut self, limits: ReplLimits) {
+        self.limits = limits;
+    }
+
+    /// Sets the policy new passwords are checked against when creating files. Defaults to
+    /// [`PermissivePolicy`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::policy::RulesPolicy;
+    /// use crypt_client::repl::{MockDriver, Repl};
+    ///
+    /// let mut repl = Repl::new(MockDriver::Echo);
+    /// repl.set_password_policy(RulesPolicy { min_length: 12, ..RulesPolicy::default() });
+    /// ```
+    ///
+    pub fn set_password_policy(&mut self, policy: impl PasswordPolicy + 'static) {
+        self.password_policy = Box::new(policy);
+    }
+
+    /// Fetches the password of the file at `filepath` from `source` instead of asking for it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::repl::{MockDriver, Repl};
+    /// use crypt_client::secret::ConfiguredSecretSource;
+    ///
+    /// let mut repl = Repl::new(MockDriver::Echo);
+    /// repl.set_secret_source("./work.crypt", ConfiguredSecretSource::Env("WORK_CRYPT_PASSWORD".to_string()));
+    /// ```
+    ///
+    pub fn set_secret_source(&mut self, filepath: impl AsRef<Path>, source: impl SecretSource + 'static) {
+        self.secret_sources.insert(source_key(filepath.as_ref()), Box::new(source));
+    }
+
+    /// Sets how list output is laid out. Defaults to [`OutputStyle::from_env`].
+    pub fn set_output_style(&mut self, style: OutputStyle) {
+        self.output = style;
+    }
+
+    /// Sets the autosave policy given to files unlocked from now on. Defaults to
+    /// [`AutosavePolicy::Off`], and can be changed per file with `crypt autosave`.
+    pub fn set_autosave(&mut self, policy: AutosavePolicy) {
+        self.autosave = policy;
+    }
+
+    /// Makes `get` copy values to the clipboard and print only a confirmation, unless `--print` is
+    /// given. Values are printed by default.
+    pub fn set_copy_on_get(&mut self, copy_on_get: bool) {
+        self.copy_on_get = copy_on_get;
+    }
+
+    /// Sets whether unlocking a file prints how many of its entries have expired or expire soon.
+    pub fn set_expiry_reminders(&mut self, expiry_reminders: ExpiryReminders) {
+        self.expiry_reminders = expiry_reminders;
+    }
+
+    /// Sets whether `data delete` moves entries to the trash and how long they are kept there.
+    pub fn set_trash(&mut self, trash: TrashPolicy) {
+        self.trash = trash;
+    }
+
+    /// Sets whether commands followed by unknown flags or extra arguments fail, which they do by
+    /// default, or only print a warning.
+    pub fn set_extra_arguments(&mut self, extra_arguments: ExtraArguments) {
+        self.extra_arguments = extra_arguments;
+    }
+
+    /// Sets the cipher new files are encrypted with, AES-256-GCM by default.
+    pub fn set_cipher(&mut self, cipher: CipherKind) {
+        self.cipher = cipher;
+    }
+
+    /// Sets how new files are compressed before they are encrypted, not at all by default.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Sets how the payload of new files is laid out, as a single encrypted block by default.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    /// Sets the key derivation function new files are written with, Argon2id by default.
+    pub fn set_kdf(&mut self, kdf: KdfKind) {
+        self.kdf = kdf;
+    }
+
+    /// Sets whether and where files unlocked from now on are backed up before they are
+    /// overwritten, off by default.
+    pub fn set_backups(&mut self, backups: Backups) {
+        self.backups = backups;
+    }
+
+    /// Sets the shell commands run when files are unlocked, saved or changed, see
+    /// [`HookCommands`].
+    pub fn set_hooks(&mut self, commands: HookCommands) {
+        self.hooks.set_commands(commands);
+    }
+
+    /// Adds a callback called on every [`HookEvent`], before the configured command, for programs
+    /// embedding the REPL.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use crypt_client::repl::{HookEvent, MockDriver, Repl};
+    ///
+    /// let mut repl = Repl::new(MockDriver::MockDefault("exit 0".to_string()));
+    /// repl.add_hook(|context| {
+    ///     if context.event == HookEvent::PostSave {
+    ///         println!("{} was saved to {}", context.alias, context.filepath.display());
+    ///     }
+    /// });
+    /// ```
+    ///
+    pub fn add_hook(&mut self, callback: impl FnMut(&HookContext<'_>) + 'static) {
+        self.hooks.add_callback(callback);
+    }
+
+    /// Saves every open file whose autosave policy is due.
+    fn autosave(&mut self) {
+        let mut failed = Vec::new();
+        for (alias, open) in &mut self.open_files {
+            let due = match open.autosave {
+                AutosavePolicy::Off => false,
+                AutosavePolicy::OnChange => true,
+                AutosavePolicy::Every(interval) => open.saved_at.elapsed() >= interval
+            };
+            if !due || !open.file.is_dirty() {
+                continue;
+            }
+            self.hooks.fire(&HookContext { event: HookEvent::PreSave, alias, filepath: open.file.filepath(), key: None });
+            match open.secret.save(&mut open.file) {
+                Ok(()) => {
+                    open.saved_at = Instant::now();
+                    self.hooks.fire(&HookContext { event: HookEvent::PostSave, alias, filepath: open.file.filepath(), key: None });
+                }
+                Err(error) => failed.push((alias.clone(), error))
+            }
+        }
+        for (alias, error) in failed {
+            self.report(ErrorCode::WriteFailed, format!("Failed to autosave {}: {}", alias, error));
+        }
+    }
+
+    /// Prints `report` as a table with a summary, and reports its failures with `code`.
+    fn print_report(&mut self, report: &OutcomeReport, code: ErrorCode) {
+        if report.is_empty() {
+            return;
+        }
+        self.driver.print(self.output.table(&report.rows()));
+        if report.is_success() {
+            self.driver.print(format!("{}\n", report.summary()));
+        } else {
+            self.report(code, report.summary());
+        }
+    }
+
+    /// Reports a failure of the current command through the driver.
+    fn report(&mut self, code: ErrorCode, message: impl Into<String>) {
+        let error = ReplError::new(code, self.command_index, message);
+        self.driver.report_error(&error);
+    }
+
+    fn report_unknown_alias(&mut self, alias: &str) {
+        let similar = self.similar_aliases(alias);
+        let message = if similar.is_empty() {
+            format!("No files are open with the alias: {}", alias)
+        } else {
+            format!("No files are open with the alias: {}, did you mean {}?", alias, similar.join(", "))
+        };
+        self.report(ErrorCode::UnknownAlias, message);
+    }
+
+    /// Returns `false` and reports why if `password` breaks the password policy.
+    fn check_password_policy(&mut self, password: &str) -> bool {
+        let violations = self.password_policy.check(password);
+        if violations.is_empty() {
+            return true;
+        }
+        let violations = violations.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+        self.report(ErrorCode::PasswordRejected, format!("The password does not meet the password policy, it {}", violations));
+        false
+    }
+
+    /// Fetches the password of `filepath` from its secret source, or asks the user if there is
+    /// none. Passwords for new files must follow the password policy. Returns [`None`] if no
+    /// usable password was given.
+    fn password_for(&mut self, filepath: &Path) -> Result<Option<Zeroizing<String>>, D::Error> {
+        let exists = filepath.exists();
+        if let Some(source) = self.secret_sources.get(&source_key(filepath)) {
+            match source.fetch() {
+                Ok(Some(password)) => {
+                    let usable = exists || self.check_password_policy(password.as_str());
+                    return Ok(usable.then_some(password));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    self.report(ErrorCode::SecretUnavailable, format!("Failed to fetch the password: {}", error));
+                    return Ok(None);
+                }
+            }
+        }
+        if exists {
+            Ok(Some(Zeroizing::new(self.driver.prompt_password("Enter password for file: ")?)))
+        } else {
+            self.prompt_new_password("Enter a password for the new file: ")
+        }
+    }
+
+    /// Asks two people in turn for their passwords to a dual control file and combines them,
+    /// see [`dual_control_password`]. Secret sources are not used, as they would let one person
+    /// unlock the file alone.
+    fn prompt_dual_control_password(&mut self, filepath: &Path) -> Result<Option<Zeroizing<String>>, D::Error> {
+        let exists = filepath.exists();
+        let mut passwords = Vec::with_capacity(2);
+        for person in ["first", "second"] {
+            let prompt = format!("Enter the {} person's password: ", person);
+            let password = if exists {
+                Some(Zeroizing::new(self.driver.prompt_password(&prompt)?))
+            } else {
+                self.prompt_new_password(&prompt)?
+            };
+            let Some(password) = password else {
+                return Ok(None);
+            };
+            passwords.push(password);
+        }
+        if passwords[0] == passwords[1] {
+            self.report(ErrorCode::PasswordRejected, "Dual control needs two different passwords");
+            return Ok(None);
+        }
+        Ok(Some(dual_control_password(&passwords[0], &passwords[1])))
+    }
+
+    /// Asks for a new password for the file open as `alias`, which is re-encrypted with it when it
+    /// is next saved.
+    fn change_password(&mut self, alias: &str) -> Result<(), D::Error> {
+        if !self.open_files.contains_key(alias) {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        }
+        let Some(new) = self.prompt_new_password("Enter the new password: ")? else {
+            return Ok(());
+        };
+        let Some(OpenFile { secret, file, .. }) = self.open_files.get_mut(alias) else {
+            return Ok(());
+        };
+        match secret.change_password(file, new) {
+            Ok(()) => self.driver.print(format!("Changed the password of {}, it takes effect when the file is next saved\n", alias)),
+            Err(error @ CryptFileError::WrongPassword) => self.report(ErrorCode::WrongPassword, format!("Failed to change the password: {}", error)),
+            Err(error @ CryptFileError::SamePassword) => self.report(ErrorCode::PasswordRejected, format!("Failed to change the password: {}", error)),
+            Err(error) => self.report(ErrorCode::UnlockFailed, format!("Failed to change the password: {}", error))
+        }
+        Ok(())
+    }
+
+    /// Prints the alias, the fingerprint of the data in memory and of the file on disk, whether
+    /// there are unsaved changes and the path of every open file, tab-separated and ordered by
+    /// alias, so the output is the same whatever the output style.
+    fn print_fingerprints(&mut self) {
+        let mut aliases: Vec<&String> = self.open_files.keys().collect();
+        aliases.sort();
+        for alias in aliases {
+            let open = &self.open_files[alias];
+            let contents = open.file.data().fingerprint().unwrap_or_else(|| "-".to_string());
+            let disk = match open.file.disk_fingerprint() {
+                Ok(disk) => disk.unwrap_or_else(|| "-".to_string()),
+                Err(error) => format!("unreadable ({})", error)
+            };
+            let state = if open.file.is_dirty() { "unsaved" } else { "saved" };
+            self.driver.print(format!("{}\t{}\t{}\t{}\t{}\n", alias, contents, disk, state, open.file.filepath().display()));
+        }
+    }
+
+    /// Rewrites the file open as `alias` with the configured cipher, key derivation function,
+    /// compression and layout, and prints how its format changed.
+    fn migrate_file(&mut self, alias: &str) {
+        let Some(open) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let filepath = open.file.filepath().clone();
+        let format = FileFormat { cipher: self.cipher, kdf: self.kdf.with_defaults(), compression: self.compression, layout: self.layout };
+        let before = CryptFile::new(filepath.clone()).inspect().ok();
+        self.fire_hook(HookEvent::PreSave, alias, None);
+        let Some(open) = self.open_files.get_mut(alias) else {
+            return;
+        };
+        if let Err(error) = open.secret.migrate(&mut open.file, format) {
+            self.report(ErrorCode::WriteFailed, format!("Failed to migrate {}: {}", alias, error));
+            return;
+        }
+        open.saved_at = Instant::now();
+        self.fire_hook(HookEvent::PostSave, alias, None);
+        let Ok(after) = CryptFile::new(filepath).inspect() else {
+            self.driver.print(format!("Migrated {}\n", alias));
+            return;
+        };
+        let describe = |info: &FileInfo| vec![info.format_version.to_string(), info.cipher.clone(), info.kdf.clone(), info.compression.to_string(), info.layout.to_string()];
+        let before = before.as_ref().map_or_else(|| vec!["new file".to_string(); 5], describe);
+        let rows: Vec<Vec<String>> = ["format version", "cipher", "kdf", "compression", "layout"].iter()
+            .zip(before.into_iter().zip(describe(&after)))
+            .map(|(field, (before, after))| vec![field.to_string(), before, after])
+            .collect();
+        self.driver.print(format!("Migrated {}:\n", alias));
+        self.driver.print(self.output.table(&rows));
+    }
+
+    /// Merges the copy of the file open as `alias` on disk, or the copy at `filepath`, into it by
+    /// the clocks kept since its `sync` setting was set to crdt, see [`CryptData::merge_replica`].
+    fn sync_file(&mut self, alias: &str, filepath: Option<&str>) {
+        let Some(open) = self.open_files.get_mut(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        if open.file.data().sync_mode() != SyncMode::Crdt {
+            self.report(ErrorCode::InvalidArgument, format!("{} isn't synced by clocks, run crypt meta {} set-setting sync crdt and save it first", alias, alias));
+            return;
+        }
+        let path = match filepath.map(CryptPath::new).transpose() {
+            Ok(path) => path.map_or_else(|| open.file.filepath().clone(), CryptPath::into_path_buf),
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Cannot sync {} with {}, {}", alias, filepath.unwrap_or_default(), error));
+                return;
+            }
+        };
+        if !path.is_file() {
+            self.report(ErrorCode::InvalidArgument, format!("Cannot sync {} with {}, it doesn't exist", alias, path.display()));
+            return;
+        }
+        let other = match open.secret.decrypt_copy(&open.file, &path) {
+            Ok(other) => other,
+            Err(error @ CryptFileError::WrongPassword) => {
+                self.report(ErrorCode::WrongPassword, format!("Failed to sync {} with {}: {}", alias, path.display(), error));
+                return;
+            }
+            Err(error) => {
+                self.report(ErrorCode::UnlockFailed, format!("Failed to sync {} with {}: {}", alias, path.display(), error));
+                return;
+            }
+        };
+        let SyncReport { added, updated, deleted, conflicts } = open.file.data_mut().merge_replica(&other, SystemTime::now());
+        self.driver.print(format!("Synced {} with {}:\n", alias, path.display()));
+        let rows = vec![
+            vec!["added".to_string(), added.len().to_string()],
+            vec!["updated".to_string(), updated.len().to_string()],
+            vec!["deleted".to_string(), deleted.len().to_string()],
+            vec!["conflicts".to_string(), conflicts.len().to_string()],
+        ];
+        self.driver.print(self.output.table(&rows));
+        if !conflicts.is_empty() {
+            // Both copies changed these before they had clocks, so set them again to stamp them.
+            self.driver.print(format!("Kept the entries of {} for these keys, changed in both copies before they were synced by clocks:\n", alias));
+            for key in &conflicts {
+                self.driver.print(format!("  {}\n", key));
+            }
+        }
+        self.entries_changed(alias, added.into_iter().chain(updated).chain(deleted));
+    }
+
+    /// Prints who the GPG file open as `alias` is encrypted to, or replaces them with
+    /// `recipients` from the next time it is saved.
+    fn recipients(&mut self, alias: &str, recipients: &[Cow<str>]) {
+        let Some(open) = self.open_files.get_mut(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        if !is_gpg(open.file.container()) {
+            self.report(ErrorCode::InvalidArgument, format!("{} is not a GPG file, only files created with --container gpg have recipients", alias));
+            return;
+        }
+        if !recipients.is_empty() {
+            open.file.set_recipients(recipients.iter().map(ToString::to_string).collect());
+            self.driver.print(format!("{} is encrypted to {} recipients when it is next saved\n", alias, recipients.len()));
+            return;
+        }
+        let listed = open.file.recipients().join("\n");
+        if listed.is_empty() {
+            self.driver.print(format!("{} has no recipients, add them with crypt recipients {} <key-id>...\n", alias, alias));
+        } else {
+            self.driver.print(format!("{}\n", listed));
+        }
+    }
+
+    /// Empties the trash of the file open as `alias`, rewrites it and removes its earlier
+    /// versions, reporting each one.
+    fn compact_file(&mut self, alias: &str, shred: bool) {
+        if !self.open_files.contains_key(alias) {
+            self.report_unknown_alias(alias);
+            return;
+        }
+        self.fire_hook(HookEvent::PreSave, alias, None);
+        let Some(open) = self.open_files.get_mut(alias) else {
+            return;
+        };
+        let compaction = match open.secret.compact(&mut open.file, shred) {
+            Ok(compaction) => compaction,
+            Err(error) => {
+                self.report(ErrorCode::WriteFailed, format!("Failed to compact {}: {}", alias, error));
+                return;
+            }
+        };
+        open.saved_at = Instant::now();
+        self.fire_hook(HookEvent::PostSave, alias, None);
+        let mut report = OutcomeReport::new();
+        for path in compaction.removed {
+            report.succeeded(path.display().to_string(), if shred { "overwritten and removed" } else { "removed" });
+        }
+        for (path, error) in compaction.failed {
+            report.failed(path.display().to_string(), error.to_string());
+        }
+        self.driver.print(format!("Purged {} entries from the trash and {} old tombstones and rewrote {}, {} earlier versions found\n", compaction.purged, compaction.tombstones, alias, report.len()));
+        self.print_report(&report, ErrorCode::WriteFailed);
+        if shred {
+            self.driver.print("Overwriting only reaches the old data on disks that write in place. SSDs, flash storage, copy-on-write filesystems and snapshots can keep copies, use full-disk encryption to be sure\n");
+        }
+    }
+
+    /// Prompts for a new password twice, returning [`None`] if the password breaks the password
+    /// policy or the two entries don't match.
+    fn prompt_new_password(&mut self, prompt: &str) -> Result<Option<Zeroizing<String>>, D::Error> {
+        let password = Zeroizing::new(self.driver.prompt_password(prompt)?);
+        if !self.check_password_policy(password.as_str()) {
+            return Ok(None);
+        }
+        let confirmation = Zeroizing::new(self.driver.prompt_password("Confirm password: ")?);
+        if password != confirmation {
+            self.report(ErrorCode::PasswordRejected, "Passwords do not match");
+            return Ok(None);
+        }
+        Ok(Some(password))
+    }
+
+    /// Execute a command, returning the exit command if the REPL should exit, which `exit` and
+    /// an `if-set` or sourced file running it do.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypt_client::repl::{ReplDriver, MockDriver, Repl, ReplCommand};
+    ///
+    /// let mut repl = Repl::new(MockDriver::Echo);
+    /// let command = ReplCommand::ClearScreen;
+    /// assert_eq!(repl.execute_command(&command).unwrap(), None);
+    /// ```
+    ///
+    pub fn execute_command(&mut self, command: &ReplCommand) -> Result<Option<ReplExitCommand>, D::Error> {
+        match command {
+            ReplCommand::ClearScreen => {
+                self.clear_screen();
+            }
+            ReplCommand::Help { markdown } => {
+                if *markdown {
+                    self.driver.print(markdown_reference());
+                } else {
+                    self.print_usage();
+                }
+            }
+            ReplCommand::Timings => {
+                self.print_timings();
+            }
+            ReplCommand::Exit(_) | ReplCommand::IfSet { .. } | ReplCommand::Source { .. } => return self.dispatch(command),
+            ReplCommand::Let { .. } | ReplCommand::Unset { .. } | ReplCommand::Vars => {
+                self.execute_variable_command(command);
+            }
+            ReplCommand::Crypt(ReplCryptCommand::List { fingerprint: true }) => self.print_fingerprints(),
+            ReplCommand::Crypt(ReplCryptCommand::List { fingerprint: false }) => {
+                self.driver.print(format!("{} files are currently open, holding {} bytes of decrypted data:\n", self.open_files.len(), self.payload_size()));
+                let mut rows: Vec<Vec<String>> = self.open_files.iter()
+                    .map(|(alias, open)| vec![
+                        alias.clone(),
+                        open.file.filepath().display().to_string(),
+                        open.file.data().description().unwrap_or_default().to_string(),
+                    ])
+                    .collect();
+                rows.sort();
+                self.driver.print(self.output.table(&rows));
+            }
+            ReplCommand::Crypt(ReplCryptCommand::SaveAll) => {
+                let report = self.save_all_files();
+                self.print_report(&report, ErrorCode::WriteFailed);
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Unlock { alias, filepath, dual, cipher, compression, layout, container, keyfile }) => {
+                let format = FormatChoice { cipher: *cipher, compression: *compression, layout: *layout, container: *container };
+                self.unlock_file(alias, filepath, *dual, format, keyfile.as_deref().map(Path::new))?;
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Lock { alias }) => {
+                self.driver.print("Attempting to lock file...\n");
+                match self.lock_file(alias) {
+                    Ok(()) => self.driver.print(format!("Locked {}\n", alias)),
+                    Err(LockError::NotOpen) => self.report_unknown_alias(alias),
+                    Err(LockError::Crypt(error)) => self.report(ErrorCode::LockFailed, format!("Failed to lock file: {}", error))
+                }
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Inspect { filepath }) => self.inspect_file(filepath),
+            ReplCommand::Crypt(ReplCryptCommand::Verify { filepath }) => self.verify_file(filepath)?,
+            ReplCommand::Crypt(ReplCryptCommand::Recover { alias, filepath }) => self.recover_file(alias, filepath)?,
+            ReplCommand::Crypt(ReplCryptCommand::Data { alias, cmd }) => {
+                if let Some(cmd) = self.name_entry(alias, cmd)? {
+                    self.execute_map_command(alias, &cmd)?;
+                }
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Meta { alias, cmd }) => self.execute_meta_command(alias, cmd),
+            ReplCommand::Crypt(ReplCryptCommand::Merge { alias, source, on_conflict }) => {
+                self.merge_files(alias, source, *on_conflict)?;
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Clone { alias, filepath, prefix }) => {
+                self.clone_file(alias, filepath, prefix.as_deref())?;
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Autosave { alias, policy }) => {
+                match self.open_files.get_mut(alias.as_ref()) {
+                    Some(open) => {
+                        open.autosave = *policy;
+                        self.driver.print(format!("Autosave for {} is {}\n", alias, policy));
+                    }
+                    None => self.report_unknown_alias(alias)
+                }
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Export { alias, format: ExportFormat::Json, filepath, prefix, tag }) => self.export_file(alias, filepath, prefix.as_deref(), tag.as_deref()),
+            ReplCommand::Crypt(ReplCryptCommand::Export { alias, format: ExportFormat::Bundle, filepath, prefix, tag }) => self.export_bundle(alias, filepath, prefix.as_deref(), tag.as_deref())?,
+            ReplCommand::Crypt(ReplCryptCommand::ExportArmor { filepath, armor_filepath }) => {
+                self.export_armor(filepath, armor_filepath);
+            }
+            ReplCommand::Crypt(ReplCryptCommand::ImportArmor { armor_filepath, filepath }) => self.import_armor(armor_filepath, filepath),
+            ReplCommand::Crypt(ReplCryptCommand::Import { alias, format, filepath, on_conflict }) => self.import_file(alias, format, filepath, *on_conflict)?,
+            ReplCommand::Crypt(ReplCryptCommand::Manifest { alias, filepath }) => self.write_manifest(alias, filepath),
+            ReplCommand::Crypt(ReplCryptCommand::CheckManifest { alias, filepath }) => self.check_manifest(alias, filepath),
+            ReplCommand::Crypt(ReplCryptCommand::Passwd { alias }) => self.change_password(alias)?,
+            ReplCommand::Crypt(ReplCryptCommand::Migrate { alias }) => self.migrate_file(alias),
+            ReplCommand::Crypt(ReplCryptCommand::Sync { alias, filepath }) => self.sync_file(alias, filepath.as_deref()),
+            ReplCommand::Crypt(ReplCryptCommand::Compact { alias, shred }) => self.compact_file(alias, *shred),
+            ReplCommand::Crypt(ReplCryptCommand::Recipients { alias, recipients }) => self.recipients(alias, recipients),
+            ReplCommand::Crypt(ReplCryptCommand::Backups { alias, restore: None }) => self.list_backups(alias),
+            ReplCommand::Crypt(ReplCryptCommand::Backups { alias, restore: Some(backup) }) => self.restore_backup(alias, backup),
+            ReplCommand::Crypt(ReplCryptCommand::Share { alias, key, filepath, expires }) => {
+                self.share_entry(alias, key, filepath, expires.unwrap_or(DEFAULT_SHARE_EXPIRY));
+            }
+            ReplCommand::Crypt(ReplCryptCommand::Receive { alias, filepath }) => self.receive_entry(alias, filepath)?,
+            ReplCommand::Crypt(ReplCryptCommand::ExportK8s { alias, name, keys }) => self.export_k8s(alias, name, keys),
+            ReplCommand::Crypt(ReplCryptCommand::ExtractAll { alias, dir, prefix, mode, existing }) => self.extract_all(alias, dir, prefix.as_deref(), *mode, *existing),
+        }
+        Ok(None)
+    }
+
+    fn unlock_file(&mut self, alias: &str, filepath: &str, dual: bool, format: FormatChoice, keyfile: Option<&Path>) -> Result<(), D::Error> {
+        if let Some(max) = self.limits.max_open_files {
+            if self.open_files.len() >= max && !self.open_files.contains_key(alias) {
+                self.report(ErrorCode::LimitExceeded, format!("Cannot unlock more than {} files at once, lock one first", max));
+                return Ok(());
+            }
+        }
+        // A directory asks for a new file in it.
+        let mut filepath = match crate::path::expand_home(Path::new(filepath)) {
+            Ok(filepath) => filepath,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Cannot unlock {}, {}", filepath, error));
+                return Ok(());
+            }
+        };
+        if filepath.is_dir() {
+            let dir = filepath;
+            let file_name = |name: &str| {
+                let has_extension = Path::new(name).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("crypt"));
+                if has_extension { name.to_string() } else { format!("{}.crypt", name) }
+            };
+            let Some(name) = self.choose_name(&format!("the new file in {}", dir.display()), |name| dir.join(file_name(name)).exists())? else {
+                return Ok(());
+            };
+            filepath = dir.join(file_name(&name));
+        }
+        let filepath = match CryptPath::new(&filepath) {
+            Ok(filepath) => filepath.into_path_buf(),
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Cannot unlock {}, {}", filepath.display(), error));
+                return Ok(());
+            }
+        };
+        let is_new = !filepath.exists();
+        if format.container.is_some() && !is_new {
+            self.report(ErrorCode::InvalidArgument, format!("{} already exists, the container of a file is chosen when it is created", filepath.display()));
+            return Ok(());
+        }
+        let Some((secret, mut file)) = self.unlock_with_secret(&filepath, dual, format.container, keyfile)? else {
+            return Ok(());
+        };
+        if let Some(overflow) = self.payload_overflow(file.data().payload_size()) {
+            self.report(ErrorCode::LimitExceeded, format!("Refusing to unlock file, it would exceed the decrypted data limit by {} bytes", overflow));
+            return Ok(());
+        }
+        // Existing files keep their cipher unless told otherwise, new files get the configured one.
+        if let Some(cipher) = format.cipher.or(if is_new { Some(self.cipher) } else { None }) {
+            file.set_cipher(cipher);
+        }
+        if let Some(compression) = format.compression.or(if is_new { Some(self.compression) } else { None }) {
+            file.set_compression(compression);
+        }
+        if let Some(layout) = format.layout.or(if is_new { Some(self.layout) } else { None }) {
+            file.set_layout(layout);
+        }
+        if let Some(container) = format.container {
+            file.set_container(container);
+        }
+        if is_new {
+            file.set_kdf(self.kdf.with_defaults());
+            file.data_mut().set_password_changed(SystemTime::now());
+        }
+        self.unlock_kdf_duration = file.kdf_duration();
+        self.recover_temp_files(alias, &secret, &mut file)?;
+        self.remind_expiry(file.data());
+        self.remind_rotation(alias, file.data());
+        self.purge_old_trash(file.data_mut());
+        if is_gpg(file.container()) && file.recipients().is_empty() {
+            self.driver.print(format!("Add who {} is encrypted to with crypt recipients {} <key-id>... before saving it\n", alias, alias));
+        }
+        #[cfg(feature = "gpg")]
+        if file.recipients().iter().any(|recipient| recipient == crate::gpg::HIDDEN_RECIPIENT) {
+            self.driver.print(format!("Some recipients of {} are hidden, name them all with crypt recipients {} <key-id>... before saving it\n", alias, alias));
+        }
+        // Settings stored in the file win over the local configuration.
+        let autosave = FileSettings::read(file.data()).autosave.unwrap_or(self.autosave);
+        let open = OpenFile { secret, file, autosave, saved_at: Instant::now() };
+        self.open_files.insert(alias.to_string(), open);
+        self.fire_hook(HookEvent::PostUnlock, alias, None);
+        Ok(())
+    }
+
+    /// Unlocks the file with [`unlock_with_retries`](Self::unlock_with_retries), or without a
+    /// password if it is, or is being created as, a GPG file, whose secret key gpg-agent asks
+    /// for instead.
+    ///
+    /// A version 1 file has no header and can look like a GPG file, so like
+    /// [`CryptFile::unlock`] does, one gpg can't decrypt is unlocked with a password after all.
+    fn unlock_with_secret(&mut self, filepath: &Path, dual: bool, container: Option<Container>, keyfile: Option<&Path>) -> Result<Option<(SessionSecret, UnlockedCrypt)>, D::Error> {
+        #[cfg(feature = "gpg")]
+        {
+            let existing = CryptFile::new(filepath.to_path_buf()).inspect().ok().map(|info| info.container);
+            let is_new = existing.is_none() && container == Some(Container::Gpg);
+            if is_new && (dual || keyfile.is_some()) {
+                self.report(ErrorCode::InvalidArgument, "GPG files are encrypted to public keys and can't use dual control or a keyfile");
+                return Ok(None);
+            }
+            // With dual control or a keyfile it can only be a version 1 file.
+            if is_new || (existing == Some(Container::Gpg) && !dual && keyfile.is_none()) {
+                match CryptFile::new(filepath.to_path_buf()).with_backups(self.backups.clone()).unlock("") {
+                    Ok(file) if is_new || file.container() == Container::Gpg => return Ok(Some((SessionSecret::GpgAgent, file))),
+                    Ok(_) => {}
+                    Err(error) if is_new => {
+                        self.report(ErrorCode::UnlockFailed, format!("Failed to unlock file: {}", error));
+                        return Ok(None);
+                    }
+                    Err(error) => self.driver.eprint(format!("{} looks like a GPG file but {}, trying it as a crypt file\n", filepath.display(), error))
+                }
+            }
+        }
+        #[cfg(not(feature = "gpg"))]
+        let _ = container;
+        Ok(self.unlock_with_retries(filepath, dual, keyfile)?.map(|(password, file)| (SessionSecret::Password(password), file)))
+    }
+
+    /// Asks for the password and unlocks the file, combined with `keyfile` if given, asking again
+    /// up to [`MAX_PASSWORD_ATTEMPTS`] times if it is wrong. Passwords from a secret source aren't
+    /// retried, as they would be wrong again.
+    fn unlock_with_retries(&mut self, filepath: &Path, dual: bool, keyfile: Option<&Path>) -> Result<Option<(Zeroizing<String>, UnlockedCrypt)>, D::Error> {
+        let retry = self.driver.can_retry_password() && (dual || !self.secret_sources.contains_key(&source_key(filepath)));
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let password = if dual {
+                self.prompt_dual_control_password(filepath)?
+            } else {
+                self.password_for(filepath)?
+            };
+            let Some(password) = password else {
+                return Ok(None);
+            };
+            let file = CryptFile::new(filepath.to_path_buf()).with_backups(self.backups.clone());
+            let unlocked = match keyfile {
+                Some(keyfile) => file.unlock_with_keyfile(password.as_str(), keyfile),
+                None => file.unlock(password.as_str())
+            };
+            match unlocked {
+                Ok(file) => return Ok(Some((password, file))),
+                Err(CryptFileError::WrongPassword) if retry && attempts < MAX_PASSWORD_ATTEMPTS => {
+                    self.driver.eprint("Wrong password, try again\n");
+                }
+                Err(error @ CryptFileError::WrongPassword) => {
+                    self.report(ErrorCode::WrongPassword, format!("Failed to unlock file: {}", error));
+                    return Ok(None);
+                }
+                Err(error) => {
+                    self.report(ErrorCode::UnlockFailed, format!("Failed to unlock file: {}", error));
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Prints a summary of expired and expiring entries, if there are any and reminders are on.
+    fn remind_expiry(&mut self, data: &CryptData) {
+        if !FileSettings::read(data).expiry_reminders.unwrap_or(self.expiry_reminders.enabled) {
+            return;
+        }
+        let within = self.expiry_reminders.within_days;
+        let counts = data.expiry_counts(SystemTime::now(), Duration::from_secs(within * 86_400));
+        if counts.expired > 0 || counts.expiring > 0 {
+            self.driver.print(format!("{} entries expired, {} expiring within {} days\n", counts.expired, counts.expiring, within));
+        }
+    }
+
+    /// Reminds to change the password of the file if it is older than its `rotate_after` setting.
+    fn remind_rotation(&mut self, alias: &str, data: &CryptData) {
+        let (Some(rotate_after), Some(changed)) = (FileSettings::read(data).rotate_after, data.password_changed()) else {
+            return;
+        };
+        let age = SystemTime::now().duration_since(changed).unwrap_or_default();
+        if age >= rotate_after {
+            self.driver.print(format!("The password of {} was last changed {} days ago and is due to be rotated, change it with crypt passwd {}\n", alias, age.as_secs() / 86_400, alias));
+        }
+    }
+
+    /// Purges entries that have been in the trash for longer than the trash policy keeps them.
+    fn purge_old_trash(&mut self, data: &mut CryptData) {
+        let keep_days = self.trash.keep_days;
+        let Some(cutoff) = self.trash.keep().and_then(|keep| SystemTime::now().checked_sub(keep)) else {
+            return;
+        };
+        let purged = data.purge_trash(Some(cutoff));
+        if purged > 0 {
+            self.driver.print(format!("Purged {} entries deleted more than {} days ago from the trash\n", purged, keep_days));
+        }
+    }
+
+    fn inspect_file(&mut self, filepath: &str) {
+        match CryptFile::new(PathBuf::from(filepath)).inspect() {
+            Ok(info) => {
+                self.driver.print(format!("  format version: {}\n", info.format_version));
+                self.driver.print(format!("  cipher: {}\n", info.cipher));
+                self.driver.print(format!("  kdf: {}\n", info.kdf));
+                self.driver.print(format!("  compression: {}\n", info.compression));
+                self.driver.print(format!("  layout: {}\n", info.layout));
+                self.driver.print(format!("  container: {}\n", info.container));
+                self.driver.print(format!("  size: {} bytes, {} bytes encrypted payload\n", info.file_size, info.payload_size));
+                if let Some(modified) = info.modified {
+                    self.driver.print(format!("  modified: {}\n", format_utc(modified)));
+                }
+            }
+            Err(error) => self.report(ErrorCode::InvalidArgument, format!("Failed to inspect file: {}", error))
+        }
+    }
+
+    /// Opens what is intact of a damaged file under `alias`, listing what was lost, see
+    /// [`CryptFile::recover`].
+    fn recover_file(&mut self, alias: &str, filepath: &str) -> Result<(), D::Error> {
+        let path = PathBuf::from(filepath);
+        if !path.is_file() {
+            self.report(ErrorCode::InvalidArgument, format!("Cannot recover {}, it doesn't exist", filepath));
+            return Ok(());
+        }
+        let Some(password) = self.password_for(&path)? else {
+            return Ok(());
+        };
+        let (file, recovery) = match CryptFile::new(path).with_backups(self.backups.clone()).recover(&password) {
+            Ok(recovered) => recovered,
+            Err(error @ CryptFileError::WrongPassword) => {
+                self.report(ErrorCode::WrongPassword, format!("Failed to recover file: {}", error));
+                return Ok(());
+            }
+            Err(error) => {
+                self.report(ErrorCode::UnlockFailed, format!("Failed to recover file: {}", error));
+                return Ok(());
+            }
+        };
+        self.print_recovery(alias, file.data().len(), &recovery);
+        let autosave = FileSettings::read(file.data()).autosave.unwrap_or(self.autosave);
+        let open = OpenFile { secret: SessionSecret::Password(password), file, autosave, saved_at: Instant::now() };
+        self.open_files.insert(alias.to_string(), open);
+        self.fire_hook(HookEvent::PostUnlock, alias, None);
+        Ok(())
+    }
+
+    fn print_recovery(&mut self, alias: &str, recovered: usize, recovery: &Recovery) {
+        if recovery.is_intact() {
+            self.driver.print(format!("Recovered all {} entries, the file is intact\n", recovered));
+            return;
+        }
+        self.driver.print(format!("Recovered {} entries, {} chunks were damaged or missing\n", recovered, recovery.lost_chunks));
+        if recovery.index_lost {
+            self.driver.print("The index was lost, so the description, metadata, settings and the keys of lost entries are unknown\n");
+        }
+        for key in &recovery.lost_keys {
+            self.driver.print(format!("  lost: {}\n", key));
+        }
+        self.driver.print(format!("Lock or save {} to rewrite the file with what was recovered\n", alias));
+    }
+
+    /// Checks a file on disk stage by stage without opening it, see [`crate::verify::verify_file`].
+    fn verify_file(&mut self, filepath: &str) -> Result<(), D::Error> {
+        let path = PathBuf::from(filepath);
+        // A missing file fails to read before any password is needed.
+        let password = if path.is_file() {
+            let Some(password) = self.password_for(&path)? else {
+                return Ok(());
+            };
+            password
+        } else {
+            Zeroizing::new(String::new())
+        };
+        let mut report = OutcomeReport::new();
+        match crate::verify::verify_file(&path, &password) {
+            Ok(verified) => {
+                let info = verified.info;
+                report.succeeded(VerifyStage::Read.to_string(), format!("{} bytes", info.file_size));
+                report.succeeded(VerifyStage::Header.to_string(), format!("version {}, {}, {} compression", info.format_version, info.cipher, info.compression));
+                report.succeeded(VerifyStage::Authenticate.to_string(), "password accepted");
+                report.succeeded(VerifyStage::Decode.to_string(), format!("{} entries", verified.entries));
+                self.driver.print(self.output.table(&report.rows()));
+                self.driver.print(format!("Verified {}\n", filepath));
+            }
+            Err(VerifyError { stage, error }) => {
+                for passed in VerifyStage::ALL.iter().take_while(|passed| **passed != stage) {
+                    report.succeeded(passed.to_string(), "ok");
+                }
+                report.failed(stage.to_string(), error.to_string());
+                self.driver.print(self.output.table(&report.rows()));
+                let code = if stage == VerifyStage::Authenticate { ErrorCode::WrongPassword } else { ErrorCode::InvalidArgument };
+                self.report(code, format!("Failed to verify {} at the {} stage", filepath, stage));
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_meta_command(&mut self, alias: &str, cmd: &ReplMetaCommand) {
+        let added = match cmd {
+            ReplMetaCommand::Describe { description } => description.len(),
+            ReplMetaCommand::Set { key, value } => key.len() + value.len(),
+            ReplMetaCommand::SetSetting { name, value } => name.len() + value.len(),
+            ReplMetaCommand::Show | ReplMetaCommand::Unset { .. } | ReplMetaCommand::UnsetSetting { .. } => 0
+        };
+        if let Some(overflow) = self.payload_overflow(added) {
+            self.report(ErrorCode::LimitExceeded, format!("Refusing to set metadata, it would exceed the decrypted data limit by {} bytes", overflow));
+            return;
+        }
+        let Some(OpenFile { file, autosave, .. }) = self.open_files.get_mut(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        match cmd {
+            ReplMetaCommand::Show => {
+                let data = file.data();
+                self.driver.print(format!("  description: {}\n", data.description().unwrap_or("(none)")));
+                let rows: Vec<Vec<String>> = data.metadata()
+                    .map(|(key, value)| vec![key.to_string(), value.to_string()])
+                    .collect();
+                self.driver.print(self.output.table(&rows));
+                for (name, value) in data.settings() {
+                    self.driver.print(format!("  setting {}: {}\n", name, value));
+                }
+            }
+            ReplMetaCommand::Describe { description } => {
+                file.data_mut().set_description(Some(description.to_string()));
+            }
+            ReplMetaCommand::Set { key, value } => {
+                file.data_mut().set_metadata(key.as_ref(), Some(value.to_string()));
+            }
+            ReplMetaCommand::Unset { key } => {
+                if file.data_mut().set_metadata(key.as_ref(), None).is_none() {
+                    self.report(ErrorCode::UnknownKey, "Metadata field doesn't exist");
+                }
+            }
+            ReplMetaCommand::SetSetting { name, value } => {
+                if let Err(error) = FileSettings::validate(name, value) {
+                    self.report(ErrorCode::InvalidArgument, format!("Cannot store setting, {}", error));
+                    return;
+                }
+                file.data_mut().set_setting(name.as_ref(), Some(value.to_string()));
+                // Without a recorded change, the policy counts from when it was set.
+                if name == "rotate_after" && file.data().password_changed().is_none() {
+                    file.data_mut().set_password_changed(SystemTime::now());
+                }
+                let settings = FileSettings::read(file.data());
+                *autosave = settings.autosave.unwrap_or(self.autosave);
+                if name.starts_with("key_") {
+                    let policy = settings.key_policy();
+                    let breaking = file.data().keys().filter(|key| policy.check(key).is_err()).count();
+                    if breaking > 0 {
+                        self.driver.print(format!("{} existing keys don't follow the naming policy, they are left as they are\n", breaking));
+                    }
+                }
+            }
+            ReplMetaCommand::UnsetSetting { name } => {
+                let removed = file.data_mut().set_setting(name.as_ref(), None);
+                *autosave = FileSettings::read(file.data()).autosave.unwrap_or(self.autosave);
+                if removed.is_none() {
+                    self.report(ErrorCode::UnknownKey, "Setting doesn't exist");
+                }
+            }
+        }
+    }
+
+    /// Prints `value`, or copies it to the clipboard if `output` or the copy-on-get setting says so.
+    fn show_value(&mut self, key: &str, value: &str, output: Option<GetOutput>) {
+        if !output.map_or(self.copy_on_get, |output| output == GetOutput::Copy) {
+            self.driver.print(format!("{}\n", value));
+        } else if let Err(error) = copy_to_clipboard(value) {
+            self.report(ErrorCode::ClipboardFailed, format!("Failed to copy to the clipboard: {}", error));
+        } else {
+            self.driver.print(format!("Copied {} to the clipboard\n", key));
+        }
+    }
+
+    /// Replaces the value of `key` with the result of editing it with `tool`, see [`edit_with`].
+    fn edit_value_with(&mut self, alias: &str, key: &str, tool: &[Cow<str>]) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let Some(value) = file.data().get(key).map(|value| Zeroizing::new(value.to_string())) else {
+            self.report(ErrorCode::UnknownKey, "Key doesn't exist");
+            return;
+        };
+        let edited = match edit_with(&value, tool) {
+            Ok(edited) => edited,
+            Err(error) => {
+                self.report(ErrorCode::EditFailed, format!("Failed to edit {}, it was left unchanged: {}", key, error));
+                return;
+            }
+        };
+        if let Some(overflow) = self.payload_overflow(edited.len().saturating_sub(value.len())) {
+            self.report(ErrorCode::LimitExceeded, format!("Refusing to set value, it would exceed the decrypted data limit by {} bytes", overflow));
+            return;
+        }
+        if *edited == *value {
+            self.driver.print(format!("{} was not changed\n", key));
+        } else if let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) {
+            file.data_mut().insert(key, edited.as_str());
+            self.driver.print(format!("Updated {}\n", key));
+            self.fire_hook(HookEvent::EntryChanged, alias, Some(key));
+        }
+    }
+
+    fn exec_with_files(&mut self, alias: &str, keys: &[Cow<str>], command: &[Cow<str>]) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let mut entries = Vec::new();
+        for key in keys {
+            let Some(value) = file.data().get(key) else {
+                self.report(ErrorCode::UnknownKey, format!("Cannot pass {} to the command, it is not in {}", key, alias));
+                return;
+            };
+            entries.push((key.as_ref(), value));
+        }
+        match exec_with_files(entries, command) {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.report(ErrorCode::ExecFailed, format!("{} failed with {}", command[0], status)),
+            Err(error) => self.report(ErrorCode::ExecFailed, format!("Failed to run {}: {}", command[0], error))
+        }
+    }
+
+    fn execute_map_command(&mut self, alias: &str, cmd: &ReplMapCommand) -> Result<(), D::Error> {
+        if let ReplMapCommand::Set { key, value, note, force, .. } = cmd {
+            if !self.check_set_limits(alias, key, value, note.as_deref(), *force) {
+                return Ok(());
+            }
+        }
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        };
+        let mut changed = Vec::new();
+        match cmd {
+            ReplMapCommand::List { sort } => {
+                self.driver.print("Listing data:\n");
+                let rows = Self::list_rows(file.data(), *sort);
+                self.driver.print(self.output.table(&rows));
+            }
+            // Only the keys are printed, so scripts can rely on the output whatever the style.
+            ReplMapCommand::Keys { prefix, null } => {
+                let terminator = if *null { '\0' } else { '\n' };
+                let prefix = prefix.as_deref().unwrap_or("");
+                for key in file.data().keys().filter(|key| key.starts_with(prefix)) {
+                    self.driver.print(format!("{}{}", key, terminator));
+                }
+            }
+            ReplMapCommand::Get { key, output } => self.get_value(alias, key, *output),
+            ReplMapCommand::Set { key, value, note, .. } => {
+                file.data_mut().insert(key.to_string(), value.to_string());
+                if let Some(note) = note {
+                    file.data_mut().set_note(key, Some(note.to_string()));
+                }
+                changed.push(key.to_string());
+            }
+            ReplMapCommand::Delete { key } => changed.extend(self.delete_entry(alias, key)),
+            ReplMapCommand::Trash { cmd } => changed = self.execute_trash_command(alias, cmd),
+            ReplMapCommand::Field { cmd } => changed = self.execute_field_command(alias, cmd),
+            ReplMapCommand::Info { key } => self.print_entry_info(alias, key),
+            ReplMapCommand::ShowBig { key, duration } => self.show_big(alias, key, duration.unwrap_or(SHOW_BIG_DURATION)),
+            ReplMapCommand::Pick { query } => self.pick_key(alias, query.as_deref())?,
+            ReplMapCommand::Move { pattern, to } => self.move_entries(alias, pattern, to),
+            ReplMapCommand::Generate { key, generator, replace, .. } => changed = self.generate_value(alias, key, *generator, *replace),
+            ReplMapCommand::Tag { key, tag } => {
+                if file.data_mut().add_tag(key, tag.as_ref()) {
+                    changed.push(key.to_string());
+                } else {
+                    self.driver.report_error(&ReplError::new(ErrorCode::UnknownKey, self.command_index, "Key doesn't exist"));
+                }
+            }
+            ReplMapCommand::Untag { key, tag } => {
+                if file.data_mut().remove_tag(key, tag) {
+                    changed.push(key.to_string());
+                } else {
+                    self.driver.report_error(&ReplError::new(ErrorCode::UnknownKey, self.command_index, "Key doesn't exist or doesn't have that tag"));
+                }
+            }
+            ReplMapCommand::Expire { key, expires } => {
+                if file.data_mut().set_expiry(key, *expires) {
+                    changed.push(key.to_string());
+                } else {
+                    self.driver.report_error(&ReplError::new(ErrorCode::UnknownKey, self.command_index, "Key doesn't exist"));
+                }
+            }
+            ReplMapCommand::Search { term } => {
+                let keys = file.data().search(term);
+                self.driver.print(format!("{} matching keys:\n", keys.len()));
+                for key in keys {
+                    self.driver.print(format!("  {}\n", key));
+                }
+            }
+            ReplMapCommand::Clear { prefix } => self.clear_entries(alias, prefix.as_deref().unwrap_or(""))?,
+            ReplMapCommand::EditWith { key, tool } => self.edit_value_with(alias, key, tool),
+            ReplMapCommand::ImportEnv { prefix } => self.import_env(alias, prefix),
+            ReplMapCommand::Exec { keys, command } => self.exec_with_files(alias, keys, command),
+            ReplMapCommand::Rename { key, new_key } => {
+                if !file.data().contains_key(key) {
+                    self.driver.report_error(&ReplError::new(ErrorCode::UnknownKey, self.command_index, "Key doesn't exist"));
+                    return Ok(());
+                }
+                let renames = file.data().plan_renames(|existing| (existing == key).then(|| new_key.to_string()));
+                changed = Self::apply_renames(&mut self.driver, self.command_index, file, renames, false);
+            }
+            ReplMapCommand::RenamePrefix { old_prefix, new_prefix, dry_run } => {
+                let renames = file.data().plan_renames(|key| key.strip_prefix(old_prefix.as_ref()).map(|rest| format!("{}{}", new_prefix, rest)));
+                changed = Self::apply_renames(&mut self.driver, self.command_index, file, renames, *dry_run);
+            }
+            ReplMapCommand::RenamePattern { pattern, replacement, dry_run } => {
+                let pattern = match Regex::new(pattern) {
+                    Ok(pattern) => pattern,
+                    Err(error) => {
+                        self.driver.report_error(&ReplError::new(ErrorCode::InvalidPattern, self.command_index, format!("Invalid pattern: {}", error)));
+                        return Ok(());
+                    }
+                };
+                let renames = file.data().plan_renames(|key| pattern.is_match(key).then(|| pattern.replace_all(key, replacement.as_ref()).into_owned()));
+                changed = Self::apply_renames(&mut self.driver, self.command_index, file, renames, *dry_run);
+            }
+        }
+        self.entries_changed(alias, changed);
+        Ok(())
+    }
+
+    /// Moves the entries of `from` whose key matches `pattern` to `to`, which is saved before
+    /// the originals are deleted. If the save fails, `to` is put back as it was and `from` is left
+    /// untouched, so no entry is lost.
+    fn move_entries(&mut self, from: &str, pattern: &str, to: &str) {
+        if from == to {
+            self.report(ErrorCode::InvalidArgument, "Cannot move entries within the same crypt, use rename");
+            return;
+        }
+        let pattern = match Regex::new(pattern) {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                self.report(ErrorCode::InvalidPattern, format!("Invalid pattern: {}", error));
+                return;
+            }
+        };
+        let Some(moving) = self.open_files.get(from).map(|open| open.file.data().select(|key| pattern.is_match(key))) else {
+            return;
+        };
+        let Some(OpenFile { file, .. }) = self.open_files.get(to) else {
+            self.report_unknown_alias(to);
+            return;
+        };
+        if moving.is_empty() {
+            self.driver.print(format!("No keys of {} match the pattern, nothing was moved\n", from));
+            return;
+        }
+        // Saving would write them along with the moved entries.
+        if file.is_dirty() {
+            self.report(ErrorCode::InvalidArgument, format!("Nothing was moved, {} has unsaved changes, save or discard them first", to));
+            return;
+        }
+        let destination = file.data();
+        if let Some(key) = moving.keys().find(|key| destination.contains_key(key)) {
+            self.report(ErrorCode::KeyExists, format!("Nothing was moved, {} already exists in {}", key, to));
+            return;
+        }
+        let policy = FileSettings::read(destination).key_policy();
+        if let Some(reason) = moving.keys().find_map(|key| policy.check(key).err()) {
+            self.report(ErrorCode::KeyRejected, format!("Nothing was moved, {}", reason));
+            return;
+        }
+
+        let keys: Vec<String> = moving.keys().map(str::to_string).collect();
+        let Some(open) = self.open_files.get_mut(to) else {
+            self.report_unknown_alias(to);
+            return;
+        };
+        let before = open.file.data().clone();
+        open.file.data_mut().merge(moving, |_| ConflictPolicy::KeepExisting);
+        self.hooks.fire(&HookContext { event: HookEvent::PreSave, alias: to, filepath: open.file.filepath(), key: None });
+        if let Err(error) = open.secret.save(&mut open.file) {
+            *open.file.data_mut() = before;
+            self.report(ErrorCode::WriteFailed, format!("Nothing was moved, {} could not be saved: {}", to, error));
+            return;
+        }
+        open.saved_at = Instant::now();
+        self.hooks.fire(&HookContext { event: HookEvent::PostSave, alias: to, filepath: open.file.filepath(), key: None });
+
+        let Some(OpenFile { file: source, .. }) = self.open_files.get_mut(from) else {
+            self.report_unknown_alias(from);
+            return;
+        };
+        for key in &keys {
+            source.data_mut().remove(key);
+        }
+        self.driver.print(format!("Moved {} entries from {} to {}, which was saved:\n", keys.len(), from, to));
+        for key in &keys {
+            self.driver.print(format!("  {}\n", key));
+        }
+        self.entries_changed(to, keys.clone());
+        self.entries_changed(from, keys);
+    }
+
+    /// Stores a value made by `generator` under `key`, and the public key of a keypair under
+    /// `<key>.pub`, returning the keys that were set. The public key is printed, the value isn't.
+    fn generate_value(&mut self, alias: &str, key: &str, generator: Generator, replace: bool) -> Vec<String> {
+        let Generated { value, public } = generator.generate(key);
+        let mut entries = vec![(key.to_string(), value)];
+        if let Some(public) = &public {
+            entries.push((format!("{}.pub", key), Zeroizing::new(public.clone())));
+        }
+        let existing = self.open_files.get(alias)
+            .and_then(|open| entries.iter().find(|(key, _)| open.file.data().contains_key(key)));
+        if let (Some((existing, _)), false) = (existing, replace) {
+            self.report(ErrorCode::KeyExists, format!("{} already exists, add --replace to replace it", existing));
+            return Vec::new();
+        }
+        for (key, value) in &entries {
+            if !self.check_set_limits(alias, key, value, None, false) {
+                return Vec::new();
+            }
+        }
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return Vec::new();
+        };
+        for (key, value) in &entries {
+            file.data_mut().insert(key.clone(), value.to_string());
+        }
+        self.driver.print(format!("Generated {} into {}\n", generator, key));
+        if let Some(public) = public {
+            self.driver.print(format!("Public key, stored under {}.pub:\n{}\n", key, public));
+        }
+        entries.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// Moves `key` to the trash, or deletes it for good if the trash is off, returning the key if
+    /// it existed.
+    fn delete_entry(&mut self, alias: &str, key: &str) -> Option<String> {
+        let file = &mut self.open_files.get_mut(alias)?.file;
+        if self.trash.enabled {
+            if !file.data_mut().trash(key, SystemTime::now()) {
+                return None;
+            }
+            self.driver.print(format!("Moved {} to the trash\n", key));
+        } else if file.data_mut().remove(key).is_none() {
+            return None;
+        }
+        Some(key.to_string())
+    }
+
+    /// Lists, restores or purges trashed entries, returning the keys that were restored.
+    fn execute_trash_command(&mut self, alias: &str, cmd: &ReplTrashCommand) -> Vec<String> {
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return Vec::new();
+        };
+        match cmd {
+            ReplTrashCommand::List => {
+                let keep = self.trash.keep();
+                let rows: Vec<Vec<String>> = file.data().trashed()
+                    .map(|(key, deleted)| {
+                        let purged = keep.and_then(|keep| deleted.checked_add(keep))
+                            .map_or_else(|| "never purged".to_string(), |purged| format!("purged after {}", format_utc(purged)));
+                        vec![key.to_string(), format!("deleted {}", format_utc(deleted)), purged]
+                    })
+                    .collect();
+                self.driver.print(format!("{} entries in the trash:\n", rows.len()));
+                self.driver.print(self.output.table(&rows));
+            }
+            ReplTrashCommand::Restore { key } => match file.data_mut().restore(key) {
+                Ok(()) => {
+                    self.driver.print(format!("Restored {}\n", key));
+                    return vec![key.to_string()];
+                }
+                Err(error @ RestoreError::NotInTrash) => self.report(ErrorCode::UnknownKey, format!("Cannot restore {}, {}", key, error)),
+                Err(error @ RestoreError::KeyInUse) => self.report(ErrorCode::RenameCollision, format!("Cannot restore {}, {}", key, error))
+            },
+            ReplTrashCommand::Purge { key: Some(key) } => {
+                if file.data_mut().purge_trashed(key) {
+                    self.driver.print(format!("Purged {} from the trash\n", key));
+                } else {
+                    self.report(ErrorCode::UnknownKey, format!("Cannot purge {}, it isn't in the trash", key));
+                }
+            }
+            ReplTrashCommand::Purge { key: None } => {
+                let purged = file.data_mut().purge_trash(None);
+                self.driver.print(format!("Purged {} entries from the trash\n", purged));
+            }
+        }
+        Vec::new()
+    }
+
+    fn execute_field_command(&mut self, alias: &str, cmd: &ReplFieldCommand) -> Vec<String> {
+        if let ReplFieldCommand::Set { key, value, .. } = cmd {
+            if !self.check_set_limits(alias, key, value, None, false) {
+                return Vec::new();
+            }
+        }
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return Vec::new();
+        };
+        match cmd {
+            ReplFieldCommand::List { key } => match file.data().entry(key) {
+                Some(entry) => {
+                    for (field, _) in entry.fields() {
+                        self.driver.print(format!("  {}\n", field));
+                    }
+                }
+                None => self.report(ErrorCode::UnknownKey, "Key doesn't exist")
+            },
+            ReplFieldCommand::Get { key, field, output } => {
+                let Some(value) = file.data().field(key, field).map(|value| Zeroizing::new(value.to_string())) else {
+                    let message = if file.data().contains_key(key) { format!("{} has no {} field", key, field) } else { "Key doesn't exist".to_string() };
+                    self.report(ErrorCode::UnknownKey, message);
+                    return Vec::new();
+                };
+                let stored = FileSettings::read(file.data()).copy_on_get;
+                let output = output.or_else(|| stored.map(|copy| if copy { GetOutput::Copy } else { GetOutput::Print }));
+                file.data_mut().record_read(key, SystemTime::now());
+                self.show_value(&format!("{} {}", key, field), &value, output);
+            }
+            ReplFieldCommand::Set { key, field, value } => {
+                file.data_mut().set_field(key, field, Some(value.to_string()));
+                return vec![key.to_string()];
+            }
+        }
+        Vec::new()
+    }
+
+    /// Shows the value of `key` as `output` asks, or as the file's `copy_on_get` setting says.
+    fn get_value(&mut self, alias: &str, key: &str, output: Option<GetOutput>) {
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return;
+        };
+        match file.data().get(key).map(|value| Zeroizing::new(value.to_string())) {
+            Some(value) => {
+                let stored = FileSettings::read(file.data()).copy_on_get;
+                let output = output.or_else(|| stored.map(|copy| if copy { GetOutput::Copy } else { GetOutput::Print }));
+                file.data_mut().record_read(key, SystemTime::now());
+                self.show_value(key, &value, output);
+            }
+            None => self.report(ErrorCode::UnknownKey, "Key doesn't exist")
+        }
+    }
+
+    /// Shows the value of `key` in large type for `duration`, at most [`MAX_SHOW_BIG_DURATION`],
+    /// or until a key is pressed, then clears the screen. The countdown is printed every ten
+    /// seconds and then every second, so it doesn't scroll the value away.
+    fn show_big(&mut self, alias: &str, key: &str, duration: Duration) {
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return;
+        };
+        let Some(value) = file.data().get(key).map(|value| Zeroizing::new(value.to_string())) else {
+            self.report(ErrorCode::UnknownKey, "Key doesn't exist");
+            return;
+        };
+        file.data_mut().record_read(key, SystemTime::now());
+        self.driver.print(format!("{}:\n\n", key));
+        let rendered = Zeroizing::new(large_type(&value, self.output.width));
+        self.driver.print(rendered.as_str());
+        let seconds = duration.min(MAX_SHOW_BIG_DURATION).as_secs();
+        for remaining in (1..=seconds).rev() {
+            if remaining % 10 == 0 || remaining <= 5 || remaining == seconds {
+                self.driver.print(format!("Clearing the screen in {}s, press any key to clear it now\n", remaining));
+            }
+            if self.driver.wait_for_key(Duration::from_secs(1)) {
+                break;
+            }
+        }
+        self.clear_screen();
+    }
+
+    fn print_entry_info(&mut self, alias: &str, key: &str) {
+        let Some(entry) = self.open_files.get(alias).and_then(|open| open.file.data().entry(key)) else {
+            self.report(ErrorCode::UnknownKey, "Key doesn't exist");
+            return;
+        };
+        let never = || "never".to_string();
+        let lines = [
+            format!("  key: {}\n", key),
+            format!("  length: {}\n", entry.value().chars().count()),
+            format!("  note: {}\n", entry.note().unwrap_or("")),
+            format!("  fields: {}\n", entry.fields().iter().map(|(field, _)| field.name()).collect::<Vec<_>>().join(", ")),
+            format!("  tags: {}\n", entry.tags().collect::<Vec<_>>().join(", ")),
+            format!("  expires: {}\n", entry.expires().map_or_else(never, format_utc)),
+            format!("  reads: {}, last read: {}\n", entry.reads(), entry.last_read().map_or_else(never, format_utc)),
+        ];
+        for line in lines {
+            self.driver.print(line);
+        }
+    }
+
+    /// Lets the user choose one of the keys of `alias` that fuzzy match `query`, then what to do
+    /// with it.
+    fn pick_key(&mut self, alias: &str, query: Option<&str>) -> Result<(), D::Error> {
+        const MAX_CHOICES: usize = 20;
+        let query = match query {
+            Some(query) => query.to_string(),
+            None => self.driver.prompt_line("Search keys: ")?
+        };
+        let Some(open) = self.open_files.get(alias) else {
+            return Ok(());
+        };
+        let matches = fuzzy_filter(query.trim(), open.file.data().keys());
+        if matches.is_empty() {
+            self.report(ErrorCode::UnknownKey, format!("No keys match {}", query.trim()));
+            return Ok(());
+        }
+        if matches.len() > MAX_CHOICES {
+            self.driver.print(format!("Showing the best {} of {} matches\n", MAX_CHOICES, matches.len()));
+        }
+        let keys: Vec<String> = matches.into_iter().take(MAX_CHOICES).map(str::to_string).collect();
+        let choices: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let key = keys[self.driver.select("Key: ", &choices)?].as_str();
+        let output = match self.driver.select(format!("{}: ", key).as_str(), &["show", "copy", "info", "cancel"])? {
+            0 => GetOutput::Print,
+            1 => GetOutput::Copy,
+            2 => {
+                self.print_entry_info(alias, key);
+                return Ok(());
+            }
+            _ => return Ok(())
+        };
+        let Some(open) = self.open_files.get_mut(alias) else {
+            return Ok(());
+        };
+        let value = Zeroizing::new(open.file.data().get(key).unwrap_or_default().to_string());
+        open.file.data_mut().record_read(key, SystemTime::now());
+        self.show_value(key, &value, Some(output));
+        Ok(())
+    }
+
+    /// Checks setting `key` to `value` against the limits, reporting the first one it would break.
+    /// Only the decrypted data limit applies if `force` is set.
+    fn check_set_limits(&mut self, alias: &str, key: &str, value: &str, note: Option<&str>, force: bool) -> bool {
+        // The naming policy is the file's own, so --force doesn't override it. Keys that predate
+        // it can still be set.
+        let policy = self.open_files.get(alias)
+            .filter(|open| !open.file.data().contains_key(key))
+            .map(|open| FileSettings::read(open.file.data()).key_policy())
+            .unwrap_or_default();
+        if let Err(reason) = policy.check(key) {
+            self.report(ErrorCode::KeyRejected, format!("Refusing to set {}, {}", key, reason));
+            return false;
+        }
+        // Setting an existing key replaces its value, and its note if one is given.
+        let added = key.len() + value.len() + note.map_or(0, str::len);
+        let replaced = self.open_files.get(alias).and_then(|open| open.file.data().entry(key)).map_or(0, |entry| {
+            key.len() + entry.value().len() + note.and(entry.note()).map_or(0, str::len)
+        });
+        if let Some(overflow) = self.payload_overflow(added.saturating_sub(replaced)) {
+            self.report(ErrorCode::LimitExceeded, format!("Refusing to set value, it would exceed the decrypted data limit by {} bytes", overflow));
+            return false;
+        }
+        if force {
+            return true;
+        }
+        if let Some(max) = self.limits.max_entry_size.filter(|max| value.len() > *max) {
+            self.report(ErrorCode::LimitExceeded, format!(
+                "Refusing to set {}, the value is {} but values are limited to {}. Add --force to set it anyway",
+                key, format_size(value.len()), format_size(max)
+            ));
+            return false;
+        }
+        let Some(max) = self.limits.max_crypt_size else {
+            return true;
+        };
+        let Some(open) = self.open_files.get(alias) else {
+            return true;
+        };
+        let size = (open.file.data().payload_size() + added).saturating_sub(replaced);
+        if size > max {
+            self.report(ErrorCode::LimitExceeded, format!(
+                "Refusing to set {}, {} would grow to {} but files are limited to {}. Add --force to set it anyway",
+                key, alias, format_size(size), format_size(max)
+            ));
+            return false;
+        }
+        true
+    }
+
+    /// Rows of keys and values ordered by key or, with `sort`, by reads along with each key's read
+    /// count and last read time.
+    fn list_rows(data: &CryptData, sort: Option<ListSort>) -> Vec<Vec<String>> {
+        let mut entries: Vec<(&str, &Entry)> = data.entries().collect();
+        match sort {
+            None => return entries.into_iter().map(|(key, entry)| vec![key.to_string(), entry.value().to_string()]).collect(),
+            // Never read sorts first, as `None` is less than any time.
+            Some(ListSort::LastAccessed) => entries.sort_by_key(|(_, entry)| entry.last_read()),
+            Some(ListSort::Reads) => entries.sort_by_key(|(_, entry)| entry.reads())
+        }
+        entries.into_iter()
+            .map(|(key, entry)| vec![
+                key.to_string(),
+                entry.value().to_string(),
+                format!("{} reads", entry.reads()),
+                entry.last_read().map_or_else(|| "never read".to_string(), format_utc),
+            ])
+            .collect()
+    }
+
+    /// Copies the environment variables whose names start with `prefix` into `alias`, without the
+    /// prefix, replacing existing values. Variables that aren't valid Unicode are skipped.
+    fn import_env(&mut self, alias: &str, prefix: &str) {
+        if prefix.is_empty() {
+            self.report(ErrorCode::InvalidArgument, "An empty prefix would import the whole environment");
+            return;
+        }
+        let mut variables: Vec<(String, Zeroizing<String>)> = std::env::vars_os()
+            .filter_map(|(name, value)| {
+                let key = name.to_str()?.strip_prefix(prefix).filter(|key| !key.is_empty())?.to_string();
+                Some((key, Zeroizing::new(value.into_string().ok()?)))
+            })
+            .collect();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut imported = Vec::new();
+        for (key, value) in &variables {
+            if !self.check_set_limits(alias, key, value, None, false) {
+                continue;
+            }
+            if let Some(open) = self.open_files.get_mut(alias) {
+                open.file.data_mut().insert(key.clone(), value.to_string());
+                imported.push(key.clone());
+            }
+        }
+        self.driver.print(format!("Imported {} of {} environment variables starting with {}\n", imported.len(), variables.len(), prefix));
+        self.entries_changed(alias, imported);
+    }
+
+    /// Deletes every entry of `alias` whose key starts with `prefix`, once the user confirms.
+    fn clear_entries(&mut self, alias: &str, prefix: &str) -> Result<(), D::Error> {
+        let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) else {
+            return Ok(());
+        };
+        let keys: Vec<String> = file.data().keys().filter(|key| key.starts_with(prefix)).map(str::to_string).collect();
+        let count = keys.len();
+        if count == 0 {
+            self.driver.print("Nothing to clear\n");
+            return Ok(());
+        }
+        let phrase = format!("delete {} entries", count);
+        let answer = self.driver.prompt_line(format!("This will delete {} entries from {}. Type '{}' to confirm: ", count, alias, phrase).as_str())?;
+        if answer.trim() == phrase {
+            file.data_mut().clear_prefix(prefix);
+            self.driver.print(format!("Deleted {} entries\n", count));
+            self.entries_changed(alias, keys);
+        } else {
+            self.driver.print("Nothing was deleted\n");
+        }
+        Ok(())
+    }
+
+    /// Applies `renames` unless `dry_run` is set, returning the old and new keys of every entry
+    /// that was renamed.
+    fn apply_renames(driver: &mut D, command_index: usize, file: &mut CryptFile<UnlockedFile>, renames: Result<Vec<(String, String)>, RenameCollision>, dry_run: bool) -> Vec<String> {
+        let renames = match renames {
+            Ok(renames) => renames,
+            Err(error) => {
+                driver.report_error(&ReplError::new(ErrorCode::RenameCollision, command_index, format!("Nothing was renamed, {}", error)));
+                return Vec::new();
+            }
+        };
+        let policy = FileSettings::read(file.data()).key_policy();
+        if let Some(reason) = renames.iter().find_map(|(_, to)| policy.check(to).err()) {
+            driver.report_error(&ReplError::new(ErrorCode::KeyRejected, command_index, format!("Nothing was renamed, {}", reason)));
+            return Vec::new();
+        }
+        if dry_run {
+            driver.print(format!("Would rename {} keys:\n", renames.len()));
+        } else {
+            file.data_mut().apply_renames(&renames);
+            driver.print(format!("Renamed {} keys:\n", renames.len()));
+        }
+        for (from, to) in &renames {
+            driver.print(format!("  {} -> {}\n", from, to));
+        }
+        if dry_run {
+            return Vec::new();
+        }
+        renames.into_iter().flat_map(|(from, to)| [from, to]).collect()
+    }
+
+    /// Fires [`HookEvent::EntryChanged`] for each of `keys` in the file open under `alias`.
+    fn entries_changed(&mut self, alias: &str, keys: impl IntoIterator<Item = String>) {
+        for key in keys {
+            self.fire_hook(HookEvent::EntryChanged, alias, Some(&key));
+        }
+    }
+
+    fn merge_files(&mut self, alias: &str, source: &str, on_conflict: Option<ConflictPolicy>) -> Result<(), D::Error> {
+        if alias == source {
+            self.report(ErrorCode::InvalidArgument, "Cannot merge a crypt into itself");
+            return Ok(());
+        }
+        let Some(incoming) = self.open_files.get(source).map(|open| open.file.data().clone()) else {
+            self.report_unknown_alias(source);
+            return Ok(());
+        };
+        self.merge_data(alias, source, incoming, on_conflict)
+    }
+
+    /// Reports that nothing was merged from `source` into `alias` because of the `rejected` keys.
+    fn report_rejected_merge(&mut self, alias: &str, source: &str, rejected: Vec<String>) {
+        self.report(ErrorCode::KeyRejected, format!("Nothing was merged from {}, {} keys don't follow the naming policy of {}", source, rejected.len(), alias));
+        for reason in rejected {
+            self.driver.print(format!("  {}\n", reason));
+        }
+    }
+
+    /// Merges `incoming`, read from `source`, into `alias`. Conflicts are resolved with
+    /// `on_conflict`, or by asking about each one if it isn't given.
+    fn merge_data(&mut self, alias: &str, source: &str, incoming: CryptData, on_conflict: Option<ConflictPolicy>) -> Result<(), D::Error> {
+        let Some(existing) = self.open_files.get(alias).map(|open| open.file.data()) else {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        };
+        let conflicts = existing.conflicts(&incoming);
+        let policy = FileSettings::read(existing).key_policy();
+        let rejected: Vec<String> = incoming.keys()
+            .filter(|key| !existing.contains_key(key))
+            .filter_map(|key| policy.check(key).err())
+            .collect();
+        if !rejected.is_empty() {
+            self.report_rejected_merge(alias, source, rejected);
+            return Ok(());
+        }
+
+        let mut resolutions = HashMap::new();
+        if !conflicts.is_empty() {
+            self.driver.print(format!("{} keys would be overwritten:\n", conflicts.len()));
+            for key in &conflicts {
+                self.driver.print(format!("  {}\n", key));
+            }
+            if on_conflict.is_none() {
+                for key in conflicts {
+                    let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+                        self.report_unknown_alias(alias);
+                        return Ok(());
+                    };
+                    let existing = file.data().get(key.as_str()).unwrap_or_default().to_string();
+                    let incoming_value = incoming.get(key.as_str()).unwrap_or_default();
+                    let resolution = self.resolve_conflict(key.as_str(), existing.as_str(), incoming_value)?;
+                    resolutions.insert(key, resolution);
+                }
+            }
+        }
+
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        };
+        // Merged into a copy, so nothing changes if a key it ends up with, such as the new name of
+        // a renamed value, doesn't follow the naming policy.
+        let existing = file.data();
+        let mut data = existing.clone();
+        let mut edited = Vec::new();
+        for (key, resolution) in &resolutions {
+            if let ConflictResolution::Edit(value) = resolution {
+                data.insert(key.clone(), value.clone());
+                edited.push(key.clone());
+            }
+        }
+        // Kept until the merge is done, a resolved conflict is skipped by it.
+        let incoming_entries: HashMap<&str, Entry> = resolutions.keys()
+            .filter_map(|key| incoming.entry(key).map(|entry| (key.as_str(), entry.clone())))
+            .collect();
+        let MergeReport { added, replaced, kept, renamed } = data.merge(incoming, |key| {
+            on_conflict.or_else(|| match resolutions.get(key) {
+                Some(ConflictResolution::Policy(policy)) => Some(*policy),
+                _ => None
+            }).unwrap_or(ConflictPolicy::KeepExisting)
+        });
+        // Only conflicts settled in favour of the existing value are recorded, skipped and renamed
+        // ones are asked about again on the next merge.
+        for (key, resolution) in &resolutions {
+            let settled = matches!(resolution, ConflictResolution::Edit(_) | ConflictResolution::Policy(ConflictPolicy::KeepExisting));
+            if let Some(entry) = incoming_entries.get(key.as_str()).filter(|_| settled) {
+                data.mark_resolved(key, entry);
+            }
+        }
+        let rejected: Vec<String> = data.keys()
+            .filter(|key| !existing.contains_key(key))
+            .filter_map(|key| policy.check(key).err())
+            .collect();
+        if !rejected.is_empty() {
+            self.report_rejected_merge(alias, source, rejected);
+            return Ok(());
+        }
+        let (before, after) = (existing.payload_size(), data.payload_size());
+        if let Some(overflow) = self.payload_overflow(after.saturating_sub(before)) {
+            self.report(ErrorCode::LimitExceeded, format!("Refusing to merge {} into {}, it would exceed the decrypted data limit by {} bytes", source, alias, overflow));
+            return Ok(());
+        }
+        if let Some(max) = self.limits.max_crypt_size.filter(|max| after > *max && after > before) {
+            self.report(ErrorCode::LimitExceeded, format!(
+                "Refusing to merge {} into {}, it would grow to {} but files are limited to {}",
+                source, alias, format_size(after), format_size(max)
+            ));
+            return Ok(());
+        }
+        if let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) {
+            *file.data_mut() = data;
+        }
+        self.driver.print(format!("Merged {} into {}:\n", source, alias));
+        let rows = vec![
+            vec!["added".to_string(), added.len().to_string()],
+            vec!["replaced".to_string(), replaced.len().to_string()],
+            vec!["kept".to_string(), kept.len().to_string()],
+            vec!["renamed".to_string(), renamed.len().to_string()],
+            vec!["edited".to_string(), edited.len().to_string()],
+        ];
+        self.driver.print(self.output.table(&rows));
+        for (key, new_key) in &renamed {
+            self.driver.print(format!("  {} -> {}\n", key, new_key));
+        }
+        let renamed = renamed.into_iter().map(|(_, new_key)| new_key);
+        self.entries_changed(alias, added.into_iter().chain(replaced).chain(renamed).chain(edited));
+        Ok(())
+    }
+
+    fn import_file(&mut self, alias: &str, format: &ImportFormat, filepath: &str, on_conflict: Option<ConflictPolicy>) -> Result<(), D::Error> {
+        if !self.open_files.contains_key(alias) {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        }
+        let open = || std::fs::File::open(filepath).map(std::io::BufReader::new).map_err(|error| error.to_string());
+        let result = match format {
+            ImportFormat::Json => open().and_then(|reader| CryptData::read_json(reader).map_err(|error| match error {
+                // The position is safe to show, unlike the rest of the error, which can quote values.
+                CryptFileError::Json(error) => format!("expected a JSON object of string values at line {} column {}", error.line(), error.column()),
+                error => error.to_string()
+            })),
+            ImportFormat::Csv(options) => open().and_then(|reader| read_csv(reader, options).map_err(|error| error.to_string())),
+            ImportFormat::Dotenv => open().and_then(|reader| read_dotenv(reader).map_err(|error| error.to_string())),
+            ImportFormat::Bitwarden => open().and_then(|reader| read_bitwarden(reader).map_err(|error| error.to_string())),
+            ImportFormat::Browser => open().and_then(|reader| read_browser_csv(reader).map_err(|error| error.to_string())),
+            ImportFormat::Bundle => self.read_bundle(filepath)?,
+            #[cfg(feature = "onepassword")]
+            ImportFormat::OnePux => open()
+                .and_then(|reader| crate::import::read_1pux(reader).map_err(|error| error.to_string()))
+                .map(|import| {
+                    if !import.skipped.is_empty() {
+                        self.driver.print(format!("{} items or fields of {} couldn't be imported:\n", import.skipped.len(), filepath));
+                        for skipped in &import.skipped {
+                            self.driver.print(format!("  {}\n", skipped));
+                        }
+                    }
+                    import.data
+                }),
+            #[cfg(feature = "gpg")]
+            ImportFormat::Pass => crate::path::expand_home(Path::new(filepath))
+                .map_err(|error| error.to_string())
+                .and_then(|dir| crate::import::read_pass_store(&dir).map_err(|error| error.to_string())),
+        };
+        let incoming = match result {
+            Ok(incoming) => incoming,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to import {}: {}", filepath, error));
+                return Ok(());
+            }
+        };
+        self.merge_data(alias, filepath, incoming, on_conflict)
+    }
+
+    fn clone_file(&mut self, alias: &str, filepath: &str, prefix: Option<&str>) -> Result<(), D::Error> {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        };
+        let data = file.data().filtered(prefix.unwrap_or(""), None);
+        let count = data.len();
+        if let Some(filepath) = self.lock_new_file(filepath, data, "clone")? {
+            self.driver.print(format!("Cloned {} entries to {}\n", count, filepath.display()));
+        }
         Ok(())
     }
 
-    fn lock_file(&mut self, alias: impl AsRef<str>) -> Result<bool, CryptFileError> {
-        let alias = alias.as_ref();
-        let (password, file) = match self.open_files.remove(alias) {
-            Some(file) => file,
-            None => {
-                return Ok(false);
+    /// Writes the matching entries of `alias` to a new file locked with a password of its own,
+    /// which `import <alias> bundle` reads back.
+    fn export_bundle(&mut self, alias: &str, filepath: &str, prefix: Option<&str>, tag: Option<&str>) -> Result<(), D::Error> {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        };
+        let data = file.data().filtered(prefix.unwrap_or(""), tag);
+        let count = data.len();
+        if let Some(filepath) = self.lock_new_file(filepath, data, "export")? {
+            self.driver.print(format!("Exported {} entries to {}, encrypted with the new password\n", count, filepath.display()));
+        }
+        Ok(())
+    }
+
+    /// Writes `data` to a new file at `filepath`, asking for its password, and returns where it was
+    /// written. `action` names what is being done in the errors.
+    fn lock_new_file(&mut self, filepath: &str, data: CryptData, action: &str) -> Result<Option<PathBuf>, D::Error> {
+        let filepath = match CryptPath::new(filepath) {
+            Ok(filepath) => filepath.into_path_buf(),
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Cannot {} into {}, {}", action, filepath, error));
+                return Ok(None);
             }
         };
-        return match file.lock(password.as_str()) {
-            Ok(_) => Ok(true),
-            Err((file, error)) => {
-                self.open_files.insert(alias.to_string(), (password, file));
-                Err(error)
+        if filepath.exists() {
+            self.report(ErrorCode::FileExists, format!("Refusing to {} into {}, the file already exists", action, filepath.display()));
+            return Ok(None);
+        }
+        let Some(password) = self.prompt_new_password("Enter a password for the new file: ")? else {
+            return Ok(None);
+        };
+        match CryptFile::with_data(filepath, data).lock(password.as_str()) {
+            Ok(file) => Ok(Some(file.filepath().clone())),
+            Err((_, error)) => {
+                self.report(ErrorCode::WriteFailed, format!("Failed to {} file: {}", action, error));
+                Ok(None)
             }
+        }
+    }
+
+    /// Decrypts a bundle `export <alias> bundle` wrote, asking for its password.
+    fn read_bundle(&mut self, filepath: &str) -> Result<Result<CryptData, String>, D::Error> {
+        // Unlocking a missing file would create an empty one.
+        if !Path::new(filepath).is_file() {
+            return Ok(Err("the bundle doesn't exist".to_string()));
+        }
+        let password = Zeroizing::new(self.driver.prompt_password("Enter the password of the bundle: ")?);
+        let bundle = CryptFile::new(PathBuf::from(filepath)).unlock(password.as_str());
+        Ok(bundle.map(|bundle| bundle.data().clone()).map_err(|error| error.to_string()))
+    }
+
+    fn export_file(&mut self, alias: &str, filepath: &str, prefix: Option<&str>, tag: Option<&str>) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
         };
+        let data = file.data().filtered(prefix.unwrap_or(""), tag);
+        let dropped = data.entries().filter(|(_, entry)| !entry.is_plain()).count();
+        if dropped > 0 {
+            self.driver.eprint(format!("{} entries have notes, tags, expiry dates or fields that JSON exports leave out, use crypt export {} bundle <filepath> to keep them\n", dropped, alias));
+        }
+        let result = edit::create_private(Path::new(filepath))
+            .map_err(CryptFileError::from)
+            .and_then(|out| data.write_json(out));
+        match result {
+            Ok(()) => self.driver.print(format!("Exported {} unencrypted entries to {}\n", data.len(), filepath)),
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Failed to export file: {}", error))
+        }
     }
 
-    fn lock_all_files(&mut self) -> Result<(), HashMap<String, CryptFileError>> {
-        let (error_files, errors) = std::mem::take(&mut self.open_files)
-            .into_iter()
-            .filter_map(|(alias, (password, file))| match file.lock(password.as_str()) {
-                Ok(_) => None,
-                Err((file, error)) => Some((alias, password, file, error))
-            })
-            .fold((HashMap::new(), HashMap::new()), |mut acc, (alias, password, file, error)| {
-                acc.0.insert(alias.clone(), (password, file));
-                acc.1.insert(alias, error);
-                acc
+    fn extract_all(&mut self, alias: &str, dir: &str, prefix: Option<&str>, mode: Option<u32>, existing: Existing) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let defaults = ExtractOptions::default();
+        let options = ExtractOptions { mode: mode.unwrap_or(defaults.mode), existing, ..defaults };
+        match extract_all(file.data(), prefix.unwrap_or(""), Path::new(dir), &options) {
+            Ok(report) if report.skipped.is_empty() => self.driver.print(format!("Extracted {} unencrypted files to {}\n", report.written.len(), dir)),
+            Ok(report) => self.driver.print(format!("Extracted {} unencrypted files to {}, skipped {} that already exist\n", report.written.len(), dir, report.skipped.len())),
+            Err(error @ ExtractError::Io(_)) => self.report(ErrorCode::WriteFailed, format!("Failed to extract into {}: {}", dir, error)),
+            Err(error) => self.report(ErrorCode::InvalidArgument, format!("Nothing was extracted into {}, {}", dir, error))
+        }
+    }
+
+    fn list_backups(&mut self, alias: &str) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let backups = match file.backups() {
+            Ok(backups) => backups,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to list backups: {}", error));
+                return;
+            }
+        };
+        if backups.is_empty() {
+            self.driver.print(format!("{} has no backups\n", alias));
+            return;
+        }
+        let rows: Vec<Vec<String>> = backups.iter()
+            .map(|backup| vec![backup_name(&backup.path), format_utc(backup.taken)])
+            .collect();
+        self.driver.print(self.output.table(&rows));
+    }
+
+    fn restore_backup(&mut self, alias: &str, name: &str) {
+        let Some(OpenFile { secret, file, .. }) = self.open_files.get_mut(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        // Only backups of this file can be restored, not any file the name happens to point at.
+        let backup = match file.backups() {
+            Ok(backups) => backups.into_iter().find(|backup| backup_name(&backup.path) == name),
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to list backups: {}", error));
+                return;
+            }
+        };
+        let Some(backup) = backup else {
+            self.report(ErrorCode::InvalidArgument, format!("{} has no backup called {}", alias, name));
+            return;
+        };
+        match secret.restore_backup(file, &backup.path) {
+            Ok(()) => self.driver.print(format!("Restored {} from {}, it is written to disk when the file is next saved\n", alias, name)),
+            Err(error @ CryptFileError::WrongPassword) => self.report(ErrorCode::WrongPassword, format!("Failed to restore the backup, it may use an older password: {}", error)),
+            Err(error) => self.report(ErrorCode::UnlockFailed, format!("Failed to restore the backup: {}", error))
+        }
+    }
+
+    fn share_entry(&mut self, alias: &str, key: &str, filepath: &str, expires_in: Duration) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let Some(value) = file.data().get(key) else {
+            self.report(ErrorCode::UnknownKey, "Key doesn't exist");
+            return;
+        };
+        let Some(expires) = SystemTime::now().checked_add(expires_in) else {
+            self.report(ErrorCode::InvalidArgument, "That expiry is too far in the future");
+            return;
+        };
+        let result = share::seal(key, value, expires)
+            .map_err(|error| error.to_string())
+            .and_then(|(sealed, one_time_key)| {
+                let mut out = OpenOptions::new().write(true).create_new(true).open(filepath).map_err(|error| error.to_string())?;
+                out.write_all(sealed.as_bytes()).map_err(|error| error.to_string())?;
+                Ok(one_time_key)
             });
-        if error_files.is_empty() {
-            Ok(())
+        match result {
+            Ok(one_time_key) => {
+                self.driver.print(format!("Wrote {} to {}, it can be received until {}\n", key, filepath, format_utc(expires)));
+                self.driver.print(format!("Send this one-time key separately: {}\n", one_time_key.as_str()));
+            }
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Failed to share {}: {}", key, error))
+        }
+    }
+
+    /// Stores the entry shared in `filepath` in `alias` and deletes the share, so it can only be
+    /// received once. Existing keys are never replaced.
+    fn receive_entry(&mut self, alias: &str, filepath: &str) -> Result<(), D::Error> {
+        if !self.open_files.contains_key(alias) {
+            self.report_unknown_alias(alias);
+            return Ok(());
+        }
+        let sealed = match std::fs::read_to_string(filepath) {
+            Ok(sealed) => sealed,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to read {}: {}", filepath, error));
+                return Ok(());
+            }
+        };
+        let one_time_key = Zeroizing::new(self.driver.prompt_password("One-time key: ")?);
+        let entry = match share::open(&sealed, one_time_key.as_str(), SystemTime::now()) {
+            Ok(entry) => entry,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to receive {}: {}", filepath, error));
+                return Ok(());
+            }
+        };
+        if self.open_files.get(alias).is_some_and(|open| open.file.data().contains_key(&entry.key)) {
+            self.report(ErrorCode::RenameCollision, format!("{} already has {}, rename it first", alias, entry.key));
+            return Ok(());
+        }
+        if !self.check_set_limits(alias, &entry.key, &entry.value, None, false) {
+            return Ok(());
+        }
+        if let Some(OpenFile { file, .. }) = self.open_files.get_mut(alias) {
+            file.data_mut().insert(entry.key.as_str(), entry.value.as_str());
+        }
+        self.fire_hook(HookEvent::EntryChanged, alias, Some(&entry.key));
+        match std::fs::remove_file(filepath) {
+            Ok(()) => self.driver.print(format!("Received {} into {} and deleted {}\n", entry.key, alias, filepath)),
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Received {}, but failed to delete {}: {}", entry.key, filepath, error))
+        }
+        Ok(())
+    }
+
+    fn export_k8s(&mut self, alias: &str, name: &str, keys: &[Cow<str>]) {
+        let Some(OpenFile { file, .. }) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let data = file.data();
+        let entries: Vec<(&str, &str)> = if keys.is_empty() {
+            data.iter().collect()
         } else {
-            self.open_files = error_files;
-            Err(errors)
+            let mut entries = Vec::new();
+            for key in keys {
+                let Some(value) = data.get(key) else {
+                    self.report(ErrorCode::UnknownKey, format!("Cannot export {}, it is not in {}", key, alias));
+                    return;
+                };
+                entries.push((key.as_ref(), value));
+            }
+            entries
+        };
+        match secret_manifest(name, entries) {
+            Ok(manifest) => self.driver.print(manifest),
+            Err(error) => self.report(ErrorCode::InvalidArgument, format!("Failed to export secret: {}", error))
         }
     }
-}
 
-impl<D: ReplDriver> Repl<D> {
-    /// Creates a new [`Repl`] with `driver`.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// use crypt_client::repl::{ReplDriver, MockDriver, Repl};
-    ///
-    /// let repl = Repl::new(MockDriver::Echo);
-    /// ```
-    ///
-    pub fn new(driver: D) -> Self {
-        Self { driver, open_files: HashMap::new() }
+    fn export_armor(&mut self, filepath: &str, armor_filepath: &str) {
+        let result = CryptFile::new(PathBuf::from(filepath)).to_armor().and_then(|armored| {
+            OpenOptions::new().write(true).create_new(true).open(armor_filepath)?.write_all(armored.as_bytes())?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => self.driver.print(format!("Wrote armored {} to {}\n", filepath, armor_filepath)),
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Failed to export armor: {}", error))
+        }
     }
 
-    /// Execute a command.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// use crypt_client::repl::{ReplDriver, MockDriver, Repl, ReplCommand};
-    ///
-    /// let mut repl = Repl::new(MockDriver::Echo);
-    /// let command = ReplCommand::ClearScreen;
-    /// repl.execute_command(&command).unwrap();
-    /// ```
-    ///
-    pub fn execute_command(&mut self, command: &ReplCommand) -> Result<(), D::Error> {
-        println!("Executing command: {:?}", command);
-        match command {
-            ReplCommand::ClearScreen => {
-                self.driver.clear_screen()?;
-            }
-            ReplCommand::Help => {
-                self.print_usage();
-            }
-            ReplCommand::Exit(ReplExitCommand { no_save, .. }) => {
-                if !*no_save && !self.open_files.is_empty() {
-                    self.driver.print(format!("Attempting to lock {} open files\n", self.open_files.len()));
-                    if let Err(errors) = self.lock_all_files() {
-                        self.driver.eprint(format!("Failed to lock {} files:\n", errors.len()));
-                        for (alias, error) in errors {
-                            self.driver.eprint(format!("  {}: {}\n", alias, error));
-                        }
-                    }
-                }
+    fn import_armor(&mut self, armor_filepath: &str, filepath: &str) {
+        let result = std::fs::read_to_string(armor_filepath)
+            .map_err(CryptFileError::from)
+            .and_then(|armored| CryptFile::from_armor(PathBuf::from(filepath), &armored));
+        match result {
+            Ok(_) => self.driver.print(format!("Wrote {}, unlock it with its original password\n", filepath)),
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Failed to import armor: {}", error))
+        }
+    }
+
+    fn write_manifest(&mut self, alias: &str, filepath: &str) {
+        let Some(open) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let result = open.secret.manifest(open.file.data())
+            .map_err(|error| error.to_string())
+            .and_then(|manifest| std::fs::write(filepath, manifest.to_json()).map_err(|error| error.to_string()));
+        match result {
+            Ok(()) => self.driver.print(format!("Wrote manifest of {} to {}\n", alias, filepath)),
+            Err(error) => self.report(ErrorCode::WriteFailed, format!("Failed to write manifest: {}", error))
+        }
+    }
+
+    fn check_manifest(&mut self, alias: &str, filepath: &str) {
+        let Some(open) = self.open_files.get(alias) else {
+            self.report_unknown_alias(alias);
+            return;
+        };
+        let manifest = match std::fs::read_to_string(filepath).map_err(|error| error.to_string())
+            .and_then(|json| Manifest::from_json(&json).map_err(|error| error.to_string()))
+        {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                self.report(ErrorCode::InvalidArgument, format!("Failed to read manifest: {}", error));
+                return;
             }
-            ReplCommand::Crypt(ReplCryptCommand::List) => {
-                self.driver.print(format!("{} files are currently open:\n", self.open_files.len()));
-                for (alias, (_, file)) in self.open_files.iter() {
-                    self.driver.eprint(format!("  {}: {}\n", alias, file.filepath().display()));
-                }
+        };
+        let diff = match open.secret.check_manifest(&manifest, open.file.data()) {
+            Ok(diff) => diff,
+            Err(error) => {
+                self.report(ErrorCode::ManifestMismatch, format!("Manifest check failed: {}", error));
+                return;
+            }
+        };
+        if diff.is_clean() {
+            self.driver.print(format!("{} matches {}\n", alias, filepath));
+            return;
+        }
+        let rows: Vec<Vec<String>> = [("added", &diff.added), ("removed", &diff.removed), ("changed", &diff.changed)].iter()
+            .flat_map(|(change, keys)| keys.iter().map(move |key| vec![change.to_string(), key.clone()]))
+            .collect();
+        self.driver.print(self.output.table(&rows));
+        self.report(ErrorCode::ManifestMismatch, format!("{} doesn't match {}", alias, filepath));
+    }
+
+    /// Lets the user pick a memorable name for `what`, such as `brave-otter`, or type their own.
+    /// Names `taken` says are in use aren't offered or accepted. If no one can be asked, the first
+    /// free suggestion is taken. Returns [`None`] if they cancel, or if every suggestion is taken.
+    fn choose_name(&mut self, what: &str, taken: impl Fn(&str) -> bool) -> Result<Option<String>, D::Error> {
+        const SUGGESTIONS: usize = 5;
+        let mut names: Vec<String> = Vec::new();
+        // There are thousands of names, so only a crowded directory or prefix runs out of tries.
+        for _ in 0..SUGGESTIONS * 10 {
+            if names.len() == SUGGESTIONS {
+                break;
             }
-            ReplCommand::Crypt(ReplCryptCommand::Unlock { alias, filepath }) => {
-                let password = self.driver.prompt_password("Enter password for file: ")?;
-                if let Err(error) = self.unlock_file(alias.to_string(), filepath.to_string(), password) {
-                    self.driver.eprint(format!("Failed to unlock file: {}\n", error));
+            let name = memorable_name();
+            if !names.contains(&name) && !taken(&name) {
+                names.push(name);
+            }
+        }
+        if !self.driver.is_interactive() {
+            let Some(name) = names.into_iter().next() else {
+                self.report(ErrorCode::InvalidArgument, format!("Give {} a name, every name tried was taken", what));
+                return Ok(None);
+            };
+            self.driver.print(format!("Named {} {}\n", what, name));
+            return Ok(Some(name));
+        }
+        let mut options: Vec<&str> = names.iter().map(String::as_str).collect();
+        options.extend(["enter a name", "cancel"]);
+        self.driver.print(format!("Choose a name for {}:\n", what));
+        let choice = self.driver.select("Name: ", &options)?;
+        if choice < names.len() {
+            return Ok(Some(names.swap_remove(choice)));
+        }
+        if choice == names.len() {
+            // An empty name cancels.
+            loop {
+                let name = self.driver.prompt_line("Name: ")?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return Ok(None);
+                }
+                if !taken(name) {
+                    return Ok(Some(name.to_string()));
                 }
+                self.driver.eprint(format!("{} is taken, enter another name\n", name));
             }
-            ReplCommand::Crypt(ReplCryptCommand::Lock { alias }) => {
-                self.driver.print("Attempting to lock file...\n");
-                if let Err(error) = self.lock_file(alias) {
-                    self.driver.eprint(format!("Failed to lock file: {}\n", error));
+        }
+        Ok(None)
+    }
+
+    /// Names the new entry of a `set` or `generate` given `--name` or an empty key, adding the name
+    /// to the key as a prefix, see [`choose_name`](Self::choose_name). Other commands are returned
+    /// as they are, and [`None`] if no name was chosen.
+    fn name_entry<'c, 'a>(&mut self, alias: &str, cmd: &'c ReplMapCommand<'a>) -> Result<Option<Cow<'c, ReplMapCommand<'a>>>, D::Error> {
+        let (ReplMapCommand::Set { key: prefix, name, .. } | ReplMapCommand::Generate { key: prefix, name, .. }) = cmd else {
+            return Ok(Some(Cow::Borrowed(cmd)));
+        };
+        let Some(open) = self.open_files.get(alias).filter(|_| *name || prefix.is_empty()) else {
+            return Ok(Some(Cow::Borrowed(cmd)));
+        };
+        let taken: HashSet<String> = open.file.data().entries_with_prefix(prefix).map(|(key, _)| key.to_string()).collect();
+        let what = if prefix.is_empty() { "the new entry".to_string() } else { format!("the new entry in {}", prefix) };
+        let Some(name) = self.choose_name(&what, |name| taken.contains(&format!("{}{}", prefix, name)))? else {
+            return Ok(None);
+        };
+        let mut named = cmd.clone();
+        if let ReplMapCommand::Set { key, .. } | ReplMapCommand::Generate { key, .. } = &mut named {
+            *key = Cow::Owned(format!("{}{}", prefix, name));
+        }
+        Ok(Some(Cow::Owned(named)))
+    }
+
+    /// Reports the temporary files interrupted saves of `alias` left behind, which may hold newer
+    /// data than the file, and asks whether to recover from each, delete it or leave it for now.
+    fn recover_temp_files(&mut self, alias: &str, secret: &SessionSecret, file: &mut UnlockedCrypt) -> Result<(), D::Error> {
+        const RECOVERY_OPTIONS: [&str; 3] = ["recover from it", "delete it", "keep it for now"];
+        let temp_files = match file.stale_temp_files() {
+            Ok(temp_files) => temp_files,
+            Err(error) => {
+                self.driver.eprint(format!("Failed to look for files left by interrupted saves of {}: {}\n", alias, error));
+                return Ok(());
+            }
+        };
+        for temp in temp_files {
+            let name = backup_name(&temp);
+            let decrypted = match secret.decrypt_copy(file, &temp) {
+                Ok(copy) => {
+                    let changed = copy.into_iter().filter(|(key, value)| file.data().get(key) != Some(*value)).count()
+                        + file.data().keys().filter(|key| copy.get(key).is_none()).count();
+                    if changed == 0 {
+                        self.driver.print(format!("{} was left by an interrupted save of {}, and holds the same entries\n", name, alias));
+                    } else {
+                        self.driver.print(format!("{} was left by an interrupted save of {}, and {} of its entries differ from the file\n", name, alias, changed));
+                    }
+                    true
+                }
+                Err(error) => {
+                    self.driver.print(format!("{} was left by an interrupted save of {}, and can't be decrypted: {}\n", name, alias, error));
+                    false
                 }
+            };
+            if !self.driver.is_interactive() {
+                self.driver.eprint(format!("Leaving {}, delete it or run crypt compact {} once {} is checked\n", name, alias, alias));
+                continue;
             }
-            ReplCommand::Crypt(ReplCryptCommand::Data { alias, cmd }) => {
-                match cmd {
-                    ReplMapCommand::List => match self.open_files.get((*alias).as_ref()) {
-                        Some((_, file)) => {
-                            self.driver.print("Listing data:\n");
-                            for (key, value) in file.data() {
-                                self.driver.print(format!("  {}={}\n", key, value));
-                            }
-                        }
-                        None => self.driver.eprint(format!("No files are open with the alias: {}\n", alias))
-                    },
-                    ReplMapCommand::Get { key } => match self.open_files.get((*alias).as_ref()) {
-                        Some((_, file)) => {
-                            match file.data().get((*key).as_ref()) {
-                                Some(value) => self.driver.print(format!("{}\n", value)),
-                                None => self.driver.eprint("Key doesn't exist\n")
-                            }
-                        }
-                        None => self.driver.eprint(format!("No files are open with the alias: {}\n", alias))
-                    },
-                    ReplMapCommand::Set {key, value} => match self.open_files.get_mut((*alias).as_ref()) {
-                        Some((_, file)) => {
-                            file.data_mut().insert(key.to_string(), value.to_string());
-                        }
-                        None => self.driver.eprint(format!("No files are open with the alias: {}\n", alias))
-                    },
-                    ReplMapCommand::Delete {key} => match self.open_files.get_mut((*alias).as_ref()) {
-                        Some((_, file)) => {
-                            file.data_mut().remove((*key).as_ref());
-                        }
-                        None => self.driver.eprint(format!("No files are open with the alias: {}\n", alias))
+            let options = if decrypted { &RECOVERY_OPTIONS[..] } else { &RECOVERY_OPTIONS[1..] };
+            // Without the recover option the other choices move up one.
+            let choice = self.driver.select(format!("{}: ", name).as_str(), options)? + usize::from(!decrypted);
+            match choice {
+                0 => match secret.restore_backup(file, &temp) {
+                    Ok(()) => self.driver.print(format!("Recovered {} from {}, save it to keep the changes and run crypt compact {} to remove {}\n", alias, name, alias, name)),
+                    Err(error) => self.report(ErrorCode::UnlockFailed, format!("Failed to recover {} from {}: {}", alias, name, error))
+                },
+                1 => {
+                    if let Err(error) = std::fs::remove_file(&temp) {
+                        self.report(ErrorCode::WriteFailed, format!("Failed to delete {}: {}", name, error));
                     }
                 }
+                _ => {}
             }
         }
         Ok(())
     }
 
+    /// Asks how to resolve a single merge conflict, showing both values masked until the user
+    /// chooses to reveal them.
+    fn resolve_conflict(&mut self, key: &str, existing: &str, incoming: &str) -> Result<ConflictResolution, D::Error> {
+        const OPTIONS: [&str; 6] = ["keep existing", "take incoming", "rename incoming", "edit value", "skip", "reveal values"];
+        self.driver.print(format!("Conflict on {}:\n", key));
+        self.driver.print(format!("  existing: {}\n", mask(existing)));
+        self.driver.print(format!("  incoming: {}\n", mask(incoming)));
+        loop {
+            let resolution = match self.driver.select(format!("{}: ", key).as_str(), &OPTIONS)? {
+                0 => ConflictResolution::Policy(ConflictPolicy::KeepExisting),
+                1 => ConflictResolution::Policy(ConflictPolicy::TakeIncoming),
+                2 => ConflictResolution::Policy(ConflictPolicy::RenameIncoming),
+                3 => ConflictResolution::Edit(self.driver.prompt_password(format!("New value for {}: ", key).as_str())?),
+                4 => ConflictResolution::Skip,
+                _ => {
+                    self.driver.print(format!("  existing: {}\n", existing));
+                    self.driver.print(format!("  incoming: {}\n", incoming));
+                    continue;
+                }
+            };
+            return Ok(resolution);
+        }
+    }
+
     /// Prompt for, parse, and execute a single command.
     ///
-    /// If the command entered is `exit <code> [--no-save]`, all open files will be saved unless
-    /// the `--no-save` flag is present, then the parsed [`ReplExitCommand`] will be returned.
+    /// If the command entered is `exit <code> [--save|--no-save]`, open files are saved or
+    /// discarded as described in [`prepare_exit()`](Self::prepare_exit), then the parsed
+    /// [`ReplExitCommand`] will be returned. If the user cancels the exit, [`None`] is returned.
     ///
     /// All other commands will be executed internally and [`None`] will be returned.
     ///
@@ -199,7 +2632,7 @@ impl<D: ReplDriver> Repl<D> {
     /// use crypt_client::repl::{ReplDriver, MockDriver, Repl, ReplExitCommand};
     ///
     /// let mut repl = Repl::new(MockDriver::MockDefault("exit 1 --no-save".to_string()));
-    /// if let Some(ReplExitCommand { code, no_save }) = repl.tick().unwrap() {
+    /// if let Some(ReplExitCommand { code, no_save, .. }) = repl.tick().unwrap() {
     ///     print!("Exiting with code {}, ", code);
     ///     if no_save {
     ///         println!("without saving");
@@ -212,21 +2645,162 @@ impl<D: ReplDriver> Repl<D> {
     /// ```
     ///
     pub fn tick(&mut self) -> Result<Option<ReplExitCommand>, D::Error> {
+        let completions = self.completions();
+        self.driver.set_completions(completions);
         let command_str = self.driver.prompt_line("> ")?;
-        let command = match ReplCommand::try_from(command_str.as_str()) {
-            Ok(command) => command,
+        self.run_line(command_str.as_str())
+    }
+
+    /// Expands variables in, parses and runs a single command line, typed or read from a sourced
+    /// file.
+    fn run_line(&mut self, command_str: &str) -> Result<Option<ReplExitCommand>, D::Error> {
+        self.command_index += 1;
+        let expanded = match expand_variables(command_str, &self.variables) {
+            Ok(expanded) => expanded,
+            Err(name) => {
+                self.report(ErrorCode::UnknownVariable, format!("Variable {} is not set", name));
+                return Ok(None);
+            }
+        };
+        let (command, rest) = match parse_command_line(expanded.as_ref()) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.report(ErrorCode::InvalidCommand, describe_parse_error(expanded.as_ref(), &error));
+                return Ok(None);
+            }
+        };
+        if !rest.trim().is_empty() {
+            let description = describe_extra_argument(expanded.as_ref(), rest);
+            match self.extra_arguments {
+                ExtraArguments::Reject => {
+                    self.report(ErrorCode::InvalidCommand, format!("Invalid command, {}", description));
+                    return Ok(None);
+                }
+                ExtraArguments::Warn => self.driver.eprint(format!("Ignoring {}\n", description))
+            }
+        }
+        let started = Instant::now();
+        let exit_command = self.dispatch(&command)?;
+        for failure in self.hooks.take_failures() {
+            self.report(ErrorCode::HookFailed, failure);
+        }
+        self.timings.push(CommandTiming {
+            command_index: self.command_index,
+            command: redact_command(command_str).into_owned(),
+            duration: started.elapsed(),
+            kdf_duration: self.unlock_kdf_duration.take(),
+        });
+        Ok(exit_command)
+    }
+
+    /// Runs a parsed command, returning the exit command if the REPL should exit.
+    fn dispatch(&mut self, command: &ReplCommand) -> Result<Option<ReplExitCommand>, D::Error> {
+        match command {
+            ReplCommand::Exit(exit_command) => Ok(self.prepare_exit(exit_command)?.then(|| exit_command.clone())),
+            ReplCommand::IfSet { name, command } if self.variables.contains_key(name.as_ref()) => self.dispatch(command),
+            ReplCommand::IfSet { .. } => Ok(None),
+            ReplCommand::Source { filepath } => self.source_file(filepath),
+            command => {
+                self.execute_command(command)?;
+                self.autosave();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs each line of the file at `filepath` as a command, stopping early if one exits.
+    fn source_file(&mut self, filepath: &str) -> Result<Option<ReplExitCommand>, D::Error> {
+        if self.source_depth >= MAX_SOURCE_DEPTH {
+            self.report(ErrorCode::LimitExceeded, format!("Cannot source {}, sourced files are nested more than {} deep", filepath, MAX_SOURCE_DEPTH));
+            return Ok(None);
+        }
+        let script = match std::fs::read_to_string(filepath) {
+            Ok(script) => script,
             Err(error) => {
-                let context = nom::error::convert_error(command_str.as_str(), error);
-                let error_message = format!("Invalid command:\n{}\n", context);
-                self.driver.eprint(error_message);
+                self.report(ErrorCode::InvalidArgument, format!("Failed to read {}: {}", filepath, error));
                 return Ok(None);
             }
         };
-        self.execute_command(&command)?;
+        self.source_depth += 1;
+        let mut exit_command = None;
+        for line in script.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            exit_command = self.run_line(line)?;
+            if exit_command.is_some() {
+                break;
+            }
+        }
+        self.source_depth -= 1;
+        Ok(exit_command)
+    }
+
+    fn execute_variable_command(&mut self, command: &ReplCommand) {
         match command {
-            ReplCommand::Exit(exit_command) => Ok(Some(exit_command)),
-            _ => Ok(None)
+            ReplCommand::Let { name, value } => {
+                self.variables.insert(name.to_string(), value.to_string());
+            }
+            ReplCommand::Unset { name } => {
+                if self.variables.remove(name.as_ref()).is_none() {
+                    self.report(ErrorCode::UnknownVariable, format!("Variable {} is not set", name));
+                }
+            }
+            _ => {
+                let rows: Vec<Vec<String>> = self.variables.iter()
+                    .map(|(name, value)| vec![name.clone(), value.clone()])
+                    .collect();
+                self.driver.print(format!("{} variables are set:\n", rows.len()));
+                self.driver.print(self.output.table(&rows));
+            }
+        }
+    }
+
+    /// Saves or discards open files before exiting, returning `false` if the exit was cancelled.
+    ///
+    /// With `--no-save` every open file is discarded, and with `--save` every open file is saved.
+    /// Otherwise the user is asked whether to save or discard each file with unsaved changes, or
+    /// to cancel the exit. The exit is also cancelled if a file chosen to be saved fails to save.
+    pub fn prepare_exit(&mut self, command: &ReplExitCommand) -> Result<bool, D::Error> {
+        if command.no_save || self.open_files.is_empty() {
+            return Ok(true);
+        }
+        if command.save {
+            self.driver.print(format!("Attempting to lock {} open files\n", self.open_files.len()));
+            let report = self.lock_all_files();
+            self.print_report(&report, ErrorCode::LockFailed);
+            return Ok(true);
+        }
+
+        let mut dirty: Vec<String> = self.open_files.iter()
+            .filter(|(_, open)| open.file.is_dirty())
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        if dirty.is_empty() {
+            return Ok(true);
+        }
+        dirty.sort();
+        self.driver.print(format!("{} files have unsaved changes:\n", dirty.len()));
+        for alias in &dirty {
+            self.driver.print(format!("  {}\n", alias));
+        }
+        let mut save = Vec::new();
+        for alias in dirty {
+            match self.driver.select(format!("{}: ", alias).as_str(), &["save", "discard", "cancel exit"])? {
+                0 => save.push(alias),
+                1 => {}
+                _ => return Ok(false)
+            }
+        }
+        let mut report = OutcomeReport::new();
+        for alias in save {
+            match self.lock_file(alias.as_str()) {
+                Ok(()) => report.succeeded(alias, "saved and closed"),
+                Err(error) => report.failed(alias, error.to_string())
+            }
+        }
+        self.print_report(&report, ErrorCode::LockFailed);
+        if !report.is_success() {
+            self.driver.print("Exit cancelled, files that failed to save are still open\n");
         }
+        Ok(report.is_success())
     }
 
     /// Calls [`tick()`] in a loop until it returns a [`ReplExitCommand`].
@@ -237,7 +2811,7 @@ impl<D: ReplDriver> Repl<D> {
     /// use crypt_client::repl::{ReplDriver, MockDriver, Repl, ReplExitCommand};
     ///
     /// let mut repl = Repl::new(MockDriver::MockDefault("exit 1 --no-save".to_string()));
-    /// let ReplExitCommand { code, no_save } = repl.run().unwrap();
+    /// let ReplExitCommand { code, no_save, .. } = repl.run().unwrap();
     /// print!("Exiting with code {}, ", code);
     /// if no_save {
     ///     println!("without saving");
@@ -273,8 +2847,316 @@ impl<D: ReplDriver> Repl<D> {
         std::process::exit(code);
     }
 
+    /// Clears the screen, or scrolls it with blank lines if the driver can't clear it. Failing to
+    /// clear the screen never ends the session.
+    fn clear_screen(&mut self) {
+        if self.driver.can_clear_screen() && self.driver.clear_screen().is_ok() {
+            return;
+        }
+        let height = terminal_size::terminal_size().map_or(DEFAULT_SCROLL_LINES, |(_, terminal_size::Height(height))| usize::from(height));
+        self.driver.print("\n".repeat(height));
+        self.driver.eprint("This terminal can't be cleared, scrolled past the previous output instead\n");
+    }
+
+    fn print_timings(&mut self) {
+        self.driver.print(format!("{} commands timed:\n", self.timings.len()));
+        for timing in &self.timings {
+            let kdf = timing.kdf_duration.map(|kdf| format!(" (key derivation: {:.1?})", kdf)).unwrap_or_default();
+            self.driver.print(format!("  #{} {:.1?} {}{}\n", timing.command_index, timing.duration, timing.command, kdf));
+        }
+        let unlocks: Vec<Duration> = self.timings.iter().filter_map(|timing| timing.kdf_duration).collect();
+        if let Some(slowest) = unlocks.iter().max() {
+            let count = u32::try_from(unlocks.len()).unwrap_or(u32::MAX);
+            let average = unlocks.iter().sum::<Duration>() / count;
+            self.driver.print(format!("Key derivation over {} unlocks: average {:.1?}, slowest {:.1?}\n", unlocks.len(), average, slowest));
+        }
+    }
+
     /// Prints REPL commands and usage.
     pub fn print_usage(&mut self) {
-        self.driver.print(USAGE_TEXT);
+        self.driver.print(usage_text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{legacy_gpg_lookalike, Kdf};
+    use crate::testing::{ScriptedDriver, TempDir, FAST_KDF};
+
+    /// Writes a crypt holding `pairs`, locked with `password`.
+    fn write_crypt(filepath: &Path, password: &str, pairs: &[(&str, &str)]) {
+        let mut file = CryptFile::new(filepath.to_path_buf()).with_kdf(FAST_KDF).unlock(password).unwrap();
+        for (key, value) in pairs {
+            file.data_mut().insert(*key, *value);
+        }
+        file.lock(password).map_err(|(_, error)| error).unwrap();
+    }
+
+    fn run(repl: &mut Repl<ScriptedDriver>, line: &str) {
+        repl.run_line(line).unwrap();
+    }
+
+    fn data<'a>(repl: &'a Repl<ScriptedDriver>, alias: &str) -> &'a CryptData {
+        repl.open_files[alias].file.data()
+    }
+
+    #[test]
+    fn legacy_file_that_looks_like_gpg_asks_for_the_password() {
+        let dir = TempDir::new("repl-legacy-gpg");
+        let filepath = dir.join("legacy.crypt");
+        std::fs::write(&filepath, legacy_gpg_lookalike("password", &Kdf::default())).unwrap();
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert!(repl.driver.answers.is_empty());
+        assert_eq!((repl.open_files["v"].file.container(), data(&repl, "v").get("a")), (Container::Crypt, Some("1")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exports_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("repl-export");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "1")]);
+        let json = dir.join("export.json");
+        let bundle = dir.join("bundle.crypt");
+
+        // The vault's password, then the bundle's twice.
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "bundle", "bundle"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, &format!("crypt export v json {}", json.display()));
+        run(&mut repl, &format!("crypt export v bundle {}", bundle.display()));
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        for path in [json, bundle] {
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn shares_that_never_expire_are_refused() {
+        let dir = TempDir::new("repl-share");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "1")]);
+        let share = dir.join("a.share");
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, &format!("crypt share v a {} --expires 18446744073709551615s", share.display()));
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("too far in the future")), "{:?}", repl.driver.errors);
+        assert!(!share.exists());
+    }
+
+    #[test]
+    fn show_big_is_cleared_by_a_key() {
+        let dir = TempDir::new("repl-show-big");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "1")]);
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, "crypt data v show-big a --for 7d");
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert_eq!(repl.driver.output.matches("Clearing the screen in").count(), 1);
+        assert!(repl.driver.output.contains("Clearing the screen in 300s"), "{}", repl.driver.output);
+    }
+
+    #[test]
+    fn trash_kept_for_too_long_to_count() {
+        let dir = TempDir::new("repl-trash");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "1")]);
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password"]));
+        repl.set_trash(TrashPolicy { enabled: true, keep_days: u64::MAX });
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, "crypt data v delete a");
+        run(&mut repl, "crypt data v trash list");
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert!(repl.driver.output.contains("never purged"), "{}", repl.driver.output);
+    }
+
+    #[test]
+    fn typed_entry_names_are_checked_against_taken_keys() {
+        let dir = TempDir::new("repl-name-typed");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("aws/old", "kept")]);
+
+        // The password, then "enter a name" after the five suggestions, a taken name and a free one.
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "6", "old", "new"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, "crypt data v set aws/ secret --name");
+        // Without --name a key ending with / is set as it is.
+        run(&mut repl, "crypt data v set dir/ value");
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert!(repl.driver.output.contains("old is taken"), "{}", repl.driver.output);
+        assert_eq!(data(&repl, "v").get("aws/old"), Some("kept"));
+        assert_eq!(data(&repl, "v").get("aws/new"), Some("secret"));
+        assert_eq!(data(&repl, "v").get("dir/"), Some("value"));
+    }
+
+    #[test]
+    fn batch_runs_name_entries_themselves() {
+        let dir = TempDir::new("repl-name-batch");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[]);
+
+        let mut repl = Repl::new(ScriptedDriver { batch: true, ..ScriptedDriver::new(&["password"]) });
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, "crypt data v generate tokens/ uuid --name");
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        let keys: Vec<&str> = data(&repl, "v").keys().collect();
+        assert!(matches!(keys.as_slice(), [key] if key.starts_with("tokens/") && key.len() > "tokens/".len()), "{:?}", keys);
+    }
+
+    #[test]
+    fn moves_wait_for_unsaved_changes_of_the_destination() {
+        let dir = TempDir::new("repl-move-unsaved");
+        let (from, to) = (dir.join("from.crypt"), dir.join("to.crypt"));
+        write_crypt(&from, "password", &[("a", "moved")]);
+        write_crypt(&to, "password", &[]);
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "password"]));
+        run(&mut repl, &format!("crypt unlock from {}", from.display()));
+        run(&mut repl, &format!("crypt unlock to {}", to.display()));
+        run(&mut repl, "crypt data to set draft unsaved");
+        run(&mut repl, "crypt data from move ^a$ to");
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("unsaved changes")), "{:?}", repl.driver.errors);
+        assert_eq!((data(&repl, "from").get("a"), data(&repl, "to").get("a")), (Some("moved"), None));
+    }
+
+    #[test]
+    fn moves_are_rolled_back_when_the_destination_cant_be_saved() {
+        let dir = TempDir::new("repl-move-rollback");
+        let from = dir.join("from.crypt");
+        let to_dir = dir.join("gone");
+        std::fs::create_dir(&to_dir).unwrap();
+        let to = to_dir.join("to.crypt");
+        write_crypt(&from, "password", &[("a", "moved")]);
+        write_crypt(&to, "password", &[("b", "kept")]);
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "password"]));
+        run(&mut repl, &format!("crypt unlock from {}", from.display()));
+        run(&mut repl, &format!("crypt unlock to {}", to.display()));
+        // Nowhere left to save to.
+        std::fs::remove_dir_all(&to_dir).unwrap();
+        run(&mut repl, "crypt data from move ^a$ to");
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("could not be saved")), "{:?}", repl.driver.errors);
+        assert_eq!((data(&repl, "from").get("a"), data(&repl, "to").get("a")), (Some("moved"), None));
+        assert_eq!(data(&repl, "to").get("b"), Some("kept"));
+    }
+
+    #[test]
+    fn replacing_a_value_counts_only_the_difference_against_the_limits() {
+        let dir = TempDir::new("repl-limit-replace");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "12345")]);
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        let size = data(&repl, "v").payload_size();
+        repl.set_limits(ReplLimits { max_payload_size: Some(size), max_crypt_size: Some(size), ..ReplLimits::default() });
+        run(&mut repl, "crypt data v set a 54321");
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        run(&mut repl, "crypt data v set a 654321");
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("limit")), "{:?}", repl.driver.errors);
+        assert_eq!(data(&repl, "v").get("a"), Some("54321"));
+    }
+
+    #[test]
+    fn merges_and_imports_are_held_to_the_limits() {
+        let dir = TempDir::new("repl-limit-merge");
+        let (filepath, other) = (dir.join("vault.crypt"), dir.join("other.crypt"));
+        write_crypt(&filepath, "password", &[("a", "old")]);
+        write_crypt(&other, "password", &[("b", "a value too long to fit")]);
+        let incoming = dir.join("incoming.json");
+        std::fs::write(&incoming, r#"{"c": "a value too long to fit"}"#).unwrap();
+
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "password"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, &format!("crypt unlock w {}", other.display()));
+        let size = data(&repl, "v").payload_size();
+        repl.set_limits(ReplLimits { max_crypt_size: Some(size + 4), ..ReplLimits::default() });
+        run(&mut repl, "crypt merge v w");
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("files are limited")), "{:?}", repl.driver.errors);
+
+        let total = data(&repl, "v").payload_size() + data(&repl, "w").payload_size();
+        repl.set_limits(ReplLimits { max_payload_size: Some(total + 4), ..ReplLimits::default() });
+        run(&mut repl, &format!("crypt import v json {}", incoming.display()));
+        assert!(matches!(repl.driver.errors.as_slice(), [_, error] if error.contains("decrypted data limit")), "{:?}", repl.driver.errors);
+        assert_eq!((data(&repl, "v").get("b"), data(&repl, "v").get("c")), (None, None));
+    }
+
+    #[test]
+    fn executed_commands_pass_on_exits_from_if_set_and_source() {
+        let dir = TempDir::new("repl-exit-propagation");
+        let script = dir.join("script");
+        std::fs::write(&script, "let done 1\nexit 4\n").unwrap();
+
+        let mut repl = Repl::new(ScriptedDriver::new(&[]));
+        let source = format!("source {}", script.display());
+        let exit = repl.execute_command(&ReplCommand::try_from(source.as_str()).unwrap()).unwrap();
+        assert_eq!(exit.map(|exit| exit.code), Some(4));
+        let exit = repl.execute_command(&ReplCommand::try_from("if-set done then exit 5").unwrap()).unwrap();
+        assert_eq!(exit.map(|exit| exit.code), Some(5));
+        assert_eq!(repl.execute_command(&ReplCommand::try_from("if-set missing then exit 6").unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    fn merge_renames_incoming_values() {
+        let dir = TempDir::new("repl-merge-rename");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "old")]);
+        let incoming = dir.join("incoming.json");
+        std::fs::write(&incoming, r#"{"a": "new"}"#).unwrap();
+
+        // The password, then "rename incoming" for the conflict on a.
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "3"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, &format!("crypt import v json {}", incoming.display()));
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert_eq!(data(&repl, "v").get("a"), Some("old"));
+        assert_eq!(data(&repl, "v").get("a.incoming"), Some("new"));
+        // Renaming doesn't settle the conflict, the next merge asks again.
+        let incoming = CryptData::read_json(r#"{"a": "new"}"#.as_bytes()).unwrap();
+        assert_eq!(data(&repl, "v").conflicts(&incoming), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn merge_checks_renamed_keys_against_the_naming_policy() {
+        let dir = TempDir::new("repl-merge-policy");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "old")]);
+        let incoming = dir.join("incoming.json");
+        std::fs::write(&incoming, r#"{"a": "new", "b": "added"}"#).unwrap();
+
+        // The password, then "rename incoming" for the conflict on a.
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "3"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, "crypt meta v set-setting key_pattern [a-z]+");
+        run(&mut repl, &format!("crypt import v json {}", incoming.display()));
+        assert!(matches!(repl.driver.errors.as_slice(), [error] if error.contains("naming policy")), "{:?}", repl.driver.errors);
+        assert_eq!((data(&repl, "v").get("a"), data(&repl, "v").get("b"), data(&repl, "v").get("a.incoming")), (Some("old"), None, None));
+    }
+
+    #[test]
+    fn merge_remembers_kept_values() {
+        let dir = TempDir::new("repl-merge-keep");
+        let filepath = dir.join("vault.crypt");
+        write_crypt(&filepath, "password", &[("a", "old")]);
+        let incoming = dir.join("incoming.json");
+        std::fs::write(&incoming, r#"{"a": "new"}"#).unwrap();
+
+        // Only one answer for the conflict, the second import mustn't ask again.
+        let mut repl = Repl::new(ScriptedDriver::new(&["password", "1"]));
+        run(&mut repl, &format!("crypt unlock v {}", filepath.display()));
+        run(&mut repl, &format!("crypt import v json {}", incoming.display()));
+        run(&mut repl, &format!("crypt import v json {}", incoming.display()));
+        assert!(repl.driver.errors.is_empty(), "{:?}", repl.driver.errors);
+        assert_eq!(data(&repl, "v").get("a"), Some("old"));
+        assert_eq!(data(&repl, "v").len(), 1);
     }
 }
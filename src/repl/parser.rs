@@ -1,13 +1,21 @@
 use std::borrow::Cow;
 use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use crate::file::{CipherKind, Compression, ConflictPolicy, Container, Field, Layout};
+use crate::extract::Existing;
+use crate::generate::Generator;
+use crate::import::{CsvColumn, CsvOptions};
+use crate::timestamp::parse_utc_date;
+use crate::repl::AutosavePolicy;
 use nom::{IResult, Err};
-use nom::bytes::complete::{tag, take_till, take};
+use nom::bytes::complete::{tag, take_till, take, take_while1};
 use nom::error::{ParseError, VerboseError, ContextError, context};
 use nom::sequence::{delimited, preceded, terminated, tuple, separated_pair};
 use nom::character::complete::{char, digit1, none_of, multispace1};
 use nom::branch::alt;
-use nom::combinator::{value, map, opt};
-use nom::multi::fold_many0;
+use nom::combinator::{value, map, map_opt, opt, peek, verify};
+use nom::multi::{fold_many0, many0, separated_list1};
 
 /// Parse a quoted string.
 ///
@@ -123,23 +131,470 @@ pub fn parse_i32<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a
     )(input)
 }
 
+/// Parse an optional trailing `--dry-run` flag.
+fn parse_dry_run<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, bool, E> {
+    map(opt(preceded(multispace1, tag("--dry-run"))), |flag| flag.is_some())(input)
+}
+
+/// The order `list` shows entries in, instead of by key.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ListSort {
+    /// ```last-accessed```, least recently read first.
+    LastAccessed,
+    /// ```reads```, least read first.
+    Reads,
+}
+
+/// Parse an optional trailing `--sort <last-accessed|reads>`.
+fn parse_list_sort<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<ListSort>, E> {
+    opt(preceded(tuple((multispace1, tag("--sort"), multispace1)), alt((
+        value(ListSort::LastAccessed, tag("last-accessed")),
+        value(ListSort::Reads, tag("reads")),
+    ))))(input)
+}
+
+/// Parse an optional trailing `--cipher <aes-256-gcm|chacha20-poly1305>`.
+fn parse_cipher<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<CipherKind>, E> {
+    opt(preceded(tuple((multispace1, tag("--cipher"), multispace1)), alt((
+        value(CipherKind::Aes256Gcm, tag("aes-256-gcm")),
+        value(CipherKind::ChaCha20Poly1305, tag("chacha20-poly1305")),
+    ))))(input)
+}
+
+/// Parse an optional trailing `--compression <none|zstd>`, where `zstd` needs the `zstd` feature.
+fn parse_compression<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<Compression>, E> {
+    opt(preceded(tuple((multispace1, tag("--compression"), multispace1)), |input: &'a str| {
+        #[cfg(feature = "zstd")]
+        if let Ok((next, _)) = tag::<_, _, E>("zstd")(input) {
+            return Ok((next, Compression::Zstd));
+        }
+        value(Compression::None, tag("none"))(input)
+    }))(input)
+}
+
+/// Parse an optional trailing `--layout <single|chunked>`.
+fn parse_layout<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<Layout>, E> {
+    opt(preceded(tuple((multispace1, tag("--layout"), multispace1)), alt((
+        value(Layout::Single, tag("single")),
+        value(Layout::Chunked, tag("chunked")),
+    ))))(input)
+}
+
+/// Parse an optional trailing `--container <crypt|age|gpg|openssl>`, where `age`, `gpg` and
+/// `openssl` need the Cargo feature of the same name.
+fn parse_container<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<Container>, E> {
+    opt(preceded(tuple((multispace1, tag("--container"), multispace1)), |input: &'a str| {
+        #[cfg(feature = "age")]
+        if let Ok((next, _)) = tag::<_, _, E>("age")(input) {
+            return Ok((next, Container::Age));
+        }
+        #[cfg(feature = "gpg")]
+        if let Ok((next, _)) = tag::<_, _, E>("gpg")(input) {
+            return Ok((next, Container::Gpg));
+        }
+        #[cfg(feature = "openssl")]
+        if let Ok((next, _)) = tag::<_, _, E>("openssl")(input) {
+            return Ok((next, Container::Openssl));
+        }
+        value(Container::Crypt, tag("crypt"))(input)
+    }))(input)
+}
+
+/// Parse `unlock <alias> <filepath>` and its options, see [`ReplCryptCommand::Unlock`].
+fn parse_unlock<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ReplCryptCommand<'a>, E> {
+    map(
+        preceded(tag("unlock"), preceded(multispace1, tuple((
+            parse_str,
+            preceded(multispace1, parse_str),
+            map(opt(preceded(multispace1, tag("--dual"))), |flag| flag.is_some()),
+            parse_cipher,
+            parse_compression,
+            parse_layout,
+            parse_container,
+            parse_keyfile,
+        )))),
+        |(alias, filepath, dual, cipher, compression, layout, container, keyfile)| ReplCryptCommand::Unlock { alias, filepath, dual, cipher, compression, layout, container, keyfile },
+    )(input)
+}
+
+/// Parse an optional trailing `--keyfile <path>`.
+fn parse_keyfile<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<Cow<'a, str>>, E> {
+    opt(preceded(tuple((multispace1, tag("--keyfile"), multispace1)), parse_str))(input)
+}
+
+/// Where `get` shows a value, overriding the configured default.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GetOutput {
+    /// ```--print```
+    Print,
+    /// ```--copy```
+    Copy,
+}
+
+/// Parse an optional trailing `--print` or `--copy` flag.
+fn parse_get_output<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<GetOutput>, E> {
+    opt(preceded(multispace1, alt((
+        value(GetOutput::Print, tag("--print")),
+        value(GetOutput::Copy, tag("--copy")),
+    ))))(input)
+}
+
+/// The format `export` writes entries in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// ```json```, the default, the values as pretty-printed unencrypted JSON.
+    Json,
+    /// ```bundle```, a standalone crypt file encrypted with a new password, which keeps the
+    /// notes, tags and fields of each entry for `import <alias> bundle`.
+    Bundle,
+}
+
+/// The format `import` reads entries in.
 #[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ImportFormat {
+    /// ```json```, an object of keys and string values like `export` writes.
+    Json,
+    /// ```csv```, with the options that follow the file path:
+    /// ```[--key-column <column>] [--value-column <column>] [--no-header]```. Columns are
+    /// numbered from 1 or named as in the header row.
+    Csv(CsvOptions),
+    /// ```dotenv```, `KEY=VALUE` lines, see [`read_dotenv`](crate::import::read_dotenv).
+    Dotenv,
+    /// ```bitwarden```, an unencrypted Bitwarden JSON export, see
+    /// [`read_bitwarden`](crate::import::read_bitwarden).
+    Bitwarden,
+    /// ```bundle```, a file `export <alias> bundle` wrote, asking for its password.
+    Bundle,
+    /// ```browser```, the saved passwords a web browser exports as CSV, see
+    /// [`read_browser_csv`](crate::import::read_browser_csv).
+    Browser,
+    /// ```pass```, a directory of gpg-encrypted entries, see
+    /// [`read_pass_store`](crate::import::read_pass_store).
+    #[cfg(feature = "gpg")]
+    Pass,
+    /// ```1pux```, a 1Password export, see [`read_1pux`](crate::import::read_1pux).
+    #[cfg(feature = "onepassword")]
+    OnePux,
+}
+
+/// Parse the import formats that need a feature, `pass <dir>` with `gpg` and `1pux <filepath>`
+/// with `onepassword`.
+fn parse_feature_source<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, (ImportFormat, Cow<'a, str>), E> {
+    #[cfg(feature = "gpg")]
+    if let Ok((next, dir)) = preceded(tag::<_, _, E>("pass"), preceded(multispace1, parse_str))(input) {
+        return Ok((next, (ImportFormat::Pass, dir)));
+    }
+    #[cfg(feature = "onepassword")]
+    if let Ok((next, filepath)) = preceded(tag::<_, _, E>("1pux"), preceded(multispace1, parse_str))(input) {
+        return Ok((next, (ImportFormat::OnePux, filepath)));
+    }
+    Err(nom::Err::Error(E::from_error_kind(input, nom::error::ErrorKind::Tag)))
+}
+
+/// Parse an import format followed by the file to import and the options of the format.
+fn parse_import_source<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, (ImportFormat, Cow<'a, str>), E> {
+    let column = || map_opt(parse_str, |column: Cow<str>| match column.parse::<usize>() {
+        Ok(number) => number.checked_sub(1).map(CsvColumn::Index),
+        Err(_) => Some(CsvColumn::Name(column.into_owned())),
+    });
+    context(
+        "import format",
+        alt((
+            map(preceded(tag("json"), preceded(multispace1, parse_str)), |filepath| (ImportFormat::Json, filepath)),
+            map(preceded(tag("dotenv"), preceded(multispace1, parse_str)), |filepath| (ImportFormat::Dotenv, filepath)),
+            map(preceded(tag("bitwarden"), preceded(multispace1, parse_str)), |filepath| (ImportFormat::Bitwarden, filepath)),
+            map(preceded(tag("browser"), preceded(multispace1, parse_str)), |filepath| (ImportFormat::Browser, filepath)),
+            map(preceded(tag("bundle"), preceded(multispace1, parse_str)), |filepath| (ImportFormat::Bundle, filepath)),
+            parse_feature_source,
+            map(
+                preceded(tag("csv"), preceded(multispace1, tuple((
+                    parse_str,
+                    opt(preceded(tuple((multispace1, tag("--key-column"), multispace1)), column())),
+                    opt(preceded(tuple((multispace1, tag("--value-column"), multispace1)), column())),
+                    opt(preceded(multispace1, tag("--no-header"))),
+                )))),
+                |(filepath, key, value, no_header)| {
+                    let defaults = CsvOptions::default();
+                    let options = CsvOptions { key: key.unwrap_or(defaults.key), value: value.unwrap_or(defaults.value), header: no_header.is_none() };
+                    (ImportFormat::Csv(options), filepath)
+                },
+            ),
+        )),
+    )(input)
+}
+
+/// Parse `import <alias> <format> <filepath>` and its options, see [`ReplCryptCommand::Import`].
+fn parse_import<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ReplCryptCommand<'a>, E> {
+    map(
+        preceded(tag("import"), preceded(multispace1, tuple((
+            parse_str,
+            preceded(multispace1, parse_import_source),
+            opt(preceded(multispace1, alt((
+                value(ConflictPolicy::TakeIncoming, tag("--overwrite")),
+                value(ConflictPolicy::KeepExisting, tag("--skip-existing")),
+            )))),
+        )))),
+        |(alias, (format, filepath), on_conflict)| ReplCryptCommand::Import { alias, format, filepath, on_conflict },
+    )(input)
+}
+
+/// Parse `extract-all <alias> <dir>` and its options, see [`ReplCryptCommand::ExtractAll`].
+fn parse_extract_all<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ReplCryptCommand<'a>, E> {
+    let mode = context(
+        "octal file mode",
+        map_opt(take_while1(|c: char| c.is_digit(8)), |digits: &str| u32::from_str_radix(digits, 8).ok().filter(|mode| *mode <= 0o777)),
+    );
+    map(
+        preceded(tag("extract-all"), preceded(multispace1, tuple((
+            parse_str,
+            preceded(multispace1, parse_str),
+            opt(preceded(tuple((multispace1, tag("--prefix"), multispace1)), parse_str)),
+            opt(preceded(tuple((multispace1, tag("--mode"), multispace1)), mode)),
+            opt(preceded(multispace1, alt((
+                value(Existing::Overwrite, tag("--overwrite")),
+                value(Existing::Skip, tag("--skip-existing")),
+            )))),
+        )))),
+        |(alias, dir, prefix, mode, existing)| ReplCryptCommand::ExtractAll { alias, dir, prefix, mode, existing: existing.unwrap_or(Existing::Refuse) },
+    )(input)
+}
+
+/// Parse an expiry date, `YYYY-MM-DD` or `never`.
+fn parse_expiry<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Option<SystemTime>, E> {
+    context(
+        "expiry date",
+        alt((
+            value(None, tag("never")),
+            |input: &'a str| {
+                let (next, date) = take_while1(|c: char| c.is_ascii_digit() || c == '-')(input)?;
+                match parse_utc_date(date) {
+                    Some(date) => Ok((next, Some(date))),
+                    None => Err(nom::Err::Error(E::from_error_kind(input, nom::error::ErrorKind::Verify)))
+                }
+            },
+        )),
+    )(input)
+}
+
+#[derive(Clone, Eq, PartialEq)]
 pub enum ReplMapCommand<'a> {
-    /// ```list```
-    List,
-    /// ```get <key>```
+    /// ```list [--sort <last-accessed|reads>]```
+    List {
+        sort: Option<ListSort>,
+    },
+    /// ```keys [--prefix <prefix>] [--null]```
+    Keys {
+        prefix: Option<Cow<'a, str>>,
+        /// End each key with a NUL byte instead of a newline.
+        null: bool,
+    },
+    /// ```get <key> [--print|--copy]```
     Get {
         key: Cow<'a, str>,
+        output: Option<GetOutput>,
     },
-    /// ```set <key> <value>```
+    /// ```set <key> <value> [--note <note>] [--force] [--name]```
     Set {
         key: Cow<'a, str>,
         value: Cow<'a, str>,
+        note: Option<Cow<'a, str>>,
+        /// Ignore the entry and crypt size limits.
+        force: bool,
+        /// Ask for a name to add to `key`, which is then a prefix such as `aws/`. An empty key
+        /// asks for one either way.
+        name: bool,
     },
     /// ```delete <key>```
     Delete {
         key: Cow<'a, str>,
     },
+    /// ```info <key>```
+    Info {
+        key: Cow<'a, str>,
+    },
+    /// ```search <term>```
+    Search {
+        term: Cow<'a, str>,
+    },
+    /// ```show-big <key> [--for <duration>]```, showing the value in large type for the duration,
+    /// or [`SHOW_BIG_DURATION`](crate::repl::SHOW_BIG_DURATION) if it isn't given.
+    ShowBig {
+        key: Cow<'a, str>,
+        duration: Option<Duration>,
+    },
+    /// ```generate <key> <uuid|hex <bytes>|base64 <bytes>|ssh-ed25519> [--replace] [--name]```,
+    /// storing a new value, and the public key of a keypair under `<key>.pub`. Existing keys are
+    /// only replaced with `--replace`, and `--name` asks for a name as [`Set`](Self::Set) does.
+    Generate {
+        key: Cow<'a, str>,
+        generator: Generator,
+        replace: bool,
+        name: bool,
+    },
+    /// ```move <pattern> <to-alias>```, moving the entries whose key matches the regex to another
+    /// open crypt without unsaved changes. The originals are only deleted once the other crypt is
+    /// saved.
+    Move {
+        pattern: Cow<'a, str>,
+        to: Cow<'a, str>,
+    },
+    /// ```pick [<query>]```, asking for the query if it isn't given.
+    Pick {
+        query: Option<Cow<'a, str>>,
+    },
+    /// ```clear [<prefix>]```
+    Clear {
+        prefix: Option<Cow<'a, str>>,
+    },
+    /// ```tag <key> <tag>```
+    Tag {
+        key: Cow<'a, str>,
+        tag: Cow<'a, str>,
+    },
+    /// ```untag <key> <tag>```
+    Untag {
+        key: Cow<'a, str>,
+        tag: Cow<'a, str>,
+    },
+    /// ```expire <key> <YYYY-MM-DD|never>```, [`None`] meaning never.
+    Expire {
+        key: Cow<'a, str>,
+        expires: Option<SystemTime>,
+    },
+    /// ```rename <key> <new-key>```
+    Rename {
+        key: Cow<'a, str>,
+        new_key: Cow<'a, str>,
+    },
+    /// ```edit-with <key> -- <tool> [<args>...]```
+    EditWith {
+        key: Cow<'a, str>,
+        tool: Vec<Cow<'a, str>>,
+    },
+    /// ```rename-prefix <old-prefix> <new-prefix> [--dry-run]```
+    RenamePrefix {
+        old_prefix: Cow<'a, str>,
+        new_prefix: Cow<'a, str>,
+        dry_run: bool,
+    },
+    /// ```rename --pattern <regex> <replacement> [--dry-run]```
+    RenamePattern {
+        pattern: Cow<'a, str>,
+        replacement: Cow<'a, str>,
+        dry_run: bool,
+    },
+    /// ```import-env --prefix <prefix>```
+    ImportEnv {
+        prefix: Cow<'a, str>,
+    },
+    /// ```exec <key>... -- <command> [<args>...]```, see [`exec_with_files`](crate::repl::exec_with_files).
+    Exec {
+        keys: Vec<Cow<'a, str>>,
+        command: Vec<Cow<'a, str>>,
+    },
+    /// ```trash <trash command>```
+    Trash {
+        cmd: ReplTrashCommand<'a>,
+    },
+    /// ```field <field command>```
+    Field {
+        cmd: ReplFieldCommand<'a>,
+    },
+}
+
+/// What to do with the named fields of a record, see [`Field`].
+#[derive(Clone, Eq, PartialEq)]
+pub enum ReplFieldCommand<'a> {
+    /// ```list <key>```, listing the names of the fields the record has.
+    List {
+        key: Cow<'a, str>,
+    },
+    /// ```get <key> <field> [--print|--copy]```
+    Get {
+        key: Cow<'a, str>,
+        field: Field,
+        output: Option<GetOutput>,
+    },
+    /// ```set <key> <field> <value>```, an empty value removing the field.
+    Set {
+        key: Cow<'a, str>,
+        field: Field,
+        value: Cow<'a, str>,
+    },
+}
+
+impl fmt::Debug for ReplFieldCommand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::List { key } => f.debug_struct("List").field("key", key).finish(),
+            Self::Get { key, field, output } => f.debug_struct("Get").field("key", key).field("field", field).field("output", output).finish(),
+            Self::Set { key, field, .. } => f.debug_struct("Set").field("key", key).field("field", field).field("value", &"<redacted>").finish()
+        }
+    }
+}
+
+/// What to do with the entries `delete` moved to the trash.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplTrashCommand<'a> {
+    /// ```list```
+    List,
+    /// ```restore <key>```
+    Restore {
+        key: Cow<'a, str>,
+    },
+    /// ```purge [<key>]```, purging everything if no key is given.
+    Purge {
+        key: Option<Cow<'a, str>>,
+    },
+}
+
+impl fmt::Debug for ReplMapCommand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::List { sort } => f.debug_struct("List").field("sort", sort).finish(),
+            Self::Keys { prefix, null } => f.debug_struct("Keys").field("prefix", prefix).field("null", null).finish(),
+            Self::Get { key, output } => f.debug_struct("Get").field("key", key).field("output", output).finish(),
+            Self::Set { key, note, force, name, .. } => f.debug_struct("Set")
+                .field("key", key)
+                .field("value", &"<redacted>")
+                .field("note", note)
+                .field("force", force)
+                .field("name", name)
+                .finish(),
+            Self::Delete { key } => f.debug_struct("Delete").field("key", key).finish(),
+            Self::Info { key } => f.debug_struct("Info").field("key", key).finish(),
+            Self::Search { term } => f.debug_struct("Search").field("term", term).finish(),
+            Self::ShowBig { key, duration } => f.debug_struct("ShowBig").field("key", key).field("duration", duration).finish(),
+            Self::Generate { key, generator, replace, name } => f.debug_struct("Generate")
+                .field("key", key)
+                .field("generator", generator)
+                .field("replace", replace)
+                .field("name", name)
+                .finish(),
+            Self::Move { pattern, to } => f.debug_struct("Move").field("pattern", pattern).field("to", to).finish(),
+            Self::Pick { query } => f.debug_struct("Pick").field("query", query).finish(),
+            Self::Clear { prefix } => f.debug_struct("Clear").field("prefix", prefix).finish(),
+            Self::Tag { key, tag } => f.debug_struct("Tag").field("key", key).field("tag", tag).finish(),
+            Self::Untag { key, tag } => f.debug_struct("Untag").field("key", key).field("tag", tag).finish(),
+            Self::Expire { key, expires } => f.debug_struct("Expire").field("key", key).field("expires", expires).finish(),
+            Self::EditWith { key, tool } => f.debug_struct("EditWith").field("key", key).field("tool", tool).finish(),
+            Self::Rename { key, new_key } => f.debug_struct("Rename").field("key", key).field("new_key", new_key).finish(),
+            Self::RenamePrefix { old_prefix, new_prefix, dry_run } => f.debug_struct("RenamePrefix")
+                .field("old_prefix", old_prefix)
+                .field("new_prefix", new_prefix)
+                .field("dry_run", dry_run)
+                .finish(),
+            Self::RenamePattern { pattern, replacement, dry_run } => f.debug_struct("RenamePattern")
+                .field("pattern", pattern)
+                .field("replacement", replacement)
+                .field("dry_run", dry_run)
+                .finish(),
+            Self::ImportEnv { prefix } => f.debug_struct("ImportEnv").field("prefix", prefix).finish(),
+            Self::Exec { keys, command } => f.debug_struct("Exec").field("keys", keys).field("command", command).finish(),
+            Self::Trash { cmd } => f.debug_struct("Trash").field("cmd", cmd).finish(),
+            Self::Field { cmd } => f.debug_struct("Field").field("cmd", cmd).finish()
+        }
+    }
 }
 
 /// Parse a map command.
@@ -148,27 +603,164 @@ pub enum ReplMapCommand<'a> {
 ///
 /// ```
 /// use std::borrow::Cow;
+/// use std::time::Duration;
 /// use nom::error::VerboseError;
-/// use crypt_client::repl::{ReplMapCommand, parse_map_command};
+/// use crypt_client::file::Field;
+/// use crypt_client::generate::Generator;
+/// use crypt_client::repl::{GetOutput, ListSort, ReplFieldCommand, ReplMapCommand, ReplTrashCommand, parse_map_command};
 ///
 /// let data = "list ...";
 /// let result = parse_map_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok((" ...", ReplMapCommand::List)));
+/// assert_eq!(result, Ok((" ...", ReplMapCommand::List { sort: None })));
+///
+/// let data = "list --sort last-accessed";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::List { sort: Some(ListSort::LastAccessed) })));
 ///
 /// let data = "get <key>";
 /// let result = parse_map_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("<key>") })));
+/// assert_eq!(result, Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("<key>"), output: None })));
+///
+/// let data = "get <key> --print";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("<key>"), output: Some(GetOutput::Print) })));
 ///
 /// let data = "set <key> <value>";
 /// let result = parse_map_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplMapCommand::Set {
 ///     key: Cow::Borrowed("<key>"),
-///     value: Cow::Borrowed("<value>")
+///     value: Cow::Borrowed("<value>"),
+///     note: None,
+///     force: false,
+///     name: false
+/// })));
+///
+/// let data = "set <key> <value> --note 'rotated quarterly'";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Set {
+///     key: Cow::Borrowed("<key>"),
+///     value: Cow::Borrowed("<value>"),
+///     note: Some(Cow::Borrowed("rotated quarterly")),
+///     force: false,
+///     name: false
+/// })));
+///
+/// let data = "set <key> <value> --force";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Set {
+///     key: Cow::Borrowed("<key>"),
+///     value: Cow::Borrowed("<value>"),
+///     note: None,
+///     force: true,
+///     name: false
 /// })));
 ///
 /// let data = "delete <key>";
 /// let result = parse_map_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplMapCommand::Delete { key: Cow::Borrowed("<key>") })));
+///
+/// let data = "info <key>";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Info { key: Cow::Borrowed("<key>") })));
+///
+/// let data = "search <term>";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Search { term: Cow::Borrowed("<term>") })));
+///
+/// let data = "show-big <key> --for 10s";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::ShowBig { key: Cow::Borrowed("<key>"), duration: Some(Duration::from_secs(10)) })));
+///
+/// let data = "generate api/token hex 32 --replace";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Generate { key: Cow::Borrowed("api/token"), generator: Generator::Hex(32), replace: true, name: false })));
+///
+/// let data = "generate api/ uuid --name";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Generate { key: Cow::Borrowed("api/"), generator: Generator::Uuid, replace: false, name: true })));
+/// assert!(parse_map_command::<VerboseError<&str>>("generate api/token base64 1025").is_err());
+///
+/// let data = "move ^prod/ archive";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Move { pattern: Cow::Borrowed("^prod/"), to: Cow::Borrowed("archive") })));
+///
+/// let data = "keys --prefix aws/ --null";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Keys { prefix: Some(Cow::Borrowed("aws/")), null: true })));
+///
+/// let data = "pick db";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Pick { query: Some(Cow::Borrowed("db")) })));
+///
+/// let data = "clear aws/";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Clear { prefix: Some(Cow::Borrowed("aws/")) })));
+///
+/// let data = "tag <key> shared";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Tag { key: Cow::Borrowed("<key>"), tag: Cow::Borrowed("shared") })));
+///
+/// let data = "expire <key> 2030-01-31";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Expire {
+///     key: Cow::Borrowed("<key>"),
+///     expires: crypt_client::timestamp::parse_utc_date("2030-01-31")
+/// })));
+/// assert!(parse_map_command::<VerboseError<&str>>("expire <key> 2030-02-30").is_err());
+///
+/// let data = "edit-with kubeconfig -- code --wait";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::EditWith {
+///     key: Cow::Borrowed("kubeconfig"),
+///     tool: vec![Cow::Borrowed("code"), Cow::Borrowed("--wait")]
+/// })));
+///
+/// let data = "rename <key> <new-key>";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Rename { key: Cow::Borrowed("<key>"), new_key: Cow::Borrowed("<new-key>") })));
+///
+/// let data = "rename-prefix prod/ live/ --dry-run";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::RenamePrefix {
+///     old_prefix: Cow::Borrowed("prod/"),
+///     new_prefix: Cow::Borrowed("live/"),
+///     dry_run: true
+/// })));
+///
+/// let data = "rename --pattern ^old-(.*)$ new-$1";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::RenamePattern {
+///     pattern: Cow::Borrowed("^old-(.*)$"),
+///     replacement: Cow::Borrowed("new-$1"),
+///     dry_run: false
+/// })));
+///
+/// let data = "import-env --prefix MYAPP_";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::ImportEnv { prefix: Cow::Borrowed("MYAPP_") })));
+///
+/// let data = "trash restore <key>";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Trash { cmd: ReplTrashCommand::Restore { key: Cow::Borrowed("<key>") } })));
+///
+/// let data = "trash purge";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Trash { cmd: ReplTrashCommand::Purge { key: None } })));
+///
+/// let data = "field set github url https://github.com";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Field { cmd: ReplFieldCommand::Set {
+///     key: Cow::Borrowed("github"),
+///     field: Field::Url,
+///     value: Cow::Borrowed("https://github.com")
+/// } })));
+///
+/// let data = "exec db_password api_key -- docker compose up";
+/// let result = parse_map_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMapCommand::Exec {
+///     keys: vec![Cow::Borrowed("db_password"), Cow::Borrowed("api_key")],
+///     command: vec![Cow::Borrowed("docker"), Cow::Borrowed("compose"), Cow::Borrowed("up")]
+/// })));
 /// ```
 ///
 pub fn parse_map_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplMapCommand<'a>, E>
@@ -177,10 +769,146 @@ pub fn parse_map_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplMapComma
     context(
         "map command",
         alt((
-            value(ReplMapCommand::List, tag("list")),
-            map(preceded(terminated(tag("get"), multispace1), parse_str), |s| ReplMapCommand::Get { key: s }),
-            map(preceded(terminated(tag("set"), multispace1), separated_pair(parse_str, multispace1, parse_str)), |s| ReplMapCommand::Set { key: s.0, value: s.1 }),
+            map(preceded(tag("list"), parse_list_sort), |sort| ReplMapCommand::List { sort }),
+            map(
+                preceded(tag("keys"), tuple((
+                    opt(preceded(tuple((multispace1, tag("--prefix"), multispace1)), parse_str)),
+                    map(opt(preceded(multispace1, tag("--null"))), |flag| flag.is_some()),
+                ))),
+                |(prefix, null)| ReplMapCommand::Keys { prefix, null },
+            ),
+            map(
+                preceded(terminated(tag("get"), multispace1), tuple((parse_str, parse_get_output))),
+                |(key, output)| ReplMapCommand::Get { key, output },
+            ),
+            map(
+                preceded(terminated(tag("set"), multispace1), tuple((
+                    parse_str,
+                    preceded(multispace1, parse_str),
+                    opt(preceded(tuple((multispace1, tag("--note"), multispace1)), parse_str)),
+                    map(opt(preceded(multispace1, tag("--force"))), |flag| flag.is_some()),
+                    map(opt(preceded(multispace1, tag("--name"))), |flag| flag.is_some()),
+                ))),
+                |(key, value, note, force, name)| ReplMapCommand::Set { key, value, note, force, name },
+            ),
             map(preceded(terminated(tag("delete"), multispace1), parse_str), |s| ReplMapCommand::Delete { key: s }),
+            map(preceded(terminated(tag("info"), multispace1), parse_str), |s| ReplMapCommand::Info { key: s }),
+            map(preceded(terminated(tag("search"), multispace1), parse_str), |s| ReplMapCommand::Search { term: s }),
+            map(preceded(tag("clear"), opt(preceded(multispace1, parse_str))), |prefix| ReplMapCommand::Clear { prefix }),
+            map(
+                preceded(terminated(tag("show-big"), multispace1), tuple((parse_str, opt(preceded(tuple((multispace1, tag("--for"), multispace1)), parse_duration))))),
+                |(key, duration)| ReplMapCommand::ShowBig { key, duration },
+            ),
+            map(preceded(tag("pick"), opt(preceded(multispace1, parse_str))), |query| ReplMapCommand::Pick { query }),
+            map(
+                preceded(terminated(tag("tag"), multispace1), separated_pair(parse_str, multispace1, parse_str)),
+                |(key, tag)| ReplMapCommand::Tag { key, tag },
+            ),
+            map(
+                preceded(terminated(tag("untag"), multispace1), separated_pair(parse_str, multispace1, parse_str)),
+                |(key, tag)| ReplMapCommand::Untag { key, tag },
+            ),
+            map(
+                preceded(terminated(tag("expire"), multispace1), separated_pair(parse_str, multispace1, parse_expiry)),
+                |(key, expires)| ReplMapCommand::Expire { key, expires },
+            ),
+            map(
+                preceded(
+                    terminated(tag("edit-with"), multispace1),
+                    separated_pair(parse_str, tuple((multispace1, tag("--"), multispace1)), separated_list1(multispace1, parse_str)),
+                ),
+                |(key, tool)| ReplMapCommand::EditWith { key, tool },
+            ),
+            map(
+                preceded(terminated(tag("rename-prefix"), multispace1), tuple((parse_str, preceded(multispace1, parse_str), parse_dry_run))),
+                |(old_prefix, new_prefix, dry_run)| ReplMapCommand::RenamePrefix { old_prefix, new_prefix, dry_run },
+            ),
+            map(
+                preceded(tuple((tag("rename"), multispace1, tag("--pattern"), multispace1)), tuple((parse_str, preceded(multispace1, parse_str), parse_dry_run))),
+                |(pattern, replacement, dry_run)| ReplMapCommand::RenamePattern { pattern, replacement, dry_run },
+            ),
+            map(
+                preceded(terminated(tag("rename"), multispace1), separated_pair(parse_str, multispace1, parse_str)),
+                |(key, new_key)| ReplMapCommand::Rename { key, new_key },
+            ),
+            map(
+                preceded(tuple((tag("import-env"), multispace1, tag("--prefix"), multispace1)), parse_str),
+                |prefix| ReplMapCommand::ImportEnv { prefix },
+            ),
+            map(
+                preceded(
+                    terminated(tag("exec"), multispace1),
+                    separated_pair(
+                        separated_list1(multispace1, verify(parse_str, |key: &str| key != "--")),
+                        tuple((multispace1, tag("--"), multispace1)),
+                        separated_list1(multispace1, parse_str),
+                    ),
+                ),
+                |(keys, command)| ReplMapCommand::Exec { keys, command },
+            ),
+            alt((
+                map(preceded(terminated(tag("trash"), multispace1), parse_trash_command), |cmd| ReplMapCommand::Trash { cmd }),
+                map(preceded(terminated(tag("field"), multispace1), parse_field_command), |cmd| ReplMapCommand::Field { cmd }),
+                map(
+                    preceded(terminated(tag("generate"), multispace1), tuple((
+                        parse_str,
+                        preceded(multispace1, parse_generator),
+                        map(opt(preceded(multispace1, tag("--replace"))), |flag| flag.is_some()),
+                        map(opt(preceded(multispace1, tag("--name"))), |flag| flag.is_some()),
+                    ))),
+                    |(key, generator, replace, name)| ReplMapCommand::Generate { key, generator, replace, name },
+                ),
+                map(preceded(terminated(tag("move"), multispace1), separated_pair(parse_str, multispace1, parse_str)), |(pattern, to)| ReplMapCommand::Move { pattern, to }),
+            )),
+        )),
+    )(input)
+}
+
+/// Parse a generator, `uuid`, `hex <bytes>`, `base64 <bytes>` or, with the `ssh` feature,
+/// `ssh-ed25519`. Byte counts go from 1 to [`Generator::MAX_BYTES`].
+fn parse_generator<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Generator, E> {
+    let bytes = || preceded(multispace1, map_opt(digit1, |digits: &str| digits.parse::<usize>().ok().filter(|len| (1..=Generator::MAX_BYTES).contains(len))));
+    #[cfg(feature = "ssh")]
+    if let Ok((next, _)) = tag::<_, _, E>("ssh-ed25519")(input) {
+        return Ok((next, Generator::SshEd25519));
+    }
+    context(
+        "generator",
+        alt((
+            value(Generator::Uuid, tag("uuid")),
+            map(preceded(tag("hex"), bytes()), Generator::Hex),
+            map(preceded(tag("base64"), bytes()), Generator::Base64),
+        )),
+    )(input)
+}
+
+/// Parse the part of a `trash` command after `trash`.
+fn parse_trash_command<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ReplTrashCommand<'a>, E> {
+    context(
+        "trash command",
+        alt((
+            value(ReplTrashCommand::List, tag("list")),
+            map(preceded(terminated(tag("restore"), multispace1), parse_str), |key| ReplTrashCommand::Restore { key }),
+            map(preceded(tag("purge"), opt(preceded(multispace1, parse_str))), |key| ReplTrashCommand::Purge { key }),
+        )),
+    )(input)
+}
+
+/// Parse the part of a `field` command after `field`.
+fn parse_field_command<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ReplFieldCommand<'a>, E> {
+    let field = || preceded(multispace1, map(parse_str, |name: Cow<str>| Field::from(name.as_ref())));
+    context(
+        "field command",
+        alt((
+            map(preceded(terminated(tag("list"), multispace1), parse_str), |key| ReplFieldCommand::List { key }),
+            map(
+                preceded(terminated(tag("get"), multispace1), tuple((parse_str, field(), parse_get_output))),
+                |(key, field, output)| ReplFieldCommand::Get { key, field, output },
+            ),
+            map(
+                preceded(terminated(tag("set"), multispace1), tuple((parse_str, field(), preceded(multispace1, parse_str)))),
+                |(key, field, value)| ReplFieldCommand::Set { key, field, value },
+            ),
         )),
     )(input)
 }
@@ -198,24 +926,361 @@ impl<'a> TryFrom<&'a str> for ReplMapCommand<'a> {
     }
 }
 
+/// Parse a conflict policy (`keep`, `take` or `rename`).
+///
+/// # Example
+///
+/// ```
+/// use nom::error::VerboseError;
+/// use crypt_client::file::ConflictPolicy;
+/// use crypt_client::repl::parse_conflict_policy;
+///
+/// let data = "rename ...";
+/// let result = parse_conflict_policy::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok((" ...", ConflictPolicy::RenameIncoming)));
+/// ```
+///
+pub fn parse_conflict_policy<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, ConflictPolicy, E> {
+    context(
+        "conflict policy",
+        alt((
+            value(ConflictPolicy::KeepExisting, tag("keep")),
+            value(ConflictPolicy::TakeIncoming, tag("take")),
+            value(ConflictPolicy::RenameIncoming, tag("rename")),
+        )),
+    )(input)
+}
+
+/// Parse a length of time as a number followed by `s`, `m`, `h` or `d`.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use nom::error::VerboseError;
+/// use crypt_client::repl::parse_duration;
+///
+/// let data = "90m ...";
+/// let result = parse_duration::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok((" ...", Duration::from_secs(5400))));
+///
+/// let data = "7d";
+/// let result = parse_duration::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", Duration::from_secs(604_800))));
+/// ```
+///
+pub fn parse_duration<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, Duration, E> {
+    context(
+        "duration",
+        |input: &'a str| {
+            let (next, (amount, unit)) = tuple((digit1, alt((char('s'), char('m'), char('h'), char('d')))))(input)?;
+            let seconds = match unit {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                _ => 86_400
+            };
+            match amount.parse::<u64>().ok().and_then(|amount| amount.checked_mul(seconds)) {
+                Some(seconds) => Ok((next, Duration::from_secs(seconds))),
+                None => Err(nom::Err::Error(E::from_error_kind(input, nom::error::ErrorKind::Digit)))
+            }
+        },
+    )(input)
+}
+
+/// Parse an autosave policy (`off`, `on-change` or a number of seconds like `30s`).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use nom::error::VerboseError;
+/// use crypt_client::repl::{AutosavePolicy, parse_autosave_policy};
+///
+/// let data = "on-change ...";
+/// let result = parse_autosave_policy::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok((" ...", AutosavePolicy::OnChange)));
+///
+/// let data = "300s";
+/// let result = parse_autosave_policy::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", AutosavePolicy::Every(Duration::from_secs(300)))));
+/// ```
+///
+pub fn parse_autosave_policy<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, AutosavePolicy, E> {
+    context(
+        "autosave policy",
+        alt((
+            value(AutosavePolicy::Off, tag("off")),
+            value(AutosavePolicy::OnChange, tag("on-change")),
+            |input: &'a str| {
+                let (next, seconds) = terminated(digit1, tag("s"))(input)?;
+                match seconds.parse::<u64>() {
+                    Ok(seconds) => Ok((next, AutosavePolicy::Every(Duration::from_secs(seconds)))),
+                    Err(_) => Err(nom::Err::Error(E::from_error_kind(input, nom::error::ErrorKind::Digit)))
+                }
+            },
+        )),
+    )(input)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplMetaCommand<'a> {
+    /// ```show```
+    Show,
+    /// ```describe <description>```
+    Describe {
+        description: Cow<'a, str>,
+    },
+    /// ```set <key> <value>```
+    Set {
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    /// ```unset <key>```
+    Unset {
+        key: Cow<'a, str>,
+    },
+    /// ```set-setting <name> <value>```, see [`FileSettings`](crate::repl::FileSettings).
+    SetSetting {
+        name: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    /// ```unset-setting <name>```
+    UnsetSetting {
+        name: Cow<'a, str>,
+    },
+}
+
+/// Parse a command editing the description, metadata and settings of a whole crypt.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+/// use nom::error::VerboseError;
+/// use crypt_client::repl::{ReplMetaCommand, parse_meta_command};
+///
+/// let data = "show";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::Show)));
+///
+/// let data = "describe 'Production credentials'";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::Describe { description: Cow::Borrowed("Production credentials") })));
+///
+/// let data = "set owner 'platform team'";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::Set { key: Cow::Borrowed("owner"), value: Cow::Borrowed("platform team") })));
+///
+/// let data = "unset owner";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::Unset { key: Cow::Borrowed("owner") })));
+///
+/// let data = "set-setting autosave on-change";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::SetSetting { name: Cow::Borrowed("autosave"), value: Cow::Borrowed("on-change") })));
+///
+/// let data = "unset-setting autosave";
+/// let result = parse_meta_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplMetaCommand::UnsetSetting { name: Cow::Borrowed("autosave") })));
+/// ```
+///
+pub fn parse_meta_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplMetaCommand<'a>, E>
+    where E: ParseError<&'a str> + ContextError<&'a str>
+{
+    context(
+        "meta command",
+        alt((
+            value(ReplMetaCommand::Show, tag("show")),
+            map(preceded(terminated(tag("describe"), multispace1), parse_str), |description| ReplMetaCommand::Describe { description }),
+            map(
+                preceded(terminated(tag("set"), multispace1), separated_pair(parse_str, multispace1, parse_str)),
+                |(key, value)| ReplMetaCommand::Set { key, value },
+            ),
+            map(preceded(terminated(tag("unset"), multispace1), parse_str), |key| ReplMetaCommand::Unset { key }),
+            map(
+                preceded(terminated(tag("set-setting"), multispace1), separated_pair(parse_str, multispace1, parse_str)),
+                |(name, value)| ReplMetaCommand::SetSetting { name, value },
+            ),
+            map(preceded(terminated(tag("unset-setting"), multispace1), parse_str), |name| ReplMetaCommand::UnsetSetting { name }),
+        )),
+    )(input)
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ReplCryptCommand<'a> {
-    /// ```list```
-    List,
-    /// ```unlock <alias> <filepath>```
+    /// ```list [--fingerprint]```, where `--fingerprint` prints one tab-separated line per file
+    /// for scripts instead of a table.
+    List {
+        fingerprint: bool,
+    },
+    /// ```save-all```
+    SaveAll,
+    /// ```unlock <alias> <filepath> [--dual] [--cipher <aes-256-gcm|chacha20-poly1305>] [--compression <none|zstd>] [--layout <single|chunked>] [--keyfile <path>]```
     Unlock {
         alias: Cow<'a, str>,
         filepath: Cow<'a, str>,
+        /// Ask two people for their passwords, see
+        /// [`dual_control_password`](crate::secret::dual_control_password).
+        dual: bool,
+        /// The cipher to encrypt the file with from now on, instead of the one it already uses.
+        cipher: Option<CipherKind>,
+        /// How to compress the file from now on, instead of how it is already compressed.
+        compression: Option<Compression>,
+        /// How to lay out the file from now on, instead of how it is already laid out.
+        layout: Option<Layout>,
+        /// The kind of file to create, which can't be changed for a file that already exists.
+        container: Option<Container>,
+        /// A file whose contents are combined with the password, see
+        /// [`CryptFile::unlock_with_keyfile`](crate::file::CryptFile::unlock_with_keyfile).
+        keyfile: Option<Cow<'a, str>>,
     },
     /// ```lock <alias>```
     Lock {
         alias: Cow<'a, str>,
     },
+    /// ```inspect <filepath>```
+    Inspect {
+        filepath: Cow<'a, str>,
+    },
+    /// ```verify <filepath>```
+    Verify {
+        filepath: Cow<'a, str>,
+    },
+    /// ```recover <alias> <filepath>```
+    Recover {
+        alias: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+    },
     /// ```data <alias> <map command>```
     Data {
         alias: Cow<'a, str>,
         cmd: ReplMapCommand<'a>,
     },
+    /// ```meta <alias> <meta command>```
+    Meta {
+        alias: Cow<'a, str>,
+        cmd: ReplMetaCommand<'a>,
+    },
+    /// ```merge <alias> <source-alias> [--on-conflict <keep|take|rename>]```
+    Merge {
+        alias: Cow<'a, str>,
+        source: Cow<'a, str>,
+        on_conflict: Option<ConflictPolicy>,
+    },
+    /// ```clone <alias> <filepath> [--prefix <prefix>]```
+    Clone {
+        alias: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+        prefix: Option<Cow<'a, str>>,
+    },
+    /// ```autosave <alias> <off|on-change|<seconds>s>```
+    Autosave {
+        alias: Cow<'a, str>,
+        policy: AutosavePolicy,
+    },
+    /// ```export <alias> [json|bundle] <filepath> [--prefix <prefix>] [--tag <tag>]```
+    Export {
+        alias: Cow<'a, str>,
+        format: ExportFormat,
+        filepath: Cow<'a, str>,
+        prefix: Option<Cow<'a, str>>,
+        tag: Option<Cow<'a, str>>,
+    },
+    /// ```export-armor <filepath> <armor-filepath>```
+    ExportArmor {
+        filepath: Cow<'a, str>,
+        armor_filepath: Cow<'a, str>,
+    },
+    /// ```manifest <alias> <filepath>```
+    Manifest {
+        alias: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+    },
+    /// ```check-manifest <alias> <filepath>```
+    CheckManifest {
+        alias: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+    },
+    /// ```import-armor <armor-filepath> <filepath>```
+    ImportArmor {
+        armor_filepath: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+    },
+    /// ```import <alias> <format> <filepath> [<format options>] [--overwrite|--skip-existing]```,
+    /// asking about each existing key with a different value if neither flag is given.
+    Import {
+        alias: Cow<'a, str>,
+        format: ImportFormat,
+        filepath: Cow<'a, str>,
+        on_conflict: Option<ConflictPolicy>,
+    },
+    /// ```passwd <alias>```
+    Passwd {
+        alias: Cow<'a, str>,
+    },
+    /// ```migrate <alias>```, rewrites the file with the configured cipher, key derivation
+    /// function, compression and layout.
+    Migrate {
+        alias: Cow<'a, str>,
+    },
+    /// ```sync <alias> [<filepath>]```, merges the file on disk, or another copy of it such as
+    /// one a sync client saved aside as conflicted, into the open file, see
+    /// [`CryptData::merge_replica`](crate::file::CryptData::merge_replica).
+    Sync {
+        alias: Cow<'a, str>,
+        filepath: Option<Cow<'a, str>>,
+    },
+    /// ```compact <alias> [--shred]```, empties the trash, rewrites the file and removes its
+    /// backups and leftover temporary files, overwriting them first with `--shred`.
+    Compact {
+        alias: Cow<'a, str>,
+        shred: bool,
+    },
+    /// ```recipients <alias> [<key-id>...]```, lists who a GPG file is encrypted to, or replaces
+    /// them, see [`Container::Gpg`](crate::file::Container).
+    Recipients {
+        alias: Cow<'a, str>,
+        /// The new recipients, or empty to list them.
+        recipients: Vec<Cow<'a, str>>,
+    },
+    /// ```backups <alias> [restore <backup>]```
+    Backups {
+        alias: Cow<'a, str>,
+        /// The file name of the backup to restore, or [`None`] to list the backups.
+        restore: Option<Cow<'a, str>>,
+    },
+    /// ```share <alias> <key> <filepath> [--expires <duration>]```, see [`seal`](crate::share::seal).
+    Share {
+        alias: Cow<'a, str>,
+        key: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+        /// How long the share can be received for, a day if [`None`].
+        expires: Option<Duration>,
+    },
+    /// ```receive <alias> <filepath>```
+    Receive {
+        alias: Cow<'a, str>,
+        filepath: Cow<'a, str>,
+    },
+    /// ```export-k8s <alias> --name <name> [keys...]```
+    ExportK8s {
+        alias: Cow<'a, str>,
+        /// The name of the Kubernetes secret.
+        name: Cow<'a, str>,
+        /// The keys to include, every key if empty.
+        keys: Vec<Cow<'a, str>>,
+    },
+    /// ```extract-all <alias> <dir> [--prefix <prefix>] [--mode <octal>] [--overwrite|--skip-existing]```,
+    /// writes each value to a file at its key's path below the directory.
+    ExtractAll {
+        alias: Cow<'a, str>,
+        dir: Cow<'a, str>,
+        prefix: Option<Cow<'a, str>>,
+        /// The permissions of the files, `600` if [`None`].
+        mode: Option<u32>,
+        existing: Existing,
+    },
 }
 
 /// Parse a crypt command.
@@ -225,28 +1290,228 @@ pub enum ReplCryptCommand<'a> {
 /// ```
 /// use std::borrow::Cow;
 /// use nom::error::VerboseError;
-/// use crypt_client::repl::{ReplCryptCommand, ReplMapCommand, parse_crypt_command};
+/// use std::time::Duration;
+/// use crypt_client::file::{CipherKind, Compression, ConflictPolicy, Container, Layout};
+/// use crypt_client::extract::Existing;
+/// use crypt_client::import::{CsvColumn, CsvOptions};
+/// use crypt_client::repl::{AutosavePolicy, ExportFormat, ImportFormat, ReplCryptCommand, ReplMapCommand, parse_crypt_command};
 ///
 /// let data = "list ...";
 /// let result = parse_crypt_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok((" ...", ReplCryptCommand::List)));
+/// assert_eq!(result, Ok((" ...", ReplCryptCommand::List { fingerprint: false })));
+///
+/// let data = "list --fingerprint";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::List { fingerprint: true })));
+///
+/// let data = "save-all";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::SaveAll)));
 ///
 /// let data = "unlock <alias> ./file.ext";
 /// let result = parse_crypt_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplCryptCommand::Unlock {
 ///     alias: Cow::Borrowed("<alias>"),
-///     filepath: Cow::Borrowed("./file.ext")
+///     filepath: Cow::Borrowed("./file.ext"),
+///     dual: false,
+///     cipher: None,
+///     compression: None,
+///     layout: None,
+///     container: None,
+///     keyfile: None
+/// })));
+///
+/// let data = "unlock <alias> ./break-glass.crypt --dual";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Unlock {
+///     alias: Cow::Borrowed("<alias>"),
+///     filepath: Cow::Borrowed("./break-glass.crypt"),
+///     dual: true,
+///     cipher: None,
+///     compression: None,
+///     layout: None,
+///     container: None,
+///     keyfile: None
+/// })));
+///
+/// let data = "unlock <alias> ./arm.crypt --cipher chacha20-poly1305";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Unlock {
+///     alias: Cow::Borrowed("<alias>"),
+///     filepath: Cow::Borrowed("./arm.crypt"),
+///     dual: false,
+///     cipher: Some(CipherKind::ChaCha20Poly1305),
+///     compression: None,
+///     layout: None,
+///     container: None,
+///     keyfile: None
+/// })));
+///
+/// let data = "unlock <alias> ./sync/vault.crypt --compression none --layout chunked --container crypt --keyfile /media/usb/crypt.key";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Unlock {
+///     alias: Cow::Borrowed("<alias>"),
+///     filepath: Cow::Borrowed("./sync/vault.crypt"),
+///     dual: false,
+///     cipher: None,
+///     compression: Some(Compression::None),
+///     layout: Some(Layout::Chunked),
+///     container: Some(Container::Crypt),
+///     keyfile: Some(Cow::Borrowed("/media/usb/crypt.key"))
 /// })));
 ///
 /// let data = "lock <alias>";
 /// let result = parse_crypt_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplCryptCommand::Lock { alias: Cow::Borrowed("<alias>") })));
 ///
+/// let data = "inspect ./file.ext";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Inspect { filepath: Cow::Borrowed("./file.ext") })));
+///
+/// let data = "verify ./file.ext";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Verify { filepath: Cow::Borrowed("./file.ext") })));
+///
+/// let data = "recover <alias> ./damaged.crypt";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Recover { alias: Cow::Borrowed("<alias>"), filepath: Cow::Borrowed("./damaged.crypt") })));
+///
 /// let data = "data <alias> set <key> <value>";
 /// let result = parse_crypt_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplCryptCommand::Data {
 ///     alias: Cow::Borrowed("<alias>"),
-///     cmd: ReplMapCommand::Set { key: Cow::Borrowed("<key>"), value: Cow::Borrowed("<value>") }
+///     cmd: ReplMapCommand::Set { key: Cow::Borrowed("<key>"), value: Cow::Borrowed("<value>"), note: None, force: false, name: false }
+/// })));
+///
+/// let data = "merge <alias> <source> --on-conflict take";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Merge {
+///     alias: Cow::Borrowed("<alias>"),
+///     source: Cow::Borrowed("<source>"),
+///     on_conflict: Some(ConflictPolicy::TakeIncoming)
+/// })));
+///
+/// let data = "clone <alias> ./copy.ext --prefix aws/";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Clone {
+///     alias: Cow::Borrowed("<alias>"),
+///     filepath: Cow::Borrowed("./copy.ext"),
+///     prefix: Some(Cow::Borrowed("aws/"))
+/// })));
+///
+/// let data = "autosave <alias> 60s";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Autosave {
+///     alias: Cow::Borrowed("<alias>"),
+///     policy: AutosavePolicy::Every(Duration::from_secs(60))
+/// })));
+///
+/// let data = "export <alias> json out.json --prefix aws/ --tag shared";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Export {
+///     alias: Cow::Borrowed("<alias>"),
+///     format: ExportFormat::Json,
+///     filepath: Cow::Borrowed("out.json"),
+///     prefix: Some(Cow::Borrowed("aws/")),
+///     tag: Some(Cow::Borrowed("shared"))
+/// })));
+///
+/// let data = "check-manifest <alias> ./file.manifest.json";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::CheckManifest {
+///     alias: Cow::Borrowed("<alias>"),
+///     filepath: Cow::Borrowed("./file.manifest.json")
+/// })));
+///
+/// let data = "passwd <alias>";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Passwd { alias: Cow::Borrowed("<alias>") })));
+///
+/// let data = "migrate <alias>";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Migrate { alias: Cow::Borrowed("<alias>") })));
+///
+/// let data = "sync <alias> ./file.conflicted.crypt";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Sync { alias: Cow::Borrowed("<alias>"), filepath: Some(Cow::Borrowed("./file.conflicted.crypt")) })));
+///
+/// let data = "compact <alias> --shred";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Compact { alias: Cow::Borrowed("<alias>"), shred: true })));
+///
+/// let data = "recipients <alias> alice@example.com 0123456789ABCDEF";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Recipients {
+///     alias: Cow::Borrowed("<alias>"),
+///     recipients: vec![Cow::Borrowed("alice@example.com"), Cow::Borrowed("0123456789ABCDEF")]
+/// })));
+///
+/// let data = "backups <alias> restore file.crypt.bak.20260101T120000Z";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Backups {
+///     alias: Cow::Borrowed("<alias>"),
+///     restore: Some(Cow::Borrowed("file.crypt.bak.20260101T120000Z"))
+/// })));
+///
+/// let data = "share <alias> db/password ./password.share --expires 1h";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Share {
+///     alias: Cow::Borrowed("<alias>"),
+///     key: Cow::Borrowed("db/password"),
+///     filepath: Cow::Borrowed("./password.share"),
+///     expires: Some(Duration::from_secs(3600))
+/// })));
+///
+/// let data = "import <alias> json exported.json --skip-existing";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Import {
+///     alias: Cow::Borrowed("<alias>"),
+///     format: ImportFormat::Json,
+///     filepath: Cow::Borrowed("exported.json"),
+///     on_conflict: Some(ConflictPolicy::KeepExisting)
+/// })));
+///
+/// let data = "import <alias> csv export.csv --key-column name --value-column 3 --overwrite";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Import {
+///     alias: Cow::Borrowed("<alias>"),
+///     format: ImportFormat::Csv(CsvOptions { key: CsvColumn::Name("name".to_string()), value: CsvColumn::Index(2), header: true }),
+///     filepath: Cow::Borrowed("export.csv"),
+///     on_conflict: Some(ConflictPolicy::TakeIncoming)
+/// })));
+///
+/// let data = "import <alias> dotenv .env";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::Import {
+///     alias: Cow::Borrowed("<alias>"),
+///     format: ImportFormat::Dotenv,
+///     filepath: Cow::Borrowed(".env"),
+///     on_conflict: None
+/// })));
+///
+/// let data = "export-k8s <alias> --name app-secrets DB_USER DB_PASSWORD";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::ExportK8s {
+///     alias: Cow::Borrowed("<alias>"),
+///     name: Cow::Borrowed("app-secrets"),
+///     keys: vec![Cow::Borrowed("DB_USER"), Cow::Borrowed("DB_PASSWORD")]
+/// })));
+///
+/// let data = "extract-all <alias> ./certs --prefix certs/ --mode 640 --skip-existing";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::ExtractAll {
+///     alias: Cow::Borrowed("<alias>"),
+///     dir: Cow::Borrowed("./certs"),
+///     prefix: Some(Cow::Borrowed("certs/")),
+///     mode: Some(0o640),
+///     existing: Existing::Skip
+/// })));
+///
+/// let data = "export-armor ./file.crypt ./file.txt";
+/// let result = parse_crypt_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCryptCommand::ExportArmor {
+///     filepath: Cow::Borrowed("./file.crypt"),
+///     armor_filepath: Cow::Borrowed("./file.txt")
 /// })));
 /// ```
 ///
@@ -256,10 +1521,101 @@ pub fn parse_crypt_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplCryptC
     context(
         "crypt command",
         alt((
-            value(ReplCryptCommand::List, tag("list")),
-            map(preceded(tag("unlock"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))), |s| ReplCryptCommand::Unlock { alias: s.0, filepath: s.1 }),
+            map(preceded(tag("list"), opt(preceded(multispace1, tag("--fingerprint")))), |flag| ReplCryptCommand::List { fingerprint: flag.is_some() }),
+            value(ReplCryptCommand::SaveAll, tag("save-all")),
+            parse_unlock,
             map(preceded(tag("lock"), preceded(multispace1, parse_str)), |s| ReplCryptCommand::Lock { alias: s }),
+            alt((
+                map(preceded(tag("inspect"), preceded(multispace1, parse_str)), |s| ReplCryptCommand::Inspect { filepath: s }),
+                map(preceded(tag("verify"), preceded(multispace1, parse_str)), |s| ReplCryptCommand::Verify { filepath: s }),
+                map(
+                    preceded(tag("recover"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))),
+                    |(alias, filepath)| ReplCryptCommand::Recover { alias, filepath },
+                ),
+            )),
             map(preceded(tag("data"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_map_command))), |s| ReplCryptCommand::Data { alias: s.0, cmd: s.1 }),
+            map(preceded(tag("meta"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_meta_command))), |s| ReplCryptCommand::Meta { alias: s.0, cmd: s.1 }),
+            map(
+                preceded(tag("merge"), preceded(multispace1, tuple((
+                    parse_str,
+                    preceded(multispace1, parse_str),
+                    opt(preceded(tuple((multispace1, tag("--on-conflict"), multispace1)), parse_conflict_policy)),
+                )))),
+                |(alias, source, on_conflict)| ReplCryptCommand::Merge { alias, source, on_conflict },
+            ),
+            map(
+                preceded(tag("clone"), preceded(multispace1, tuple((
+                    parse_str,
+                    preceded(multispace1, parse_str),
+                    opt(preceded(tuple((multispace1, tag("--prefix"), multispace1)), parse_str)),
+                )))),
+                |(alias, filepath, prefix)| ReplCryptCommand::Clone { alias, filepath, prefix },
+            ),
+            map(
+                preceded(tag("autosave"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_autosave_policy))),
+                |(alias, policy)| ReplCryptCommand::Autosave { alias, policy },
+            ),
+            map(
+                preceded(tag("export"), preceded(multispace1, tuple((
+                    parse_str,
+                    opt(preceded(multispace1, terminated(alt((value(ExportFormat::Json, tag("json")), value(ExportFormat::Bundle, tag("bundle")))), peek(multispace1)))),
+                    preceded(multispace1, parse_str),
+                    opt(preceded(tuple((multispace1, tag("--prefix"), multispace1)), parse_str)),
+                    opt(preceded(tuple((multispace1, tag("--tag"), multispace1)), parse_str)),
+                )))),
+                |(alias, format, filepath, prefix, tag)| ReplCryptCommand::Export { alias, format: format.unwrap_or(ExportFormat::Json), filepath, prefix, tag },
+            ),
+            map(
+                preceded(tag("export-armor"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))),
+                |(filepath, armor_filepath)| ReplCryptCommand::ExportArmor { filepath, armor_filepath },
+            ),
+            map(
+                preceded(tag("manifest"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))),
+                |(alias, filepath)| ReplCryptCommand::Manifest { alias, filepath },
+            ),
+            map(
+                preceded(tag("check-manifest"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))),
+                |(alias, filepath)| ReplCryptCommand::CheckManifest { alias, filepath },
+            ),
+            alt((
+                map(preceded(tag("import-armor"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))), |(armor_filepath, filepath)| ReplCryptCommand::ImportArmor { armor_filepath, filepath }),
+                parse_import,
+            )),
+            alt((
+                map(preceded(tag("passwd"), preceded(multispace1, parse_str)), |alias| ReplCryptCommand::Passwd { alias }),
+                map(preceded(tag("migrate"), preceded(multispace1, parse_str)), |alias| ReplCryptCommand::Migrate { alias }),
+                map(preceded(tag("sync"), preceded(multispace1, tuple((parse_str, opt(preceded(multispace1, parse_str)))))), |(alias, filepath)| ReplCryptCommand::Sync { alias, filepath }),
+                map(preceded(tag("compact"), preceded(multispace1, tuple((parse_str, opt(preceded(multispace1, tag("--shred"))))))), |(alias, shred)| ReplCryptCommand::Compact { alias, shred: shred.is_some() }),
+                map(preceded(tag("recipients"), preceded(multispace1, tuple((parse_str, many0(preceded(multispace1, parse_str)))))), |(alias, recipients)| ReplCryptCommand::Recipients { alias, recipients }),
+            )),
+            map(
+                preceded(tag("backups"), preceded(multispace1, tuple((
+                    parse_str,
+                    opt(preceded(tuple((multispace1, tag("restore"), multispace1)), parse_str)),
+                )))),
+                |(alias, restore)| ReplCryptCommand::Backups { alias, restore },
+            ),
+            map(
+                preceded(tag("share"), preceded(multispace1, tuple((
+                    parse_str,
+                    preceded(multispace1, parse_str),
+                    preceded(multispace1, parse_str),
+                    opt(preceded(tuple((multispace1, tag("--expires"), multispace1)), parse_duration)),
+                )))),
+                |(alias, key, filepath, expires)| ReplCryptCommand::Share { alias, key, filepath, expires },
+            ),
+            map(preceded(tag("receive"), preceded(multispace1, separated_pair(parse_str, multispace1, parse_str))), |(alias, filepath)| ReplCryptCommand::Receive { alias, filepath }),
+            alt((
+                map(
+                    preceded(tag("export-k8s"), preceded(multispace1, tuple((
+                        parse_str,
+                        preceded(tuple((multispace1, tag("--name"), multispace1)), parse_str),
+                        many0(preceded(multispace1, parse_str)),
+                    )))),
+                    |(alias, name, keys)| ReplCryptCommand::ExportK8s { alias, name, keys },
+                ),
+                parse_extract_all,
+            )),
         )),
     )(input)
 }
@@ -277,16 +1633,13 @@ impl<'a> TryFrom<&'a str> for ReplCryptCommand<'a> {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct ReplExitCommand {
     pub code: i32,
+    /// Discard changes to every open file.
     pub no_save: bool,
-}
-
-impl Default for ReplExitCommand {
-    fn default() -> Self {
-        Self { code: 0, no_save: false }
-    }
+    /// Save every open file without asking.
+    pub save: bool,
 }
 
 /// Parse an exit command.
@@ -299,19 +1652,23 @@ impl Default for ReplExitCommand {
 ///
 /// let data = "0";
 /// let result = parse_exit_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplExitCommand { code: 0, no_save: false })));
+/// assert_eq!(result, Ok(("", ReplExitCommand { code: 0, no_save: false, save: false })));
 ///
 /// let data = "0 --no-save";
 /// let result = parse_exit_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplExitCommand { code: 0, no_save: true })));
+/// assert_eq!(result, Ok(("", ReplExitCommand { code: 0, no_save: true, save: false })));
 ///
 /// let data = "50";
 /// let result = parse_exit_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplExitCommand { code: 50, no_save: false })));
+/// assert_eq!(result, Ok(("", ReplExitCommand { code: 50, no_save: false, save: false })));
 ///
 /// let data = "50 --no-save";
 /// let result = parse_exit_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplExitCommand { code: 50, no_save: true })));
+/// assert_eq!(result, Ok(("", ReplExitCommand { code: 50, no_save: true, save: false })));
+///
+/// let data = "0 --save";
+/// let result = parse_exit_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplExitCommand { code: 0, no_save: false, save: true })));
 /// ```
 ///
 pub fn parse_exit_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplExitCommand, E>
@@ -320,18 +1677,66 @@ pub fn parse_exit_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplExitCom
     context(
         "exit command",
         map(
-            tuple((parse_i32, opt(preceded(multispace1, value(true, tag("--no-save")))))),
-            |(code, force)| ReplExitCommand { code, no_save: force.unwrap_or(false) },
+            tuple((parse_i32, opt(preceded(multispace1, alt((tag("--no-save"), tag("--save"))))))),
+            |(code, flag)| ReplExitCommand { code, no_save: flag == Some("--no-save"), save: flag == Some("--save") },
         ),
     )(input)
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// Parse a session variable name, made of ASCII letters, digits and `_`.
+fn parse_var_name<'a, E: ParseError<&'a str> + ContextError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    context("variable name", take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'))(input)
+}
+
+#[derive(Clone, Eq, PartialEq)]
 pub enum ReplCommand<'a> {
     ClearScreen,
-    Help,
+    /// ```help [--all] [--markdown]```
+    Help {
+        /// Whether to print a markdown reference instead of the table. Both list every command,
+        /// `--all` is accepted to say so.
+        markdown: bool,
+    },
+    Timings,
     Exit(ReplExitCommand),
     Crypt(ReplCryptCommand<'a>),
+    /// ```let <name> <value>```
+    Let {
+        name: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    /// ```unset <name>```
+    Unset {
+        name: Cow<'a, str>,
+    },
+    /// ```vars```
+    Vars,
+    /// ```if-set <name> then <command>```
+    IfSet {
+        name: Cow<'a, str>,
+        command: Box<ReplCommand<'a>>,
+    },
+    /// ```source <filepath>```
+    Source {
+        filepath: Cow<'a, str>,
+    },
+}
+
+impl fmt::Debug for ReplCommand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ClearScreen => f.write_str("ClearScreen"),
+            Self::Help { markdown } => f.debug_struct("Help").field("markdown", markdown).finish(),
+            Self::Timings => f.write_str("Timings"),
+            Self::Exit(command) => f.debug_tuple("Exit").field(command).finish(),
+            Self::Crypt(command) => f.debug_tuple("Crypt").field(command).finish(),
+            Self::Let { name, .. } => f.debug_struct("Let").field("name", name).field("value", &"<redacted>").finish(),
+            Self::Unset { name } => f.debug_struct("Unset").field("name", name).finish(),
+            Self::Vars => f.write_str("Vars"),
+            Self::IfSet { name, command } => f.debug_struct("IfSet").field("name", name).field("command", command).finish(),
+            Self::Source { filepath } => f.debug_struct("Source").field("filepath", filepath).finish()
+        }
+    }
 }
 
 /// Parse a REPL command.
@@ -347,20 +1752,49 @@ pub enum ReplCommand<'a> {
 /// let result = parse_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplCommand::ClearScreen)));
 ///
+/// let data = "help";
+/// let result = parse_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCommand::Help { markdown: false })));
+///
+/// let data = "help --all --markdown";
+/// let result = parse_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCommand::Help { markdown: true })));
+///
+/// let data = "timings";
+/// let result = parse_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCommand::Timings)));
+///
 /// let data = "exit 50";
 /// let result = parse_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplCommand::Exit(ReplExitCommand { code: 50, no_save: false }))));
+/// assert_eq!(result, Ok(("", ReplCommand::Exit(ReplExitCommand { code: 50, no_save: false, save: false }))));
 ///
 /// let data = "exit 0 --no-save";
 /// let result = parse_command::<VerboseError<&str>>(data);
-/// assert_eq!(result, Ok(("", ReplCommand::Exit(ReplExitCommand { code: 0, no_save: true }))));
+/// assert_eq!(result, Ok(("", ReplCommand::Exit(ReplExitCommand { code: 0, no_save: true, save: false }))));
 ///
 /// let data = "crypt unlock <alias> C:\\Users\\<username>\\file.ext";
 /// let result = parse_command::<VerboseError<&str>>(data);
 /// assert_eq!(result, Ok(("", ReplCommand::Crypt(ReplCryptCommand::Unlock {
 ///     alias: Cow::Borrowed("<alias>"),
-///     filepath: Cow::Borrowed("C:\\Users\\<username>\\file.ext")
+///     filepath: Cow::Borrowed("C:\\Users\\<username>\\file.ext"),
+///     dual: false,
+///     cipher: None,
+///     compression: None,
+///     layout: None,
+///     container: None,
+///     keyfile: None
 /// }))));
+///
+/// let data = "let vault ./work.crypt";
+/// let result = parse_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCommand::Let { name: Cow::Borrowed("vault"), value: Cow::Borrowed("./work.crypt") })));
+///
+/// let data = "if-set vault then crypt lock work";
+/// let result = parse_command::<VerboseError<&str>>(data);
+/// assert_eq!(result, Ok(("", ReplCommand::IfSet {
+///     name: Cow::Borrowed("vault"),
+///     command: Box::new(ReplCommand::Crypt(ReplCryptCommand::Lock { alias: Cow::Borrowed("work") }))
+/// })));
 /// ```
 ///
 pub fn parse_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplCommand<'a>, E>
@@ -370,23 +1804,59 @@ pub fn parse_command<'a, E>(input: &'a str) -> IResult<&'a str, ReplCommand<'a>,
         "repl command",
         alt((
             value(ReplCommand::ClearScreen, tag("clear")),
-            value(ReplCommand::Help, tag("help")),
+            map(
+                preceded(tag("help"), tuple((opt(preceded(multispace1, tag("--all"))), opt(preceded(multispace1, tag("--markdown")))))),
+                |(_, markdown)| ReplCommand::Help { markdown: markdown.is_some() },
+            ),
+            value(ReplCommand::Timings, tag("timings")),
             map(preceded(tag("exit"), preceded(multispace1, parse_exit_command)), ReplCommand::Exit),
+            map(
+                preceded(terminated(tag("let"), multispace1), separated_pair(parse_var_name, multispace1, parse_str)),
+                |(name, value)| ReplCommand::Let { name: Cow::Borrowed(name), value },
+            ),
+            map(preceded(terminated(tag("unset"), multispace1), parse_var_name), |name| ReplCommand::Unset { name: Cow::Borrowed(name) }),
+            value(ReplCommand::Vars, tag("vars")),
+            map(
+                preceded(
+                    terminated(tag("if-set"), multispace1),
+                    separated_pair(parse_var_name, tuple((multispace1, tag("then"), multispace1)), parse_command),
+                ),
+                |(name, command)| ReplCommand::IfSet { name: Cow::Borrowed(name), command: Box::new(command) },
+            ),
+            map(preceded(terminated(tag("source"), multispace1), parse_str), |filepath| ReplCommand::Source { filepath }),
             map(preceded(tag("crypt"), preceded(multispace1, parse_crypt_command)), ReplCommand::Crypt)
         )),
     )(input)
 }
 
+/// Parse a whole command line, also returning whatever follows the command. Anything but
+/// whitespace left over is an unknown flag or an extra argument.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::{ReplCommand, ReplCryptCommand, parse_command_line};
+///
+/// let (command, rest) = parse_command_line("crypt lock work --force").unwrap();
+/// assert!(matches!(command, ReplCommand::Crypt(ReplCryptCommand::Lock { .. })));
+/// assert_eq!(rest, " --force");
+/// ```
+///
+pub fn parse_command_line(s: &str) -> Result<(ReplCommand<'_>, &str), VerboseError<&str>> {
+    let (rest, command) = parse_command(s)
+        .map_err(|e| match e {
+            Err::Error(e) | Err::Failure(e) => e,
+            Err::Incomplete(_) => VerboseError { errors: Vec::new() }
+        })?;
+    Ok((command, rest))
+}
+
 impl<'a> TryFrom<&'a str> for ReplCommand<'a> {
     type Error = VerboseError<&'a str>;
 
+    /// Parses a command line, ignoring anything after the command, see [`parse_command_line`].
     fn try_from(s: &'a str) -> Result<Self, Self::Error> {
-        let (_, command) = parse_command(s)
-            .map_err(|e| match e {
-                Err::Error(e) | Err::Failure(e) => e,
-                Err::Incomplete(_) => VerboseError { errors: Vec::new() }
-            })?;
-        Ok(command)
+        parse_command_line(s).map(|(command, _)| command)
     }
 }
 
@@ -421,8 +1891,51 @@ mod tests {
 
     #[test]
     fn test_parse_map_command() {
-        assert_eq!(parse_map_command::<VerboseError<&str>>("list"), Ok(("", ReplMapCommand::List)));
-        assert_eq!(parse_map_command::<VerboseError<&str>>("get abc"), Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("abc") })));
-        assert_eq!(parse_map_command::<VerboseError<&str>>("get 'abc d'"), Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("abc d") })));
+        assert_eq!(parse_map_command::<VerboseError<&str>>("list"), Ok(("", ReplMapCommand::List { sort: None })));
+        assert_eq!(parse_map_command::<VerboseError<&str>>("get abc"), Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("abc"), output: None })));
+        assert_eq!(parse_map_command::<VerboseError<&str>>("get 'abc d'"), Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("abc d"), output: None })));
+        assert_eq!(
+            parse_map_command::<VerboseError<&str>>("get abc --copy"),
+            Ok(("", ReplMapCommand::Get { key: Cow::Borrowed("abc"), output: Some(GetOutput::Copy) })),
+        );
+    }
+
+    #[test]
+    fn test_map_command_debug_redacts_value() {
+        let command = ReplMapCommand::Set { key: Cow::Borrowed("key"), value: Cow::Borrowed("hunter2"), note: None, force: false, name: false };
+        let debug = format!("{:?}", ReplCommand::Crypt(ReplCryptCommand::Data { alias: Cow::Borrowed("alias"), cmd: command }));
+        assert!(debug.contains("key"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_parse_extract_all_mode() {
+        let extract = |mode| ReplCryptCommand::ExtractAll { alias: Cow::Borrowed("a"), dir: Cow::Borrowed("out"), prefix: None, mode, existing: Existing::Refuse };
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("extract-all a out"), Ok(("", extract(None))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("extract-all a out --mode 0400"), Ok(("", extract(Some(0o400)))));
+        assert!(parse_crypt_command::<VerboseError<&str>>("extract-all a out --mode 1777").map_or(true, |(rest, _)| !rest.is_empty()));
+        assert!(parse_crypt_command::<VerboseError<&str>>("extract-all a out --mode 680").map_or(true, |(rest, _)| !rest.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_export_format() {
+        let export = |format, filepath| ReplCryptCommand::Export { alias: Cow::Borrowed("a"), format, filepath: Cow::Borrowed(filepath), prefix: None, tag: None };
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a out.json"), Ok(("", export(ExportFormat::Json, "out.json"))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a json out.json"), Ok(("", export(ExportFormat::Json, "out.json"))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a json"), Ok(("", export(ExportFormat::Json, "json"))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a json.txt"), Ok(("", export(ExportFormat::Json, "json.txt"))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a bundle out.crypt"), Ok(("", export(ExportFormat::Bundle, "out.crypt"))));
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("export a bundle"), Ok(("", export(ExportFormat::Json, "bundle"))));
+    }
+
+    #[test]
+    fn test_parse_import_csv() {
+        let import = |options| ReplCryptCommand::Import { alias: Cow::Borrowed("a"), format: ImportFormat::Csv(options), filepath: Cow::Borrowed("in.csv"), on_conflict: None };
+        assert_eq!(parse_crypt_command::<VerboseError<&str>>("import a csv in.csv"), Ok(("", import(CsvOptions::default()))));
+        assert_eq!(
+            parse_crypt_command::<VerboseError<&str>>("import a csv in.csv --value-column 'api key' --no-header"),
+            Ok(("", import(CsvOptions { value: CsvColumn::Name("api key".to_string()), header: false, ..CsvOptions::default() }))),
+        );
+        assert!(parse_crypt_command::<VerboseError<&str>>("import a csv in.csv --key-column 0").map_or(true, |(rest, _)| !rest.is_empty()));
     }
 }
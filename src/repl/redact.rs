@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use nom::error::{VerboseError, VerboseErrorKind};
+use regex::Regex;
+use serde::Deserialize;
+
+const REDACTED: &str = "<redacted>";
+
+/// Returns the byte offsets at which each whitespace separated token of `input` starts, treating
+/// single quoted strings (with `\` escapes) as one token.
+fn token_starts(input: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut in_token = false;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '\'' => {
+                if !in_token {
+                    starts.push(index);
+                    in_token = true;
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => in_token = false,
+            _ if !in_token => {
+                starts.push(index);
+                in_token = true;
+            }
+            _ => {}
+        }
+    }
+    starts
+}
+
+/// Returns the byte offset at which the secret part of a command line starts, if it has one.
+fn secret_offset(input: &str) -> Option<usize> {
+    let starts = token_starts(input);
+    let token = |index: usize| {
+        let start = *starts.get(index)?;
+        let end = starts.get(index + 1).copied().unwrap_or(input.len());
+        Some(input[start..end].trim_end())
+    };
+    // crypt data <alias> set <key> <value>
+    if token(0) == Some("crypt") && token(1) == Some("data") && token(3) == Some("set") {
+        return starts.get(5).copied();
+    }
+    // crypt data <alias> field set <key> <field> <value>
+    if token(0) == Some("crypt") && token(1) == Some("data") && token(3) == Some("field") && token(4) == Some("set") {
+        return starts.get(7).copied();
+    }
+    // let <name> <value>
+    if token(0) == Some("let") {
+        return starts.get(2).copied();
+    }
+    // if-set <name> then <command>
+    if token(0) == Some("if-set") && token(2) == Some("then") {
+        let start = *starts.get(3)?;
+        return secret_offset(&input[start..]).map(|offset| start + offset);
+    }
+    None
+}
+
+/// Returns `true` if `input` is a command line carrying a secret value, such as
+/// `crypt data <alias> set <key> <value>`.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::contains_secret;
+///
+/// assert!(contains_secret("crypt data alias set key 'hunter 2'"));
+/// assert!(contains_secret("crypt data alias field set key otp 'otpauth://totp/me'"));
+/// assert!(!contains_secret("crypt data alias get key"));
+/// ```
+///
+#[must_use]
+pub fn contains_secret(input: &str) -> bool {
+    secret_offset(input).is_some()
+}
+
+/// Patterns of command lines that are never kept in the prompt history, on top of the ones
+/// [`contains_secret`] finds, read from the `history_exclude` list of the config file.
+///
+/// # Example
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use crypt_client::repl::HistoryExclusions;
+///
+/// let exclusions = HistoryExclusions::try_from(vec!["^crypt unlock \\w+ .*prod".to_string()]).unwrap();
+/// assert!(exclusions.is_match("crypt unlock live ~/vaults/prod.crypt"));
+/// assert!(!exclusions.is_match("crypt unlock dev ~/vaults/dev.crypt"));
+/// assert!(HistoryExclusions::try_from(vec!["(".to_string()]).is_err());
+/// ```
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct HistoryExclusions {
+    patterns: Vec<Regex>,
+}
+
+impl HistoryExclusions {
+    /// Whether any of the patterns matches somewhere in `line`.
+    #[must_use]
+    pub fn is_match(&self, line: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(line))
+    }
+}
+
+impl TryFrom<Vec<String>> for HistoryExclusions {
+    type Error = String;
+
+    fn try_from(patterns: Vec<String>) -> Result<Self, Self::Error> {
+        let patterns = patterns.iter()
+            .map(|pattern| Regex::new(pattern).map_err(|error| format!("invalid history_exclude pattern '{}', {}", pattern, error)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+}
+
+/// Replaces any secret value in a command line with `<redacted>`, so the line can be shown in
+/// error messages or logs.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::redact_command;
+///
+/// assert_eq!(redact_command("crypt data alias set key 'hunter 2'"), "crypt data alias set key <redacted>");
+/// assert_eq!(redact_command("crypt data alias get key"), "crypt data alias get key");
+/// ```
+///
+#[must_use]
+pub fn redact_command(input: &str) -> Cow<'_, str> {
+    match secret_offset(input) {
+        Some(offset) => Cow::Owned(format!("{}{}", &input[..offset], REDACTED)),
+        None => Cow::Borrowed(input)
+    }
+}
+
+/// Describes the first argument in `rest` that was left over after parsing the command line
+/// `input`, naming it unless it comes after a secret value.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::describe_extra_argument;
+///
+/// assert_eq!(describe_extra_argument("crypt lock work --force", " --force"), "unexpected argument '--force' at column 17");
+/// assert_eq!(describe_extra_argument("crypt data work set key hunter 2", " 2"), "unexpected argument at column 32");
+/// ```
+///
+#[must_use]
+pub fn describe_extra_argument(input: &str, rest: &str) -> String {
+    let offset = input.len().saturating_sub(rest.trim_start().len());
+    let argument = rest.split_whitespace().next().unwrap_or_default();
+    if secret_offset(input).is_some_and(|start| offset >= start) {
+        format!("unexpected argument at column {}", offset + 1)
+    } else {
+        format!("unexpected argument '{}' at column {}", argument, offset + 1)
+    }
+}
+
+/// Describes why `input` failed to parse without echoing anything past the point of failure or
+/// any secret value before it.
+///
+/// # Example
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use crypt_client::repl::{ReplCommand, describe_parse_error};
+///
+/// let input = "crypt data alias sett key hunter2";
+/// let error = ReplCommand::try_from(input).unwrap_err();
+/// let message = describe_parse_error(input, &error);
+/// assert!(message.contains("expected crypt command"));
+/// assert!(!message.contains("hunter2"));
+/// ```
+///
+#[must_use]
+pub fn describe_parse_error(input: &str, error: &VerboseError<&str>) -> String {
+    let offset = error.errors.first()
+        .map_or(0, |(remaining, _)| input.len().saturating_sub(remaining.len()));
+    let contexts = error.errors.iter()
+        .filter_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some(*context),
+            _ => None
+        })
+        .collect::<Vec<_>>();
+    let expected = if contexts.is_empty() {
+        "a command".to_string()
+    } else {
+        contexts.join(" in ")
+    };
+    let shown = redact_command(&input[..offset]);
+    format!("Invalid command, expected {} at column {}:\n  {}\n  {}^", expected, offset + 1, shown, " ".repeat(shown.chars().count()))
+}
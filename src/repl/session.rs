@@ -0,0 +1,135 @@
+use std::fmt;
+use std::path::Path;
+use zeroize::Zeroizing;
+use crate::file::{Compaction, CryptData, CryptFileError, FileFormat, LockedCrypt, UnlockedCrypt};
+use crate::manifest::{Manifest, ManifestDiff, ManifestError};
+#[cfg(feature = "gpg")]
+use crate::gpg::GpgError;
+
+/// What a [`Repl`](crate::repl::Repl) keeps for each open file so the file can be locked again.
+///
+/// Secrets are wiped from memory when dropped. Other ways of re-locking a file, such as a cached
+/// derived key, belong here as new variants.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::repl::SessionSecret;
+///
+/// let secret = SessionSecret::password("hunter2".to_string());
+/// assert_eq!(format!("{:?}", secret), "Password(<redacted>)");
+/// ```
+///
+#[non_exhaustive]
+pub enum SessionSecret {
+    Password(Zeroizing<String>),
+    /// The file is a [`Container::Gpg`](crate::file::Container::Gpg) file, encrypted to public
+    /// keys, so nothing secret is held. gpg-agent holds the secret key, if anything does.
+    #[cfg(feature = "gpg")]
+    GpgAgent,
+}
+
+impl SessionSecret {
+    #[must_use]
+    pub fn password(password: String) -> Self {
+        Self::Password(Zeroizing::new(password))
+    }
+
+    /// Encrypts and writes `file` using this secret, see [`UnlockedCrypt::lock`].
+    #[allow(clippy::result_large_err)]
+    pub fn lock(&self, file: UnlockedCrypt) -> Result<LockedCrypt, (UnlockedCrypt, CryptFileError)> {
+        match self {
+            Self::Password(password) => file.lock(password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.lock("")
+        }
+    }
+
+    /// Encrypts and writes `file` using this secret without closing it, see
+    /// [`UnlockedCrypt::save`].
+    pub fn save(&self, file: &mut UnlockedCrypt) -> Result<(), CryptFileError> {
+        match self {
+            Self::Password(password) => file.save(password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.save("")
+        }
+    }
+
+    /// Changes the password of `file` to `new`, see [`UnlockedCrypt::change_password`], and keeps
+    /// `new` in place of this secret so the file is re-encrypted with it.
+    pub fn change_password(&mut self, file: &mut UnlockedCrypt, new: Zeroizing<String>) -> Result<(), CryptFileError> {
+        match self {
+            Self::Password(password) => file.change_password(password.as_str(), new.as_str())?,
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => return Err(GpgError::Unsupported("a password").into())
+        }
+        *self = Self::Password(new);
+        Ok(())
+    }
+
+    /// Replaces the data of `file` with a backup decrypted with this secret, see
+    /// [`UnlockedCrypt::restore_backup`].
+    pub fn restore_backup(&self, file: &mut UnlockedCrypt, backup: &Path) -> Result<(), CryptFileError> {
+        match self {
+            Self::Password(password) => file.restore_backup(backup, password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.restore_backup(backup, "")
+        }
+    }
+
+    /// Decrypts another copy of `file` with this secret, see [`UnlockedCrypt::decrypt_copy`].
+    pub fn decrypt_copy(&self, file: &UnlockedCrypt, path: &Path) -> Result<CryptData, CryptFileError> {
+        match self {
+            Self::Password(password) => file.decrypt_copy(path, password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.decrypt_copy(path, "")
+        }
+    }
+
+    /// Rewrites `file` with `format` using this secret, see [`UnlockedCrypt::migrate`].
+    pub fn migrate(&self, file: &mut UnlockedCrypt, format: FileFormat) -> Result<(), CryptFileError> {
+        match self {
+            Self::Password(password) => file.migrate(password.as_str(), format),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.migrate("", format)
+        }
+    }
+
+    /// Rewrites `file` using this secret and removes its earlier versions, see
+    /// [`UnlockedCrypt::compact`].
+    pub fn compact(&self, file: &mut UnlockedCrypt, shred: bool) -> Result<Compaction, CryptFileError> {
+        match self {
+            Self::Password(password) => file.compact(password.as_str(), shred),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => file.compact("", shred)
+        }
+    }
+
+    /// Creates a manifest of `data` keyed by this secret, see [`Manifest::create`].
+    pub fn manifest(&self, data: &CryptData) -> Result<Manifest, ManifestError> {
+        match self {
+            Self::Password(password) => Manifest::create(data, password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => Err(ManifestError::NoPassword)
+        }
+    }
+
+    /// Compares `data` against a manifest keyed by this secret, see [`Manifest::check`].
+    pub fn check_manifest(&self, manifest: &Manifest, data: &CryptData) -> Result<ManifestDiff, ManifestError> {
+        match self {
+            Self::Password(password) => manifest.check(data, password.as_str()),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => Err(ManifestError::NoPassword)
+        }
+    }
+}
+
+impl fmt::Debug for SessionSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Password(_) => f.write_str("Password(<redacted>)"),
+            #[cfg(feature = "gpg")]
+            Self::GpgAgent => f.write_str("GpgAgent")
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+use nom::error::VerboseError;
+use regex::Regex;
+use crate::file::{CryptData, SyncMode};
+use crate::repl::{parse_duration, AutosavePolicy};
+
+/// Settings stored inside a crypt with `crypt meta <alias> set-setting`, so they follow the file
+/// across machines. While the file is open they override the local
+/// [`Config`](crate::config::Config), which still applies to anything they leave unset.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use crypt_client::file::CryptData;
+/// use crypt_client::repl::{AutosavePolicy, FileSettings};
+///
+/// let mut data = CryptData::new();
+/// data.set_setting("autosave", Some("on-change".to_string()));
+/// data.set_setting("copy_on_get", Some("true".to_string()));
+/// data.set_setting("rotate_after", Some("90d".to_string()));
+///
+/// let settings = FileSettings::read(&data);
+/// assert_eq!(settings.autosave, Some(AutosavePolicy::OnChange));
+/// assert_eq!(settings.copy_on_get, Some(true));
+/// assert_eq!(settings.expiry_reminders, None);
+/// assert_eq!(settings.rotate_after, Some(Duration::from_secs(90 * 86_400)));
+/// assert!(FileSettings::validate("copy_on_get", "sometimes").is_err());
+/// assert!(FileSettings::validate("rotate_after", "90 days").is_err());
+/// assert!(FileSettings::validate("sync", "crdt").is_ok());
+/// ```
+///
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FileSettings {
+    pub autosave: Option<AutosavePolicy>,
+    pub copy_on_get: Option<bool>,
+    /// Whether unlocking the file warns about expired and expiring entries.
+    pub expiry_reminders: Option<bool>,
+    /// How long after the password was last changed unlocking the file reminds to change it.
+    pub rotate_after: Option<Duration>,
+    /// A regex every new key must match as a whole, see [`KeyPolicy`].
+    pub key_pattern: Option<String>,
+    /// How many `/`-separated levels new keys may have, see [`KeyPolicy`].
+    pub key_max_depth: Option<usize>,
+    /// How copies of the file edited on different machines are merged, see [`SyncMode`].
+    pub sync: Option<SyncMode>,
+    /// How many days deletions are kept for syncing, see
+    /// [`CryptData::tombstone_retention`].
+    pub tombstone_days: Option<u64>,
+}
+
+impl FileSettings {
+    /// The names of the settings a crypt can store.
+    pub const NAMES: &'static [&'static str] = &["autosave", "copy_on_get", "expiry_reminders", "rotate_after", "key_pattern", "key_max_depth", "sync", "tombstone_days"];
+
+    /// Reads the settings stored in `data`. Settings this version doesn't know, perhaps written
+    /// by a newer one, and invalid values are left to the local configuration.
+    #[must_use]
+    pub fn read(data: &CryptData) -> Self {
+        let mut settings = Self::default();
+        for (name, value) in data.settings() {
+            // Already checked by validate when the setting was stored.
+            let _ = settings.apply(name, value);
+        }
+        settings
+    }
+
+    /// The naming policy new keys must follow.
+    #[must_use]
+    pub fn key_policy(&self) -> KeyPolicy {
+        KeyPolicy {
+            // Already checked by validate when the setting was stored.
+            pattern: self.key_pattern.as_deref().and_then(|pattern| whole_key_regex(pattern).ok()),
+            max_depth: self.key_max_depth,
+        }
+    }
+
+    /// Checks that `value` is valid for the setting called `name`.
+    pub fn validate(name: &str, value: &str) -> Result<(), String> {
+        Self::default().apply(name, value)
+    }
+
+    fn apply(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "autosave" => self.autosave = Some(value.parse()?),
+            "copy_on_get" => self.copy_on_get = Some(parse_bool(name, value)?),
+            "expiry_reminders" => self.expiry_reminders = Some(parse_bool(name, value)?),
+            "rotate_after" => self.rotate_after = Some(match parse_duration::<VerboseError<&str>>(value) {
+                Ok(("", duration)) => duration,
+                _ => return Err(format!("invalid value '{}' for {}, expected a duration such as 90d", value, name))
+            }),
+            "key_pattern" => {
+                Regex::new(value).and_then(|_| whole_key_regex(value)).map_err(|error| format!("invalid value '{}' for {}, {}", value, name, error))?;
+                self.key_pattern = Some(value.to_string());
+            }
+            "key_max_depth" => self.key_max_depth = Some(value.parse().ok().filter(|depth| *depth > 0)
+                .ok_or_else(|| format!("invalid value '{}' for {}, expected a number of levels of at least 1", value, name))?),
+            "sync" => self.sync = Some(value.parse()?),
+            "tombstone_days" => self.tombstone_days = Some(value.parse()
+                .map_err(|_| format!("invalid value '{}' for {}, expected a number of days", value, name))?),
+            _ => return Err(format!("unknown setting '{}', expected one of {}", name, Self::NAMES.join(", ")))
+        }
+        Ok(())
+    }
+}
+
+/// The naming policy of a crypt, read from its settings with [`FileSettings::key_policy`], so
+/// shared crypts keep a consistent structure. Keys that exist when the policy is set are left as
+/// they are.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::file::CryptData;
+/// use crypt_client::repl::FileSettings;
+///
+/// let mut data = CryptData::new();
+/// data.set_setting("key_pattern", Some("[a-z0-9_/]+".to_string()));
+/// data.set_setting("key_max_depth", Some("2".to_string()));
+///
+/// let policy = FileSettings::read(&data).key_policy();
+/// assert!(policy.check("aws/access_key").is_ok());
+/// assert_eq!(policy.check("aws/Key").unwrap_err(), "aws/Key doesn't match the key pattern [a-z0-9_/]+");
+/// assert_eq!(policy.check("aws/prod/key").unwrap_err(), "aws/prod/key has 3 levels but keys may have at most 2");
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct KeyPolicy {
+    pattern: Option<Regex>,
+    max_depth: Option<usize>,
+}
+
+impl KeyPolicy {
+    /// Checks that `key` follows the policy, returning why it doesn't.
+    pub fn check(&self, key: &str) -> Result<(), String> {
+        if let Some(pattern) = self.pattern.as_ref().filter(|pattern| !pattern.is_match(key)) {
+            // Show the pattern as it was stored, without the anchors added to it.
+            let pattern = pattern.as_str().strip_prefix("^(?:").and_then(|pattern| pattern.strip_suffix(")$")).unwrap_or(pattern.as_str());
+            return Err(format!("{} doesn't match the key pattern {}", key, pattern));
+        }
+        let depth = key.split('/').count();
+        match self.max_depth {
+            Some(max_depth) if depth > max_depth => Err(format!("{} has {} levels but keys may have at most {}", key, depth, max_depth)),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Compiles `pattern` so it only matches whole keys.
+fn whole_key_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    value.parse().map_err(|_| format!("invalid value '{}' for {}, expected true or false", value, name))
+}
@@ -0,0 +1,40 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// Replaces every `${NAME}` in a command line with the value of the session variable `NAME`,
+/// set with `let`. Values are inserted as they are, so a value containing spaces must be quoted,
+/// e.g. `'${NAME}'`. On failure the name of the first unknown variable is returned.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use crypt_client::repl::expand_variables;
+///
+/// let mut variables = BTreeMap::new();
+/// variables.insert("vault".to_string(), "./work.crypt".to_string());
+///
+/// assert_eq!(expand_variables("crypt unlock work ${vault}", &variables).unwrap(), "crypt unlock work ./work.crypt");
+/// assert_eq!(expand_variables("crypt data work rename --pattern (.*) old-$1", &variables).unwrap(), "crypt data work rename --pattern (.*) old-$1");
+/// assert_eq!(expand_variables("crypt lock ${alias}", &variables).unwrap_err(), "alias");
+/// ```
+///
+pub fn expand_variables<'a>(line: &'a str, variables: &BTreeMap<String, String>) -> Result<Cow<'a, str>, String> {
+    if !line.contains("${") {
+        return Ok(Cow::Borrowed(line));
+    }
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let value = variables.get(name).ok_or_else(|| name.to_string())?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(value);
+        rest = &rest[start + 2 + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(Cow::Owned(expanded))
+}
@@ -0,0 +1,90 @@
+/// What happened to a single file or alias in an operation covering several of them.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    /// Succeeded, with a short description of what was done.
+    Succeeded(String),
+    /// Failed, with the reason.
+    Failed(String),
+}
+
+/// The per file or per alias outcomes of an operation such as saving every open file, in the
+/// order they were recorded.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::report::OutcomeReport;
+///
+/// let mut report = OutcomeReport::new();
+/// report.succeeded("work", "saved");
+/// report.failed("home", "Permission denied (os error 13)");
+///
+/// assert_eq!(report.failures(), 1);
+/// assert!(!report.is_success());
+/// assert_eq!(report.summary(), "1 succeeded, 1 failed");
+/// assert_eq!(report.rows()[1], vec!["home", "failed", "Permission denied (os error 13)"]);
+/// ```
+///
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OutcomeReport {
+    outcomes: Vec<(String, Outcome)>,
+}
+
+impl OutcomeReport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn succeeded(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.outcomes.push((name.into(), Outcome::Succeeded(detail.into())));
+    }
+
+    pub fn failed(&mut self, name: impl Into<String>, reason: impl Into<String>) {
+        self.outcomes.push((name.into(), Outcome::Failed(reason.into())));
+    }
+
+    /// Iterates over every outcome with the name of its file or alias.
+    pub fn outcomes(&self) -> impl Iterator<Item = (&str, &Outcome)> {
+        self.outcomes.iter().map(|(name, outcome)| (name.as_str(), outcome))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+
+    #[must_use]
+    pub fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|(_, outcome)| matches!(outcome, Outcome::Failed(_))).count()
+    }
+
+    /// Returns `true` if nothing failed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failures() == 0
+    }
+
+    /// A one line count of successes and failures.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        format!("{} succeeded, {} failed", self.len() - self.failures(), self.failures())
+    }
+
+    /// The outcomes as `[name, "ok" or "failed", detail]` rows, ready for
+    /// [`OutputStyle::table`](crate::repl::OutputStyle::table).
+    #[must_use]
+    pub fn rows(&self) -> Vec<Vec<String>> {
+        self.outcomes.iter()
+            .map(|(name, outcome)| match outcome {
+                Outcome::Succeeded(detail) => vec![name.clone(), "ok".to_string(), detail.clone()],
+                Outcome::Failed(reason) => vec![name.clone(), "failed".to_string(), reason.clone()]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+#[derive(Debug)]
+pub enum SecretSourceError {
+    Io(std::io::Error),
+    /// The environment variable is not set or isn't valid unicode.
+    MissingEnv(String),
+    /// The command exited unsuccessfully, with its exit status.
+    CommandFailed(String),
+    NotUtf8,
+}
+
+impl From<std::io::Error> for SecretSourceError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for SecretSourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::MissingEnv(name) => write!(f, "environment variable {} is not set", name),
+            Self::CommandFailed(status) => write!(f, "password command failed with {}", status),
+            Self::NotUtf8 => f.write_str("password is not valid UTF-8")
+        }
+    }
+}
+
+impl std::error::Error for SecretSourceError {}
+
+/// Somewhere the password of a crypt file can be fetched from instead of asking the user.
+///
+/// # Example
+///
+/// ```
+/// use zeroize::Zeroizing;
+/// use crypt_client::secret::{SecretSource, SecretSourceError};
+///
+/// struct Fixed(&'static str);
+///
+/// impl SecretSource for Fixed {
+///     fn fetch(&self) -> Result<Option<Zeroizing<String>>, SecretSourceError> {
+///         Ok(Some(Zeroizing::new(self.0.to_string())))
+///     }
+/// }
+///
+/// assert_eq!(Fixed("hunter2").fetch().unwrap().unwrap().as_str(), "hunter2");
+/// ```
+///
+pub trait SecretSource {
+    /// Returns the password, or [`None`] if the user should be asked for it.
+    fn fetch(&self) -> Result<Option<Zeroizing<String>>, SecretSourceError>;
+}
+
+/// The secret sources that can be set per file in the config file.
+///
+/// # Example
+///
+/// ```toml
+/// [secret_sources]
+/// "/home/me/work.crypt" = { env = "WORK_CRYPT_PASSWORD" }
+/// "/home/me/home.crypt" = { command = ["pass", "show", "crypt/home"] }
+/// "/srv/ci.crypt" = { file = "/run/secrets/ci-crypt" }
+/// "/home/me/other.crypt" = "prompt"
+/// ```
+///
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfiguredSecretSource {
+    /// Ask the user, the same as not configuring a source.
+    Prompt,
+    /// Read an environment variable.
+    Env(String),
+    /// Read the first line of a file.
+    File(PathBuf),
+    /// Run a program, e.g. `pass show` or `op read`, and use the first line it prints.
+    Command(Vec<String>),
+}
+
+/// Returns the first line of `s`, without its line ending.
+fn first_line(s: &str) -> Zeroizing<String> {
+    Zeroizing::new(s.lines().next().unwrap_or("").to_string())
+}
+
+impl SecretSource for ConfiguredSecretSource {
+    fn fetch(&self) -> Result<Option<Zeroizing<String>>, SecretSourceError> {
+        match self {
+            Self::Prompt => Ok(None),
+            Self::Env(name) => std::env::var(name)
+                .map(|password| Some(Zeroizing::new(password)))
+                .map_err(|_| SecretSourceError::MissingEnv(name.clone())),
+            Self::File(path) => {
+                let contents = Zeroizing::new(std::fs::read_to_string(path)?);
+                Ok(Some(first_line(contents.as_str())))
+            }
+            Self::Command(command) => {
+                let Some((program, args)) = command.split_first() else {
+                    return Err(SecretSourceError::CommandFailed("no program given".to_string()));
+                };
+                let output = Command::new(program).args(args).output()?;
+                if !output.status.success() {
+                    return Err(SecretSourceError::CommandFailed(output.status.to_string()));
+                }
+                let stdout = Zeroizing::new(output.stdout);
+                let stdout = std::str::from_utf8(stdout.as_slice()).map_err(|_| SecretSourceError::NotUtf8)?;
+                Ok(Some(first_line(stdout)))
+            }
+        }
+    }
+}
+
+/// Combines the passwords of two people into the password of a dual control file, which neither
+/// can unlock alone. The order the passwords are given in doesn't matter.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::secret::dual_control_password;
+///
+/// let combined = dual_control_password("alice's password", "bob's password");
+/// assert_eq!(combined, dual_control_password("bob's password", "alice's password"));
+/// assert_ne!(combined.as_str(), "alice's password");
+/// ```
+///
+#[must_use]
+pub fn dual_control_password(first: &str, second: &str) -> Zeroizing<String> {
+    let (first, second) = if first <= second { (first, second) } else { (second, first) };
+    Zeroizing::new(format!("dual-control:{}:{}{}", first.len(), first, second))
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+/// Bytes holding something secret, such as a key or decrypted data, which are wiped when the
+/// buffer is dropped. In builds with the `mlock` feature the buffer is also locked into RAM
+/// (`mlock` on Unix, `VirtualLock` on Windows), so it is never paged out to swap.
+///
+/// Locking is best effort: the operating system limits how much memory a process may lock, and
+/// locks cover whole pages, which a buffer may share with another that unlocks them first. Use
+/// [`is_locked`](Self::is_locked) to check. The buffer can't grow, so it is never reallocated
+/// out of the locked memory.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::secure::SecureBuffer;
+///
+/// let mut key = SecureBuffer::zeroed(32);
+/// key[0] = 1;
+/// assert_eq!(key.len(), 32);
+/// assert_eq!(format!("{:?}", key), "SecureBuffer(<32 bytes>)");
+/// if !cfg!(feature = "mlock") {
+///     assert!(!key.is_locked());
+/// }
+/// ```
+///
+pub struct SecureBuffer {
+    bytes: Vec<u8>,
+    #[cfg(feature = "mlock")]
+    lock: Option<region::LockGuard>,
+}
+
+impl SecureBuffer {
+    /// A buffer of `len` zeros, locked before anything secret is written to it.
+    #[must_use]
+    pub fn zeroed(len: usize) -> Self {
+        Self::from(vec![0; len])
+    }
+
+    /// Returns `true` if the buffer is locked into RAM.
+    #[must_use]
+    pub fn is_locked(&self) -> bool {
+        #[cfg(feature = "mlock")]
+        return self.lock.is_some();
+        #[cfg(not(feature = "mlock"))]
+        return false;
+    }
+}
+
+/// Takes ownership of `bytes` without copying them, locking them if possible. Secret bytes
+/// that existed before they were moved into a buffer may already have been swapped out.
+impl From<Vec<u8>> for SecureBuffer {
+    fn from(bytes: Vec<u8>) -> Self {
+        #[cfg(feature = "mlock")]
+        {
+            // An empty vector has no memory to lock.
+            let lock = if bytes.is_empty() { None } else { region::lock(bytes.as_ptr(), bytes.len()).ok() };
+            Self { bytes, lock }
+        }
+        #[cfg(not(feature = "mlock"))]
+        Self { bytes }
+    }
+}
+
+impl Clone for SecureBuffer {
+    fn clone(&self) -> Self {
+        let mut clone = Self::zeroed(self.len());
+        clone.copy_from_slice(self);
+        clone
+    }
+}
+
+impl Deref for SecureBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for SecureBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl Drop for SecureBuffer {
+    fn drop(&mut self) {
+        // Wipe the bytes while they are still locked, and unlock them before they are freed.
+        self.bytes.zeroize();
+        #[cfg(feature = "mlock")]
+        drop(self.lock.take());
+    }
+}
+
+impl fmt::Debug for SecureBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecureBuffer(<{} bytes>)", self.bytes.len())
+    }
+}
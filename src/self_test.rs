@@ -0,0 +1,184 @@
+use std::fmt::Write;
+use std::path::Path;
+use rand::Rng;
+use crate::file::{recover_key, Cipher, CipherKind, Compression, CryptFile, Kdf, KdfParams, Layout};
+use crate::report::OutcomeReport;
+
+const PASSWORD: &str = "password";
+const PLAINTEXT: &str = "The quick brown fox jumps over the lazy dog";
+const AAD: &[u8] = b"crypt-client self-test";
+
+/// The expected ciphertext and tag of [`PLAINTEXT`] under the key `00 01 .. 1f` and nonce
+/// `00 01 .. 0b`, with [`AAD`], computed with an independent implementation.
+const CIPHER_ANSWERS: &[(CipherKind, &str)] = &[
+    (CipherKind::Aes256Gcm, "136ab33bb490ab78e661f5f9de9e164de5b9ff149a0e320c4b478af3781b20c669758e90cebb6bb810cb18310a8b4aeab9c785b5489716345c8c5e"),
+    (CipherKind::ChaCha20Poly1305, "dd936d205862cc23dca35d81f76a6043af1fcac73b01c0c995b740b310b2864884e50c9f8764c8b8535d11620c0e5565c4c9fdbf1cdd5fff5ad0fc"),
+];
+
+/// Argon2id parameters cheap enough to check at every start.
+const ARGON2ID: KdfParams = KdfParams { memory_kib: 64, iterations: 2, parallelism: 2 };
+/// The expected 32 byte key derived from [`PASSWORD`] with the salt `00 01 .. 0f` and the secret
+/// `64 65 .. 83`.
+const ARGON2ID_ANSWER: &str = "069f2560bf6a8b77beee2d133a4f1110c3930dc740f403cfc66ae2f82f44046b";
+
+#[cfg(feature = "scrypt")]
+const SCRYPT: crate::file::ScryptParams = crate::file::ScryptParams { log_n: 10, r: 8, p: 1 };
+#[cfg(feature = "scrypt")]
+const SCRYPT_ANSWER: &str = "76cca35c077d30171272b9d69a242f159a5ab97c5a2bd46d89f1c569de76fff2";
+
+/// How thoroughly [`run`] checks the build.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SelfTest {
+    /// Known-answer tests of every cipher and key derivation function, fast enough to run at
+    /// every start.
+    Quick,
+    /// The known-answer tests, then writing a temporary file with every combination of cipher,
+    /// key derivation function, compression and layout in the build and unlocking it again.
+    Full,
+}
+
+/// Checks that the crypto in this build gives the answers it should, so a miscompiled
+/// dependency or a broken feature combination is caught before any file is written with it.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::self_test::{run, SelfTest};
+///
+/// let report = run(SelfTest::Quick);
+/// assert!(report.is_success(), "{:?}", report);
+/// assert!(report.outcomes().any(|(name, _)| name == "ChaCha20-Poly1305"));
+///
+/// let report = run(SelfTest::Full);
+/// assert!(report.is_success(), "{:?}", report);
+/// assert!(report.rows().contains(&vec![
+///     "AES-256-GCM".to_string(),
+///     "ok".to_string(),
+///     "Argon2id, none compression, file written and unlocked".to_string(),
+/// ]));
+/// ```
+///
+#[must_use]
+pub fn run(depth: SelfTest) -> OutcomeReport {
+    let mut report = OutcomeReport::new();
+    for (cipher, answer) in CIPHER_ANSWERS {
+        match check_cipher(*cipher, answer) {
+            Ok(()) => report.succeeded(cipher.name(), "known answer"),
+            Err(reason) => report.failed(cipher.name(), reason)
+        }
+    }
+    for (kdf, answer) in kdf_answers() {
+        match check_kdf(&kdf, answer) {
+            Ok(()) => report.succeeded(kdf_name(&kdf), "known answer"),
+            Err(reason) => report.failed(kdf_name(&kdf), reason)
+        }
+    }
+    if depth == SelfTest::Full {
+        for (cipher, _) in CIPHER_ANSWERS {
+            for (kdf, _) in kdf_answers() {
+                for (compression, layout) in compressions().into_iter().flat_map(|compression| [(compression, Layout::Single), (compression, Layout::Chunked)]) {
+                    let chunked = if layout == Layout::Chunked { "chunked " } else { "" };
+                    let file = format!("{}, {} compression, {}file", kdf_name(&kdf), compression, chunked);
+                    match round_trip(*cipher, kdf, compression, layout) {
+                        Ok(()) => report.succeeded(cipher.name(), format!("{} written and unlocked", file)),
+                        Err(reason) => report.failed(cipher.name(), format!("{} {}", file, reason))
+                    }
+                }
+            }
+        }
+    }
+    report
+}
+
+fn kdf_answers() -> Vec<(Kdf, &'static str)> {
+    #[cfg_attr(not(feature = "scrypt"), allow(unused_mut))]
+    let mut answers = vec![(Kdf::Argon2id(ARGON2ID), ARGON2ID_ANSWER)];
+    #[cfg(feature = "scrypt")]
+    answers.push((Kdf::Scrypt(SCRYPT), SCRYPT_ANSWER));
+    answers
+}
+
+fn kdf_name(kdf: &Kdf) -> &'static str {
+    match kdf {
+        Kdf::Argon2id(_) => "Argon2id",
+        #[cfg(feature = "scrypt")]
+        Kdf::Scrypt(_) => "scrypt",
+    }
+}
+
+fn compressions() -> Vec<Compression> {
+    #[cfg_attr(not(feature = "zstd"), allow(unused_mut))]
+    let mut compressions = vec![Compression::None];
+    #[cfg(feature = "zstd")]
+    compressions.push(Compression::Zstd);
+    compressions
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// The bytes `start`, `start + 1` and so on, `len` of them.
+#[allow(clippy::cast_possible_truncation)]
+fn counting(start: u8, len: usize) -> Vec<u8> {
+    (0..len).map(|index| start.wrapping_add(index as u8)).collect()
+}
+
+fn check_cipher(cipher: CipherKind, answer: &str) -> Result<(), String> {
+    let key = counting(0, cipher.key_size());
+    let nonce = counting(0, cipher.nonce_size());
+    let mut ciphertext = cipher.encrypt(&key, &nonce, AAD, PLAINTEXT.as_bytes()).map_err(|error| error.to_string())?;
+    if hex(&ciphertext) != answer {
+        return Err("encryption gave the wrong answer".to_string());
+    }
+    if cipher.decrypt(&key, &nonce, AAD, &ciphertext).ok().as_deref() != Some(PLAINTEXT.as_bytes()) {
+        return Err("decryption gave the wrong answer".to_string());
+    }
+    ciphertext[0] ^= 1;
+    if cipher.decrypt(&key, &nonce, AAD, &ciphertext).is_ok() {
+        return Err("a modified ciphertext was accepted".to_string());
+    }
+    Ok(())
+}
+
+fn check_kdf(kdf: &Kdf, answer: &str) -> Result<(), String> {
+    let key = recover_key(PASSWORD, &counting(0, 16), &counting(100, 32), 32, kdf).map_err(|error| error.to_string())?;
+    if hex(&key) == answer {
+        Ok(())
+    } else {
+        Err("key derivation gave the wrong answer".to_string())
+    }
+}
+
+/// Writes a temporary file with `cipher`, `kdf`, `compression` and `layout`, then unlocks it
+/// again with the right and a wrong password. The file is removed whatever happens.
+fn round_trip(cipher: CipherKind, kdf: Kdf, compression: Compression, layout: Layout) -> Result<(), String> {
+    let filepath = std::env::temp_dir().join(format!("crypt-client-self-test-{}-{:016x}.crypt", std::process::id(), rand::thread_rng().gen::<u64>()));
+    let result = write_and_unlock(&filepath, cipher, kdf, compression, layout);
+    let _ = std::fs::remove_file(&filepath);
+    result
+}
+
+fn write_and_unlock(filepath: &Path, cipher: CipherKind, kdf: Kdf, compression: Compression, layout: Layout) -> Result<(), String> {
+    let mut file = CryptFile::new(filepath.to_path_buf()).unlock(PASSWORD).map_err(|error| error.to_string())?;
+    file.set_cipher(cipher);
+    file.set_kdf(kdf);
+    file.set_compression(compression);
+    file.set_layout(layout);
+    file.data_mut().insert("self-test", PLAINTEXT);
+    let file = file.lock(PASSWORD).map_err(|(_, error)| error.to_string())?;
+    let file = file.unlock(PASSWORD).map_err(|error| error.to_string())?;
+    if file.data().get("self-test") != Some(PLAINTEXT) {
+        return Err("the file was unlocked with the wrong contents".to_string());
+    }
+    if file.cipher().name() != cipher.name() || file.kdf() != kdf || file.compression() != compression || file.layout() != layout {
+        return Err("the file was unlocked with the wrong header".to_string());
+    }
+    if CryptFile::new(filepath.to_path_buf()).unlock("not the password").is_ok() {
+        return Err("the file was unlocked with a wrong password".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+use crate::file::{Cipher, CipherKind};
+
+/// Starts every sealed share, and is authenticated along with it.
+const PREFIX: &str = "crypt-share-v1:";
+const CIPHER: CipherKind = CipherKind::Aes256Gcm;
+
+#[derive(Debug)]
+pub enum ShareError {
+    /// The text isn't a sealed share.
+    InvalidFormat,
+    /// The one-time key isn't a key at all, as opposed to the wrong one.
+    InvalidKey,
+    /// The entry couldn't be encrypted.
+    Encrypt,
+    /// The one-time key is wrong or the share was modified.
+    Decrypt,
+    /// The share expired at the given time.
+    Expired(SystemTime),
+    Json(serde_json::Error),
+}
+
+impl From<serde_json::Error> for ShareError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => f.write_str("not a shared entry"),
+            Self::InvalidKey => f.write_str("the one-time key is malformed"),
+            Self::Encrypt => f.write_str("the entry could not be encrypted"),
+            Self::Decrypt => f.write_str("the one-time key is wrong or the share was modified"),
+            Self::Expired(at) => write!(f, "the share expired at {}", crate::timestamp::format_utc(*at)),
+            Self::Json(_) => f.write_str("the shared entry could not be read")
+        }
+    }
+}
+
+impl std::error::Error for ShareError {}
+
+/// A single entry handed from one vault to another, see [`seal`]. The value is wiped when dropped.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SharedEntry {
+    pub key: String,
+    pub value: String,
+    /// Seconds since the Unix epoch after which the share can no longer be opened.
+    expires: u64,
+}
+
+impl SharedEntry {
+    #[must_use]
+    pub fn expires(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.expires)
+    }
+}
+
+impl Drop for SharedEntry {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl fmt::Debug for SharedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedEntry")
+            .field("key", &self.key)
+            .field("value", &"<redacted>")
+            .field("expires", &self.expires())
+            .finish()
+    }
+}
+
+/// Encrypts `key` and `value` with a random one-time key, returning the sealed share, which is
+/// safe to paste or store anywhere, and the one-time key, which must reach the recipient some
+/// other way. [`open`] refuses the share once `expires` has passed.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use crypt_client::share::{open, seal, ShareError};
+///
+/// let now = SystemTime::now();
+/// let (sealed, one_time_key) = seal("db/password", "hunter2", now + Duration::from_secs(3600)).unwrap();
+/// assert!(!sealed.contains("hunter2"));
+///
+/// let entry = open(&sealed, &one_time_key, now).unwrap();
+/// assert_eq!(entry.key, "db/password");
+/// assert_eq!(entry.value.as_str(), "hunter2");
+///
+/// let (_, other_key) = seal("db/password", "hunter2", now).unwrap();
+/// assert!(matches!(open(&sealed, &other_key, now), Err(ShareError::Decrypt)));
+/// assert!(matches!(open(&sealed, &one_time_key, now + Duration::from_secs(7200)), Err(ShareError::Expired(_))));
+/// ```
+///
+pub fn seal(key: &str, value: &str, expires: SystemTime) -> Result<(String, Zeroizing<String>), ShareError> {
+    let expires = expires.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let entry = SharedEntry { key: key.to_string(), value: value.to_string(), expires };
+    let plaintext = Zeroizing::new(serde_json::to_vec(&entry)?);
+    let mut rng = rand::thread_rng();
+    let mut one_time_key = Zeroizing::new(vec![0_u8; CIPHER.key_size()]);
+    rng.fill(one_time_key.as_mut_slice());
+    let mut sealed = vec![0_u8; CIPHER.nonce_size()];
+    rng.fill(sealed.as_mut_slice());
+    let ciphertext = CIPHER.encrypt(&one_time_key, &sealed, PREFIX.as_bytes(), &plaintext)
+        .map_err(|_| ShareError::Encrypt)?;
+    sealed.extend_from_slice(&ciphertext);
+    let encoded_key = Zeroizing::new(base64::encode_config(one_time_key.as_slice(), base64::URL_SAFE_NO_PAD));
+    Ok((format!("{}{}", PREFIX, base64::encode(&sealed)), encoded_key))
+}
+
+/// Decrypts a share created by [`seal`] with its one-time key, failing if it expired before
+/// `now`.
+pub fn open(sealed: &str, one_time_key: &str, now: SystemTime) -> Result<SharedEntry, ShareError> {
+    let sealed = sealed.trim().strip_prefix(PREFIX).ok_or(ShareError::InvalidFormat)?;
+    let sealed = base64::decode(sealed).map_err(|_| ShareError::InvalidFormat)?;
+    if sealed.len() < CIPHER.nonce_size() {
+        return Err(ShareError::InvalidFormat);
+    }
+    let one_time_key = Zeroizing::new(base64::decode_config(one_time_key.trim(), base64::URL_SAFE_NO_PAD).map_err(|_| ShareError::InvalidKey)?);
+    if one_time_key.len() != CIPHER.key_size() {
+        return Err(ShareError::InvalidKey);
+    }
+    let (nonce, ciphertext) = sealed.split_at(CIPHER.nonce_size());
+    let plaintext = Zeroizing::new(CIPHER.decrypt(&one_time_key, nonce, PREFIX.as_bytes(), ciphertext).map_err(|_| ShareError::Decrypt)?);
+    let entry: SharedEntry = serde_json::from_slice(&plaintext)?;
+    if now > entry.expires() {
+        return Err(ShareError::Expired(entry.expires()));
+    }
+    Ok(entry)
+}
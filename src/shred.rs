@@ -0,0 +1,43 @@
+use std::convert::TryFrom;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use rand::RngCore;
+
+/// How much is overwritten at a time.
+const BLOCK_LEN: usize = 64 * 1024;
+
+/// Overwrites the file at `path` with random bytes, flushes it to disk and then removes it.
+///
+/// This only helps where writing to a file overwrites the blocks it was stored in. SSDs, flash
+/// cards, copy-on-write filesystems such as btrfs and ZFS, journaling with data journaling on,
+/// snapshots and backups of the disk all keep old copies out of reach of the file, so the old
+/// contents may survive anyway. On those, only full-disk encryption, or destroying the key of an
+/// encrypted volume, reliably gets rid of old data.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::shred::shred_file;
+///
+/// let path = std::env::temp_dir().join(format!("crypt-client-shred-doc-{}", std::process::id()));
+/// std::fs::write(&path, "old secret").unwrap();
+/// shred_file(&path).unwrap();
+/// assert!(!path.exists());
+/// ```
+///
+pub fn shred_file(path: &Path) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut block = vec![0_u8; BLOCK_LEN];
+    let mut rng = rand::thread_rng();
+    while remaining > 0 {
+        let len = usize::try_from(remaining).map_or(BLOCK_LEN, |remaining| remaining.min(BLOCK_LEN));
+        rng.fill_bytes(&mut block[..len]);
+        file.write_all(&block[..len])?;
+        remaining -= len as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::remove_file(path)
+}
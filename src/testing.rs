@@ -0,0 +1,137 @@
+//! Fixtures shared by the unit tests.
+
+#[cfg(feature = "repl")]
+use std::collections::VecDeque;
+#[cfg(feature = "repl")]
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "repl")]
+use std::time::Duration;
+use crate::file::{Cipher, CipherError, CipherKind, KdfParams};
+#[cfg(feature = "repl")]
+use crate::repl::{ReplDriver, ReplError};
+
+/// Cheap Argon2id parameters, so the tests don't spend most of their time deriving keys.
+pub const FAST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+/// ChaCha20-Poly1305 under another name, standing in for a custom [`Cipher`].
+#[derive(Debug)]
+pub struct Renamed;
+
+impl Cipher for Renamed {
+    fn magic(&self) -> &[u8] {
+        b"RENAMED1"
+    }
+
+    fn name(&self) -> &'static str {
+        "Renamed"
+    }
+
+    fn key_size(&self) -> usize {
+        32
+    }
+
+    fn nonce_size(&self) -> usize {
+        12
+    }
+
+    fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CipherError> {
+        CipherKind::ChaCha20Poly1305.encrypt(key, nonce, aad, plaintext)
+    }
+
+    fn decrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CipherError> {
+        CipherKind::ChaCha20Poly1305.decrypt(key, nonce, aad, ciphertext)
+    }
+}
+
+/// A directory of its own for a test, removed when dropped, so a failing test doesn't leave files
+/// behind either. Tests run in parallel, so every test names its own.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("crypt-client-{}-{}", name, std::process::id()));
+        // Left over from a run that was killed before it could clean up.
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A [`ReplDriver`] that gives scripted answers to prompts, in order, and keeps what was printed
+/// and reported. Running out of answers fails the prompt, like closing stdin would. Waiting for a
+/// key returns straight away, as if one was pressed.
+#[cfg(feature = "repl")]
+#[derive(Debug, Default)]
+pub struct ScriptedDriver {
+    pub answers: VecDeque<String>,
+    pub output: String,
+    pub errors: Vec<String>,
+    /// Stand in for a batch run, where no one can be asked.
+    pub batch: bool,
+}
+
+#[cfg(feature = "repl")]
+impl ScriptedDriver {
+    pub fn new(answers: &[&str]) -> Self {
+        Self { answers: answers.iter().map(ToString::to_string).collect(), ..Self::default() }
+    }
+}
+
+#[cfg(feature = "repl")]
+impl ReplDriver for ScriptedDriver {
+    type Error = ();
+
+    fn print<T: fmt::Display>(&mut self, s: T) {
+        self.output.push_str(&s.to_string());
+    }
+
+    fn eprint<T: fmt::Display>(&mut self, s: T) {
+        self.output.push_str(&s.to_string());
+    }
+
+    fn clear_screen(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn prompt_line(&mut self, _prompt: &str) -> Result<String, Self::Error> {
+        self.answers.pop_front().ok_or(())
+    }
+
+    fn prompt_password(&mut self, _prompt: &str) -> Result<String, Self::Error> {
+        self.answers.pop_front().ok_or(())
+    }
+
+    fn report_error(&mut self, error: &ReplError) {
+        self.errors.push(error.to_string());
+    }
+
+    fn is_interactive(&self) -> bool {
+        !self.batch
+    }
+
+    fn wait_for_key(&mut self, _timeout: Duration) -> bool {
+        true
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Formats `time` as an RFC 3339 UTC timestamp with second precision.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use crypt_client::timestamp::format_utc;
+///
+/// assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+/// assert_eq!(format_utc(UNIX_EPOCH + Duration::from_secs(1_700_000_000)), "2023-11-14T22:13:20Z");
+/// ```
+///
+#[must_use]
+pub fn format_utc(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let seconds_of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, seconds_of_day / 3600, seconds_of_day % 3600 / 60, seconds_of_day % 60
+    )
+}
+
+/// Parses a `YYYY-MM-DD` date as midnight UTC. Returns [`None`] if the date is invalid or before
+/// 1970.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use crypt_client::timestamp::parse_utc_date;
+///
+/// assert_eq!(parse_utc_date("2023-11-14"), Some(UNIX_EPOCH + Duration::from_secs(1_699_920_000)));
+/// assert_eq!(parse_utc_date("2023-02-29"), None);
+/// assert_eq!(parse_utc_date("14/11/2023"), None);
+/// ```
+///
+#[must_use]
+pub fn parse_utc_date(s: &str) -> Option<SystemTime> {
+    let mut parts = s.splitn(3, '-');
+    let mut next = |len: usize| parts.next().filter(|part| part.len() == len && part.bytes().all(|b| b.is_ascii_digit()))?.parse::<u64>().ok();
+    let (year, month, day) = (next(4)?, next(2)?, next(2)?);
+    if year < 1970 || !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // Days past the end of the month roll over into the next one, so check the round trip.
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400))
+}
+
+/// Converts a (year, month, day) date from 1970 onwards to days since the Unix epoch, the inverse
+/// of [`civil_from_days`].
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date, using Howard Hinnant's
+/// `civil_from_days` algorithm.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use crate::file::{CryptFile, CryptFileError, EncryptError, FileInfo};
+use crate::report::OutcomeReport;
+
+/// The stages a file is checked in by [`verify_file`], in order.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VerifyStage {
+    /// Reading the file from disk.
+    Read,
+    /// Reading the format version, cipher and key derivation parameters.
+    Header,
+    /// Deriving the key and checking the authentication tag, which fails if the password is
+    /// wrong or the file was modified.
+    Authenticate,
+    /// Decompressing and deserializing the decrypted entries.
+    Decode,
+}
+
+impl VerifyStage {
+    pub const ALL: [Self; 4] = [Self::Read, Self::Header, Self::Authenticate, Self::Decode];
+
+    /// The stage `error` is returned from when unlocking a file.
+    fn of(error: &CryptFileError) -> Self {
+        match error {
+            CryptFileError::Io(_) => Self::Read,
+            CryptFileError::WrongPassword | CryptFileError::Damaged(_) => Self::Authenticate,
+            CryptFileError::Bincode(_) | CryptFileError::Json(_) | CryptFileError::Encrypt(EncryptError::Compression(_)) => Self::Decode,
+            _ => Self::Header
+        }
+    }
+}
+
+impl fmt::Display for VerifyStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Header => "header",
+            Self::Authenticate => "authenticate",
+            Self::Decode => "decode"
+        })
+    }
+}
+
+/// Why [`verify_file`] failed: the first stage that failed and its error.
+#[derive(Debug)]
+pub struct VerifyError {
+    pub stage: VerifyStage,
+    pub error: CryptFileError,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} failed, {}", self.stage, self.error)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// What [`verify_file`] found in a file that passed every stage.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerifiedFile {
+    pub info: FileInfo,
+    /// The number of entries in the file.
+    pub entries: usize,
+}
+
+/// Returns every file under `dir`, recursively and sorted by path. Hidden files and directories,
+/// whose names start with `.`, are skipped.
+pub fn find_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Checks that each file can be decrypted and read with `password`, using up to `threads`
+/// threads. Outcomes are named by file path, in the same order as `filepaths`.
+///
+/// # Example
+///
+/// ```
+/// use crypt_client::verify::verify_files;
+///
+/// let report = verify_files(&["./does-not-exist.crypt".into()], "hunter2", 4);
+/// assert_eq!(report.summary(), "0 succeeded, 1 failed");
+/// ```
+///
+#[must_use]
+pub fn verify_files(filepaths: &[PathBuf], password: &str, threads: usize) -> OutcomeReport {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(filepaths.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, filepaths.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(filepath) = filepaths.get(index) else {
+                        break;
+                    };
+                    let result = verify_file(filepath, password);
+                    results.lock().unwrap_or_else(PoisonError::into_inner).push((index, result));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_by_key(|(index, _)| *index);
+    let mut report = OutcomeReport::new();
+    for (index, result) in results {
+        let name = filepaths[index].display().to_string();
+        match result {
+            Ok(_) => report.succeeded(name, "verified"),
+            Err(error) => report.failed(name, error.to_string())
+        }
+    }
+    report
+}
+
+/// Checks that the file at `filepath` can be read, has a valid header, is authenticated by
+/// `password` and decodes to entries, stopping at the first [`VerifyStage`] that fails. The
+/// decrypted entries are only counted, then dropped.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use crypt_client::verify::{verify_file, VerifyStage};
+///
+/// let error = verify_file(Path::new("./does-not-exist.crypt"), "hunter2").unwrap_err();
+/// assert_eq!(error.stage, VerifyStage::Read);
+/// ```
+///
+pub fn verify_file(filepath: &Path, password: &str) -> Result<VerifiedFile, VerifyError> {
+    let fail = |stage, error| VerifyError { stage, error };
+    // Unlocking a missing file would create an empty crypt rather than fail.
+    if !filepath.is_file() {
+        return Err(fail(VerifyStage::Read, CryptFileError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "file not found"))));
+    }
+    let file = CryptFile::new(filepath.to_path_buf());
+    let info = file.inspect().map_err(|error| fail(VerifyStage::of(&error), error))?;
+    let unlocked = file.unlock(password).map_err(|error| fail(VerifyStage::of(&error), error))?;
+    Ok(VerifiedFile { info, entries: unlocked.data().len() })
+}